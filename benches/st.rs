@@ -0,0 +1,146 @@
+//! Compare algorithms written in Structured Text with the same algorithms in native Rust.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::sync::Arc;
+use toref::runtime::basic::{
+    Action, Algorithm, BasicFunctionBlock, BasicType, BasicTypeBuilder, ExecutionError, Guard,
+    Variables,
+};
+use toref::runtime::fb::{EventContext, FunctionBlock};
+use toref::runtime::value::{IecType, IecValue};
+use toref::st::{StAlgorithm, StCondition};
+
+fn uint(vars: &Variables, name: &str) -> Result<u16, ExecutionError> {
+    match vars.get(name)? {
+        IecValue::Uint(value) => Ok(*value),
+        _ => Err(ExecutionError::Failed(format!("{name} is not a UINT"))),
+    }
+}
+
+fn counter_interface() -> BasicTypeBuilder {
+    BasicType::builder("E_CTU")
+        .event_input("CU", &["PV"])
+        .event_input("R", &[])
+        .event_output("CUO", &["Q", "CV"])
+        .event_output("RO", &["Q", "CV"])
+        .input("PV", IecType::Uint)
+        .output("Q", IecType::Bool)
+        .output("CV", IecType::Uint)
+}
+
+fn counter_ecc(builder: BasicTypeBuilder, below: Guard) -> BasicFunctionBlock {
+    let r#type = builder
+        .state("START", [])
+        .state("CU", [Action::algorithm("CU").output("CUO")])
+        .state("R", [Action::algorithm("R").output("RO")])
+        .transition("START", "CU", below)
+        .transition("START", "R", Guard::event("R"))
+        .transition("CU", "START", Guard::always())
+        .transition("R", "START", Guard::always())
+        .build()
+        .unwrap();
+
+    let fb = BasicFunctionBlock::new(Arc::new(r#type));
+    fb.get_data_input("PV")
+        .unwrap()
+        .set(IecValue::Uint(u16::MAX));
+    fb
+}
+
+fn native_counter() -> BasicFunctionBlock {
+    let builder = counter_interface()
+        .algorithm("CU", |vars: &mut Variables| {
+            let cv = uint(vars, "CV")? + 1;
+            vars.set("CV", IecValue::Uint(cv))?;
+            vars.set("Q", IecValue::Bool(cv >= uint(vars, "PV")?))
+        })
+        .algorithm("R", |vars: &mut Variables| {
+            vars.set("CV", IecValue::Uint(0))?;
+            vars.set("Q", IecValue::Bool(false))
+        });
+    let below = Guard::event("CU").when(|vars: &Variables| Ok(uint(vars, "CV")? < 65535));
+    counter_ecc(builder, below)
+}
+
+fn st_counter() -> BasicFunctionBlock {
+    let builder = counter_interface();
+    let count = StAlgorithm::new("CV := CV + 1; Q := CV >= PV;", builder.variables()).unwrap();
+    let reset = StAlgorithm::new("CV := 0; Q := FALSE;", builder.variables()).unwrap();
+    let below = StCondition::new("CV < 65535", builder.variables()).unwrap();
+    counter_ecc(
+        builder
+            .algorithm_impl("CU", Arc::new(count))
+            .algorithm_impl("R", Arc::new(reset)),
+        Guard::event("CU").with_condition(Arc::new(below)),
+    )
+}
+
+fn counter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("E_CTU");
+    for (name, mut fb) in [("native", native_counter()), ("st", st_counter())] {
+        let mut context = EventContext::default();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                fb.receive_event(black_box("CU"), &mut context);
+                fb.receive_event(black_box("R"), &mut context);
+                context.take_fired()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn average_interface() -> BasicTypeBuilder {
+    BasicType::builder("AVERAGE")
+        .event_input("REQ", &["N"])
+        .event_output("CNF", &["OUT"])
+        .input("N", IecType::Dint)
+        .output("OUT", IecType::Lreal)
+}
+
+fn average(c: &mut Criterion) {
+    let builder = average_interface();
+    let st = StAlgorithm::new(
+        r#"
+        VAR_TEMP i : DINT; sum : LREAL; END_VAR
+        FOR i := 1 TO N DO
+            IF i MOD 3 = 0 THEN sum := sum + i * 0.5; ELSE sum := sum + i; END_IF;
+        END_FOR;
+        OUT := sum / N;
+        "#,
+        builder.variables(),
+    )
+    .unwrap();
+    let native = |vars: &mut Variables| {
+        let IecValue::Dint(n) = *vars.get("N")? else {
+            return Err(ExecutionError::Failed("N is not a DINT".to_string()));
+        };
+        let mut sum = 0.0;
+        for i in 1..=n {
+            sum += if i % 3 == 0 { i as f64 * 0.5 } else { i as f64 };
+        }
+        vars.set("OUT", IecValue::Lreal(sum / n as f64))
+    };
+
+    let layout = builder
+        .algorithm("REQ", native)
+        .state("START", [])
+        .build()
+        .unwrap()
+        .layout()
+        .clone();
+    let mut variables = Variables::new(layout);
+    variables.set("N", IecValue::Dint(100)).unwrap();
+
+    let mut group = c.benchmark_group("AVERAGE");
+    group.bench_function("native", |b| {
+        b.iter(|| native.execute(black_box(&mut variables)).unwrap())
+    });
+    group.bench_function("st", |b| {
+        b.iter(|| st.execute(black_box(&mut variables)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, counter, average);
+criterion_main!(benches);
@@ -0,0 +1,444 @@
+//! The standard functions of IEC 61131-3 as function blocks, like `F_ADD` or `F_INT_TO_REAL`.
+//!
+//! Each block calls its function on `REQ`, with the values of its data inputs, and fires `CNF`
+//! with the result in `OUT`. Inputs accepting a class of types are generic ports, their type is
+//! resolved when they get connected.
+
+use crate::protocol::server::Error;
+use crate::runtime::fb::{
+    DataInput, DataOutput, EventContext, EventInput, EventOutput, FunctionBlock, GenericType,
+};
+use crate::runtime::interface::{EventDeclaration, Interface, VariableDeclaration};
+use crate::runtime::value::{self, AnyType, IecType};
+use crate::st::functions::{self, Class, Function, Output};
+use std::sync::Arc;
+
+/// The type of a function block calling a standard function.
+#[derive(Debug)]
+pub struct FunctionType {
+    name: String,
+    function: Function,
+    interface: Interface,
+    /// The generic types of the ports, ports of the same generic type share it.
+    generics: Vec<AnyType>,
+    /// The index of the generic type of each input, `None` for elementary types.
+    inputs: Vec<Option<usize>>,
+    /// The index of the generic type of the output, `None` for an elementary type.
+    output: Option<usize>,
+}
+
+impl FunctionType {
+    /// The type calling the function of the name, or `None` if there is no such function.
+    ///
+    /// The type is named like the function, with an `F_` prefix. The generic inputs of the
+    /// function share their generic type, and so does the output if it is of the same type.
+    pub fn new(function: &str) -> Option<Self> {
+        let name = format!("F_{function}");
+        let function = Function::lookup(function)?;
+
+        let mut generics = vec![];
+        let mut shared = None;
+        let mut inputs = vec![];
+        let mut data_inputs = vec![];
+        for index in 0..function.arity().0 {
+            let name = function.input_name(index)?;
+            // the conversions are declared for a specific type
+            let generic = match function.input_type(index) {
+                Some(r#type) => Err(r#type),
+                None => generic_type(function.input_class(index)?).ok_or(IecType::Bool),
+            };
+            match generic {
+                Ok(generic) => {
+                    let mut add = || {
+                        generics.push(generic);
+                        generics.len() - 1
+                    };
+                    inputs.push(Some(match function.is_generic(index) {
+                        true => *shared.get_or_insert_with(add),
+                        false => add(),
+                    }));
+                    data_inputs.push(VariableDeclaration::generic(name, generic));
+                }
+                Err(r#type) => {
+                    inputs.push(None);
+                    data_inputs.push(VariableDeclaration::new(name, r#type));
+                }
+            }
+        }
+
+        let types = data_inputs
+            .iter()
+            .map(|input| input.r#type)
+            .collect::<Vec<_>>();
+        let r#type = match function.result_type(&types) {
+            Ok(r#type) => r#type,
+            Err(err) => {
+                log::warn!("{name}: Unsupported function: {err}");
+                return None;
+            }
+        };
+        let output = match function.output() {
+            Some(Output::Generic) => shared,
+            Some(Output::First) => inputs[0],
+            _ => None,
+        };
+        let data_output = match output {
+            Some(generic) => VariableDeclaration::generic("OUT", generics[generic]),
+            None => VariableDeclaration::new("OUT", r#type),
+        };
+
+        let with = data_inputs.iter().map(|input| input.name.clone());
+        let interface = Interface {
+            event_inputs: vec![EventDeclaration::new("REQ", with)],
+            event_outputs: vec![EventDeclaration::new("CNF", ["OUT"])],
+            data_inputs,
+            data_outputs: vec![data_output],
+        };
+
+        Some(Self {
+            name,
+            function,
+            interface,
+            generics,
+            inputs,
+            output,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn interface(&self) -> &Interface {
+        &self.interface
+    }
+}
+
+/// The generic type of inputs accepting a class of types, `None` for `BOOL`.
+fn generic_type(class: Class) -> Option<AnyType> {
+    match class {
+        Class::Any => Some(AnyType::Any),
+        Class::Magnitude => Some(AnyType::AnyMagnitude),
+        Class::Num => Some(AnyType::AnyNum),
+        Class::Int => Some(AnyType::AnyInt),
+        Class::Bit => Some(AnyType::AnyBit),
+        Class::Bool => None,
+        Class::String => Some(AnyType::AnyString),
+    }
+}
+
+/// The types of all standard functions: the functions of [`Function::standard_names`], and all
+/// conversions and truncations between specific types.
+pub fn function_types() -> Vec<FunctionType> {
+    let conversions = IecType::ALL.into_iter().flat_map(|from| {
+        IecType::ALL
+            .into_iter()
+            .filter(move |to| from != *to && value::is_convertible(from, *to))
+            .map(move |to| format!("{from}_TO_{to}"))
+    });
+    let truncations = IecType::ALL
+        .into_iter()
+        .filter(|from| value::is_real(*from))
+        .flat_map(|from| {
+            IecType::ALL
+                .into_iter()
+                .filter(|to| value::is_integer(*to))
+                .map(move |to| format!("{from}_TRUNC_{to}"))
+        });
+
+    Function::standard_names()
+        .map(String::from)
+        .chain(conversions)
+        .chain(truncations)
+        .filter_map(|function| FunctionType::new(&function))
+        .collect()
+}
+
+/// An instance of a [`FunctionType`].
+pub struct FunctionCall {
+    r#type: Arc<FunctionType>,
+    req: EventInput,
+    cnf: EventOutput,
+    inputs: Vec<DataInput>,
+    out: DataOutput,
+}
+
+impl FunctionCall {
+    pub fn new(r#type: Arc<FunctionType>) -> Self {
+        let generics = r#type
+            .generics
+            .iter()
+            .map(|generic| GenericType::new(*generic))
+            .collect::<Vec<_>>();
+        let interface = &r#type.interface;
+        let inputs = interface
+            .data_inputs
+            .iter()
+            .zip(&r#type.inputs)
+            .map(|(input, generic)| match generic {
+                Some(generic) => DataInput::generic(generics[*generic].clone()),
+                None => DataInput::new(input.r#type),
+            })
+            .collect();
+        let out = match r#type.output {
+            Some(generic) => DataOutput::generic(generics[generic].clone()),
+            None => DataOutput::new(interface.data_outputs[0].r#type),
+        };
+
+        Self {
+            req: EventInput::new(),
+            cnf: EventOutput::new(),
+            inputs,
+            out,
+            r#type,
+        }
+    }
+}
+
+impl FunctionBlock for FunctionCall {
+    fn type_name(&self) -> String {
+        self.r#type.name.clone()
+    }
+
+    fn interface(&self) -> Interface {
+        self.r#type.interface.clone()
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        for input in &self.inputs {
+            input.set(input.r#type().default_value());
+        }
+        self.out.set(self.out.r#type().default_value());
+        Ok(())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        self.r#type
+            .interface
+            .data_inputs
+            .iter()
+            .position(|input| input.name == name)
+            .map(|i| self.inputs[i].clone())
+    }
+
+    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+        match name {
+            "OUT" => Some(self.out.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "REQ" => Some(self.req.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "CNF" => Some(self.cnf.clone()),
+            _ => None,
+        }
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        if name != "REQ" {
+            return;
+        }
+
+        let args = self.inputs.iter().map(DataInput::get).collect::<Vec<_>>();
+        let result = self
+            .r#type
+            .function
+            .call(&args)
+            .and_then(|value| functions::convert_implicit(value, self.out.r#type()));
+        match result {
+            Ok(value) => self.out.set(value),
+            Err(err) => log::warn!("{}: Failed to call function: {err}", self.r#type.name),
+        }
+        context.fire("CNF");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::container::{Container, PortDestination, SimpleContainer};
+    use crate::runtime::factory::{FunctionBlockFactory, StandardFactory};
+    use crate::runtime::value::{AnyType, IecType};
+
+    fn port(name: &str) -> PortDestination {
+        name.parse().unwrap()
+    }
+
+    /// Call the function of a block with the inputs, returning `OUT` and the number of `CNF`
+    /// events.
+    fn call(r#type: &str, inputs: &[(&str, &str)]) -> (String, String) {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut container = SimpleContainer::new(factory);
+        container.add_child("f".into(), r#type).unwrap();
+        container.add_watch(port("f.OUT")).unwrap();
+        container.add_watch(port("f.CNF")).unwrap();
+
+        for (name, value) in inputs {
+            container.write(value, port(&format!("f.{name}"))).unwrap();
+        }
+        container.write("$e", port("f.REQ")).unwrap();
+        container.dispatch();
+
+        let ports = container.read_watches().remove(0).ports;
+        let value = |name: &str| {
+            ports
+                .iter()
+                .find(|port| port.name == name)
+                .map(|port| port.data.value.clone())
+                .unwrap()
+        };
+        (value("OUT"), value("CNF"))
+    }
+
+    fn out(r#type: &str, inputs: &[(&str, &str)]) -> String {
+        call(r#type, inputs).0
+    }
+
+    #[test]
+    fn function_types() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let types = factory.types();
+        for r#type in [
+            "F_ADD",
+            "F_SUB",
+            "F_MUL",
+            "F_DIV",
+            "F_MOD",
+            "F_MOVE",
+            "F_AND",
+            "F_SHL",
+            "F_SEL",
+            "F_MAX",
+            "F_LIMIT",
+            "F_MUX",
+            "F_EQ",
+            "F_CONCAT",
+            "F_LEN",
+            "F_LEFT",
+            "F_INT_TO_REAL",
+            "F_STRING_TO_DINT",
+            "F_REAL_TRUNC_INT",
+        ] {
+            assert!(
+                types.iter().any(|t| t == r#type),
+                "{type} is not registered"
+            );
+        }
+        assert!(!types.iter().any(|t| t == "F_INT_TO_INT"));
+        assert!(!types.iter().any(|t| t == "F_DATE_TO_BOOL"));
+
+        let add = factory.interface("F_ADD").unwrap();
+        assert_eq!(add.event_inputs[0].with, ["IN1", "IN2"]);
+        assert_eq!(add.event_outputs[0].with, ["OUT"]);
+        assert_eq!(add.data_inputs[0].generic, Some(AnyType::AnyMagnitude));
+        assert_eq!(add.data_outputs[0].generic, Some(AnyType::AnyMagnitude));
+
+        let left = factory.interface("F_LEFT").unwrap();
+        let generics = left.data_inputs.iter().map(|input| input.generic);
+        assert_eq!(
+            generics.collect::<Vec<_>>(),
+            [Some(AnyType::AnyString), Some(AnyType::AnyInt)]
+        );
+        assert_eq!(left.data_outputs[0].generic, Some(AnyType::AnyString));
+
+        let sel = factory.interface("F_SEL").unwrap();
+        assert_eq!(sel.data_inputs[0].r#type, IecType::Bool);
+        assert_eq!(sel.data_inputs[0].generic, None);
+
+        let eq = factory.interface("F_EQ").unwrap();
+        assert_eq!(eq.data_outputs[0].r#type, IecType::Bool);
+        assert_eq!(eq.data_outputs[0].generic, None);
+    }
+
+    #[test]
+    fn resolved_types() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut container = SimpleContainer::new(factory);
+        container.add_child("ctu".into(), "E_CTU").unwrap();
+        container.add_child("add".into(), "F_ADD").unwrap();
+        container.connect(port("ctu.CV"), port("add.IN1")).unwrap();
+        container.add_watch(port("add.OUT")).unwrap();
+
+        container.write("$e", port("ctu.CU")).unwrap();
+        container.dispatch();
+        container.write("2", port("add.IN2")).unwrap();
+        container.write("$e", port("add.REQ")).unwrap();
+        container.dispatch();
+        assert_eq!(container.read_watches()[0].ports[0].data.value, "3");
+        assert!(container.write("2.5", port("add.IN2")).is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(out("F_ADD", &[("IN1", "1.5"), ("IN2", "2")]), "3.5");
+        assert_eq!(out("F_SUB", &[("IN1", "1"), ("IN2", "3")]), "-2.0");
+        assert_eq!(out("F_MUL", &[("IN1", "2"), ("IN2", "4")]), "8.0");
+        assert_eq!(out("F_DIV", &[("IN1", "1"), ("IN2", "4")]), "0.25");
+        assert_eq!(out("F_MOD", &[("IN1", "7"), ("IN2", "3")]), "1");
+    }
+
+    #[test]
+    fn logic_and_selection() {
+        assert_eq!(out("F_AND", &[("IN1", "TRUE"), ("IN2", "FALSE")]), "FALSE");
+        assert_eq!(out("F_OR", &[("IN1", "TRUE"), ("IN2", "FALSE")]), "TRUE");
+        assert_eq!(out("F_NOT", &[("IN", "FALSE")]), "TRUE");
+        assert_eq!(out("F_GT", &[("IN1", "2"), ("IN2", "1")]), "TRUE");
+        assert_eq!(out("F_EQ", &[("IN1", "2"), ("IN2", "1")]), "FALSE");
+        assert_eq!(
+            out("F_SEL", &[("G", "TRUE"), ("IN0", "1"), ("IN1", "2")]),
+            "2.0"
+        );
+        assert_eq!(out("F_MAX", &[("IN1", "1"), ("IN2", "2")]), "2.0");
+        assert_eq!(
+            out("F_LIMIT", &[("MN", "0"), ("IN", "12"), ("MX", "10")]),
+            "10.0"
+        );
+        assert_eq!(
+            out("F_MUX", &[("K", "1"), ("IN0", "1"), ("IN1", "2")]),
+            "2.0"
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            out("F_CONCAT", &[("IN1", "'foo'"), ("IN2", "'bar'")]),
+            "'foobar'"
+        );
+        assert_eq!(out("F_LEN", &[("IN", "'foo'")]), "3");
+        assert_eq!(out("F_LEFT", &[("IN", "'foobar'"), ("L", "3")]), "'foo'");
+        assert_eq!(out("F_FIND", &[("IN1", "'foobar'"), ("IN2", "'bar'")]), "4");
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(out("F_INT_TO_REAL", &[("IN", "3")]), "3.0");
+        assert_eq!(out("F_REAL_TO_INT", &[("IN", "2.6")]), "3");
+        assert_eq!(out("F_REAL_TRUNC_INT", &[("IN", "-2.7")]), "-2");
+        assert_eq!(out("F_STRING_TO_DINT", &[("IN", "'42'")]), "42");
+        assert_eq!(out("F_BOOL_TO_INT", &[("IN", "TRUE")]), "1");
+    }
+
+    #[test]
+    fn failed_calls_confirm() {
+        // the output keeps its value, but the request is confirmed
+        assert_eq!(
+            call("F_MOD", &[("IN1", "1"), ("IN2", "0")]),
+            ("0".into(), "1".into())
+        );
+        assert_eq!(
+            call("F_STRING_TO_DINT", &[("IN", "'foo'")]),
+            ("0".into(), "1".into())
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::runtime::factory::Creator;
 use crate::runtime::fb::FunctionBlock;
 
+pub mod functions;
 pub mod std;
 
 pub struct MockFunctionBlock(String);
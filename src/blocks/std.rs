@@ -1,41 +1,612 @@
-use crate::runtime::fb::{DataInput, DataOutput, FunctionBlock};
+//! The standard event function blocks of IEC 61499-1, Annex A.
+//!
+//! Blocks which are defined by an ECC are provided as [`BasicType`]s by [`basic_types`], blocks
+//! depending on time or on the state of their resource are implemented natively.
 
-pub struct Switch {
-    g: DataInput,
+use crate::protocol::server::Error;
+use crate::runtime::basic::{
+    Action, BasicType, BasicTypeBuilder, ExecutionError, Guard, Variables,
+};
+use crate::runtime::fb::{
+    DataInput, DataOutput, EventContext, EventInput, EventOutput, FunctionBlock,
+};
+use crate::runtime::interface::{EventDeclaration, Interface, VariableDeclaration};
+use crate::runtime::value::{IecType, IecValue};
+use std::time::Duration;
+
+/// The names of the numbered event outputs of `E_DEMUX` and `E_N_TABLE`.
+const OUTPUTS: [&str; 4] = ["EO0", "EO1", "EO2", "EO3"];
+
+/// The standard event blocks which are defined by an ECC.
+pub fn basic_types() -> Vec<BasicType> {
+    vec![
+        split(),
+        merge(),
+        rendezvous(),
+        permit(),
+        select(),
+        switch(),
+        set_reset("E_SR"),
+        set_reset("E_RS"),
+        d_flip_flop(),
+        count_up(),
+        count_down(),
+        count_up_down(),
+        rising_edge(),
+        falling_edge(),
+        demux(),
+    ]
+}
+
+fn build(builder: BasicTypeBuilder) -> BasicType {
+    builder
+        .build()
+        .unwrap_or_else(|err| panic!("Invalid standard type: {err}"))
+}
+
+fn uint(vars: &Variables, name: &str) -> Result<u16, ExecutionError> {
+    match vars.get(name)? {
+        IecValue::Uint(value) => Ok(*value),
+        value => Err(ExecutionError::TypeMismatch {
+            name: name.to_string(),
+            expected: IecType::Uint,
+            actual: value.r#type(),
+        }),
+    }
+}
+
+/// `E_SPLIT`: fires `EO1`, then `EO2`, for each `EI`.
+fn split() -> BasicType {
+    build(
+        BasicType::builder("E_SPLIT")
+            .event_input("EI", &[])
+            .event_output("EO1", &[])
+            .event_output("EO2", &[])
+            .state("START", [])
+            .state("EI", [Action::fire("EO1"), Action::fire("EO2")])
+            .transition("START", "EI", Guard::event("EI"))
+            .transition("EI", "START", Guard::always()),
+    )
+}
+
+/// `E_MERGE`: fires `EO` for each `EI1` and `EI2`.
+fn merge() -> BasicType {
+    build(
+        BasicType::builder("E_MERGE")
+            .event_input("EI1", &[])
+            .event_input("EI2", &[])
+            .event_output("EO", &[])
+            .state("START", [])
+            .state("EO", [Action::fire("EO")])
+            .transition("START", "EO", Guard::event("EI1"))
+            .transition("START", "EO", Guard::event("EI2"))
+            .transition("EO", "START", Guard::always()),
+    )
+}
+
+/// `E_REND`: fires `EO` once both `EI1` and `EI2` were received, in any order. `R` forgets the
+/// events received so far.
+fn rendezvous() -> BasicType {
+    build(
+        BasicType::builder("E_REND")
+            .event_input("EI1", &[])
+            .event_input("EI2", &[])
+            .event_input("R", &[])
+            .event_output("EO", &[])
+            .state("START", [])
+            .state("EI1", [])
+            .state("EI2", [])
+            .state("EO", [Action::fire("EO")])
+            .transition("START", "EI1", Guard::event("EI1"))
+            .transition("START", "EI2", Guard::event("EI2"))
+            .transition("EI1", "EO", Guard::event("EI2"))
+            .transition("EI1", "START", Guard::event("R"))
+            .transition("EI2", "EO", Guard::event("EI1"))
+            .transition("EI2", "START", Guard::event("R"))
+            .transition("EO", "START", Guard::always()),
+    )
+}
+
+/// `E_PERMIT`: forwards `EI` to `EO` if `PERMIT` is set.
+fn permit() -> BasicType {
+    build(
+        BasicType::builder("E_PERMIT")
+            .event_input("EI", &["PERMIT"])
+            .event_output("EO", &[])
+            .input("PERMIT", IecType::Bool)
+            .state("START", [])
+            .state("EO", [Action::fire("EO")])
+            .transition(
+                "START",
+                "EO",
+                Guard::event("EI").when(|vars| vars.get_bool("PERMIT")),
+            )
+            .transition("EO", "START", Guard::always()),
+    )
+}
+
+/// `E_SELECT`: forwards `EI0` to `EO` if `G` is not set, and `EI1` if it is.
+fn select() -> BasicType {
+    build(
+        BasicType::builder("E_SELECT")
+            .event_input("EI0", &["G"])
+            .event_input("EI1", &["G"])
+            .event_output("EO", &[])
+            .input("G", IecType::Bool)
+            .state("START", [])
+            .state("EO", [Action::fire("EO")])
+            .transition(
+                "START",
+                "EO",
+                Guard::event("EI0").when(|vars| Ok(!vars.get_bool("G")?)),
+            )
+            .transition(
+                "START",
+                "EO",
+                Guard::event("EI1").when(|vars| vars.get_bool("G")),
+            )
+            .transition("EO", "START", Guard::always()),
+    )
+}
+
+/// `E_SWITCH`: forwards `EI` to `EO0` if `G` is not set, and to `EO1` if it is.
+fn switch() -> BasicType {
+    build(
+        BasicType::builder("E_SWITCH")
+            .event_input("EI", &["G"])
+            .event_output("EO0", &[])
+            .event_output("EO1", &[])
+            .input("G", IecType::Bool)
+            .state("START", [])
+            .state("G0", [Action::fire("EO0")])
+            .state("G1", [Action::fire("EO1")])
+            .transition(
+                "START",
+                "G0",
+                Guard::event("EI").when(|vars| Ok(!vars.get_bool("G")?)),
+            )
+            .transition(
+                "START",
+                "G1",
+                Guard::event("EI").when(|vars| vars.get_bool("G")),
+            )
+            .transition("G0", "START", Guard::always())
+            .transition("G1", "START", Guard::always()),
+    )
+}
+
+/// `E_SR` and `E_RS`: `S` sets `Q`, `R` resets it, firing `EO` when `Q` changes.
+///
+/// The standard distinguishes the set and the reset dominant bistable, but as events never
+/// arrive at the same time, both behave the same.
+fn set_reset(name: &str) -> BasicType {
+    build(
+        BasicType::builder(name)
+            .event_input("S", &[])
+            .event_input("R", &[])
+            .event_output("EO", &["Q"])
+            .output("Q", IecType::Bool)
+            .algorithm("SET", |vars| vars.set("Q", IecValue::Bool(true)))
+            .algorithm("RESET", |vars| vars.set("Q", IecValue::Bool(false)))
+            .state("Q0", [])
+            .state("SET", [Action::algorithm("SET").output("EO")])
+            .state("RESET", [Action::algorithm("RESET").output("EO")])
+            .transition("Q0", "SET", Guard::event("S"))
+            .transition("SET", "RESET", Guard::event("R"))
+            .transition("RESET", "SET", Guard::event("S")),
+    )
+}
+
+/// `E_D_FF`: latches `D` into `Q` on `CLK`, firing `EO` when `Q` changes.
+fn d_flip_flop() -> BasicType {
+    build(
+        BasicType::builder("E_D_FF")
+            .event_input("CLK", &["D"])
+            .event_output("EO", &["Q"])
+            .input("D", IecType::Bool)
+            .output("Q", IecType::Bool)
+            .algorithm("LATCH", |vars| vars.set("Q", vars.get("D")?.clone()))
+            .state("Q0", [])
+            .state("SET", [Action::algorithm("LATCH").output("EO")])
+            .state("RESET", [Action::algorithm("LATCH").output("EO")])
+            .transition(
+                "Q0",
+                "SET",
+                Guard::event("CLK").when(|vars| vars.get_bool("D")),
+            )
+            .transition(
+                "SET",
+                "RESET",
+                Guard::event("CLK").when(|vars| Ok(!vars.get_bool("D")?)),
+            )
+            .transition(
+                "RESET",
+                "SET",
+                Guard::event("CLK").when(|vars| vars.get_bool("D")),
+            ),
+    )
+}
+
+/// `E_CTU`: counts `CU` up to 65535, `Q` is set once `CV` reaches `PV`. `R` resets the counter.
+fn count_up() -> BasicType {
+    build(
+        BasicType::builder("E_CTU")
+            .event_input("CU", &["PV"])
+            .event_input("R", &[])
+            .event_output("CUO", &["Q", "CV"])
+            .event_output("RO", &["Q", "CV"])
+            .input("PV", IecType::Uint)
+            .output("Q", IecType::Bool)
+            .output("CV", IecType::Uint)
+            .algorithm("CU", |vars| {
+                let cv = uint(vars, "CV")? + 1;
+                vars.set("CV", IecValue::Uint(cv))?;
+                vars.set("Q", IecValue::Bool(cv >= uint(vars, "PV")?))
+            })
+            .algorithm("R", |vars| {
+                vars.set("CV", IecValue::Uint(0))?;
+                vars.set("Q", IecValue::Bool(false))
+            })
+            .state("START", [])
+            .state("CU", [Action::algorithm("CU").output("CUO")])
+            .state("R", [Action::algorithm("R").output("RO")])
+            .transition(
+                "START",
+                "CU",
+                Guard::event("CU").when(|vars| Ok(uint(vars, "CV")? < u16::MAX)),
+            )
+            .transition("START", "R", Guard::event("R"))
+            .transition("CU", "START", Guard::always())
+            .transition("R", "START", Guard::always()),
+    )
+}
+
+/// `E_CTD`: counts `CD` down to 0, `Q` is set once `CV` reaches 0. `LD` loads `PV` into `CV`.
+fn count_down() -> BasicType {
+    build(
+        BasicType::builder("E_CTD")
+            .event_input("CD", &[])
+            .event_input("LD", &["PV"])
+            .event_output("CDO", &["Q", "CV"])
+            .event_output("LDO", &["Q", "CV"])
+            .input("PV", IecType::Uint)
+            .output("Q", IecType::Bool)
+            .output("CV", IecType::Uint)
+            .algorithm("CD", |vars| {
+                let cv = uint(vars, "CV")? - 1;
+                vars.set("CV", IecValue::Uint(cv))?;
+                vars.set("Q", IecValue::Bool(cv == 0))
+            })
+            .algorithm("LD", |vars| {
+                let cv = uint(vars, "PV")?;
+                vars.set("CV", IecValue::Uint(cv))?;
+                vars.set("Q", IecValue::Bool(cv == 0))
+            })
+            .state("START", [])
+            .state("CD", [Action::algorithm("CD").output("CDO")])
+            .state("LD", [Action::algorithm("LD").output("LDO")])
+            .transition(
+                "START",
+                "CD",
+                Guard::event("CD").when(|vars| Ok(uint(vars, "CV")? > 0)),
+            )
+            .transition("START", "LD", Guard::event("LD"))
+            .transition("CD", "START", Guard::always())
+            .transition("LD", "START", Guard::always()),
+    )
+}
+
+/// `E_CTUD`: counts `CU` up and `CD` down, between 0 and 65535. `QU` is set while `CV` is at
+/// least `PV`, `QD` while it is 0. `R` resets the counter, `LD` loads `PV` into `CV`.
+fn count_up_down() -> BasicType {
+    fn count(vars: &mut Variables, cv: u16) -> Result<(), ExecutionError> {
+        vars.set("CV", IecValue::Uint(cv))?;
+        vars.set("QU", IecValue::Bool(cv >= uint(vars, "PV")?))?;
+        vars.set("QD", IecValue::Bool(cv == 0))
+    }
+
+    build(
+        BasicType::builder("E_CTUD")
+            .event_input("CU", &["PV"])
+            .event_input("CD", &["PV"])
+            .event_input("R", &[])
+            .event_input("LD", &["PV"])
+            .event_output("CO", &["QU", "QD", "CV"])
+            .event_output("RO", &["QU", "QD", "CV"])
+            .event_output("LDO", &["QU", "QD", "CV"])
+            .input("PV", IecType::Uint)
+            .output("QU", IecType::Bool)
+            .output("QD", IecType::Bool)
+            .output("CV", IecType::Uint)
+            .algorithm("CU", |vars| count(vars, uint(vars, "CV")? + 1))
+            .algorithm("CD", |vars| count(vars, uint(vars, "CV")? - 1))
+            .algorithm("R", |vars| count(vars, 0))
+            .algorithm("LD", |vars| count(vars, uint(vars, "PV")?))
+            .state("START", [])
+            .state("CU", [Action::algorithm("CU").output("CO")])
+            .state("CD", [Action::algorithm("CD").output("CO")])
+            .state("R", [Action::algorithm("R").output("RO")])
+            .state("LD", [Action::algorithm("LD").output("LDO")])
+            .transition(
+                "START",
+                "CU",
+                Guard::event("CU").when(|vars| Ok(uint(vars, "CV")? < u16::MAX)),
+            )
+            .transition(
+                "START",
+                "CD",
+                Guard::event("CD").when(|vars| Ok(uint(vars, "CV")? > 0)),
+            )
+            .transition("START", "R", Guard::event("R"))
+            .transition("START", "LD", Guard::event("LD"))
+            .transition("CU", "START", Guard::always())
+            .transition("CD", "START", Guard::always())
+            .transition("R", "START", Guard::always())
+            .transition("LD", "START", Guard::always()),
+    )
+}
+
+/// `E_R_TRIG`: fires `EO` on `EI` when `QI` changed from `FALSE` to `TRUE`.
+fn rising_edge() -> BasicType {
+    build(
+        BasicType::builder("E_R_TRIG")
+            .event_input("EI", &["QI"])
+            .event_output("EO", &[])
+            .input("QI", IecType::Bool)
+            .state("START", [])
+            .state("EO", [Action::fire("EO")])
+            .transition(
+                "START",
+                "EO",
+                Guard::event("EI").when(|vars| vars.get_bool("QI")),
+            )
+            .transition(
+                "EO",
+                "START",
+                Guard::event("EI").when(|vars| Ok(!vars.get_bool("QI")?)),
+            ),
+    )
+}
+
+/// `E_F_TRIG`: fires `EO` on `EI` when `QI` changed from `TRUE` to `FALSE`.
+fn falling_edge() -> BasicType {
+    build(
+        BasicType::builder("E_F_TRIG")
+            .event_input("EI", &["QI"])
+            .event_output("EO", &[])
+            .input("QI", IecType::Bool)
+            .state("START", [])
+            .state("QI", [])
+            .state("EO", [Action::fire("EO")])
+            .transition(
+                "START",
+                "QI",
+                Guard::event("EI").when(|vars| vars.get_bool("QI")),
+            )
+            .transition(
+                "QI",
+                "EO",
+                Guard::event("EI").when(|vars| Ok(!vars.get_bool("QI")?)),
+            )
+            .transition("EO", "START", Guard::always()),
+    )
+}
+
+/// `E_DEMUX`: forwards `EI` to the output selected by `K`, `EO0` to `EO3`. Other values of `K`
+/// drop the event.
+fn demux() -> BasicType {
+    let mut builder = BasicType::builder("E_DEMUX")
+        .event_input("EI", &["K"])
+        .input("K", IecType::Uint)
+        .state("START", []);
+    for (k, output) in OUTPUTS.into_iter().enumerate() {
+        builder = builder
+            .event_output(output, &[])
+            .state(output, [Action::fire(output)])
+            .transition(
+                "START",
+                output,
+                Guard::event("EI").when(move |vars| Ok(usize::from(uint(vars, "K")?) == k)),
+            )
+            .transition(output, "START", Guard::always());
+    }
+    build(builder)
+}
+
+/// The value of a `TIME` input as a duration, `None` if it is negative.
+fn duration(input: &DataInput) -> Option<Duration> {
+    match input.get() {
+        IecValue::Time(time) if time >= 0 => Some(Duration::from_nanos(time as u64)),
+        _ => None,
+    }
+}
+
+/// The value of a `UINT` input.
+fn count(input: &DataInput) -> u16 {
+    match input.get() {
+        IecValue::Uint(value) => value,
+        _ => 0,
+    }
+}
+
+/// Fires `EO` every `DT` after `START`, until `STOP`.
+///
+/// The period is sampled with `START`. Each deadline is computed from the previous one rather
+/// than from the time the timer was handled, so delays don't accumulate. Periods which were
+/// missed entirely, e.g. while the resource was stopped, are skipped.
+pub struct Cycle {
+    dt: DataInput,
+    start: EventInput,
+    stop: EventInput,
+    eo: EventOutput,
+    /// The period, while running.
+    period: Option<Duration>,
 }
 
-impl Switch {
+impl Cycle {
     pub fn new() -> Self {
-        Self { g: DataInput {} }
+        Self {
+            dt: DataInput::new(IecType::Time),
+            start: EventInput::new(),
+            stop: EventInput::new(),
+            eo: EventOutput::new(),
+            period: None,
+        }
+    }
+}
+
+impl Default for Cycle {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl FunctionBlock for Switch {
+impl FunctionBlock for Cycle {
     fn type_name(&self) -> String {
-        "E_SR".to_string()
+        "E_CYCLE".to_string()
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_inputs: vec![
+                EventDeclaration::new("START", ["DT"]),
+                EventDeclaration::new("STOP", none),
+            ],
+            event_outputs: vec![EventDeclaration::new("EO", none)],
+            data_inputs: vec![VariableDeclaration::new("DT", IecType::Time)],
+            ..Default::default()
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.dt.set(IecType::Time.default_value());
+        self.period = None;
+        Ok(())
     }
 
     fn get_data_input(&self, name: &str) -> Option<DataInput> {
         match name {
-            "G" => Some(self.g.clone()),
+            "DT" => Some(self.dt.clone()),
             _ => None,
         }
     }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "START" => Some(self.start.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "EO" => Some(self.eo.clone()),
+            _ => None,
+        }
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        match name {
+            "START" => {
+                let Some(period) = duration(&self.dt).filter(|dt| !dt.is_zero()) else {
+                    log::warn!("E_CYCLE: Invalid period: {}", self.dt.get());
+                    return;
+                };
+                self.period = Some(period);
+                context.set_timer(context.now() + period);
+            }
+            "STOP" => {
+                self.period = None;
+                context.cancel_timer();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, deadline: Duration, context: &mut EventContext) {
+        let Some(period) = self.period else {
+            return;
+        };
+        context.fire("EO");
+
+        let mut next = deadline + period;
+        if next < context.now() {
+            let periods = (context.now() - deadline).as_nanos() / period.as_nanos() + 1;
+            next = deadline + Duration::from_nanos((periods * period.as_nanos()) as u64);
+        }
+        context.set_timer(next);
+    }
 }
 
-pub struct Cycle {
+/// Fires `EO` once, `DT` after `START`, unless `STOP` comes first.
+///
+/// `E_DELAY` ignores `START` while a delay is pending, the retriggerable `E_DELAYR` starts the
+/// delay over instead.
+pub struct Delay {
+    retrigger: bool,
     dt: DataInput,
+    start: EventInput,
+    stop: EventInput,
+    eo: EventOutput,
+    pending: bool,
 }
 
-impl Cycle {
+impl Delay {
+    /// Create an `E_DELAY`.
     pub fn new() -> Self {
-        Self { dt: DataInput {} }
+        Self::with_retrigger(false)
+    }
+
+    /// Create an `E_DELAYR`.
+    pub fn retriggerable() -> Self {
+        Self::with_retrigger(true)
+    }
+
+    fn with_retrigger(retrigger: bool) -> Self {
+        Self {
+            retrigger,
+            dt: DataInput::new(IecType::Time),
+            start: EventInput::new(),
+            stop: EventInput::new(),
+            eo: EventOutput::new(),
+            pending: false,
+        }
     }
 }
 
-impl FunctionBlock for Cycle {
+impl Default for Delay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionBlock for Delay {
     fn type_name(&self) -> String {
-        "E_CYCLE".to_string()
+        match self.retrigger {
+            false => "E_DELAY".to_string(),
+            true => "E_DELAYR".to_string(),
+        }
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_inputs: vec![
+                EventDeclaration::new("START", ["DT"]),
+                EventDeclaration::new("STOP", none),
+            ],
+            event_outputs: vec![EventDeclaration::new("EO", none)],
+            data_inputs: vec![VariableDeclaration::new("DT", IecType::Time)],
+            ..Default::default()
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.dt.set(IecType::Time.default_value());
+        self.pending = false;
+        Ok(())
     }
 
     fn get_data_input(&self, name: &str) -> Option<DataInput> {
@@ -44,27 +615,972 @@ impl FunctionBlock for Cycle {
             _ => None,
         }
     }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "START" => Some(self.start.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "EO" => Some(self.eo.clone()),
+            _ => None,
+        }
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        match name {
+            "START" if self.pending && !self.retrigger => {}
+            "START" => {
+                let Some(delay) = duration(&self.dt) else {
+                    log::warn!("{}: Invalid delay: {}", self.type_name(), self.dt.get());
+                    return;
+                };
+                self.pending = true;
+                context.set_timer(context.now() + delay);
+            }
+            "STOP" => {
+                self.pending = false;
+                context.cancel_timer();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, _deadline: Duration, context: &mut EventContext) {
+        if self.pending {
+            self.pending = false;
+            context.fire("EO");
+        }
+    }
 }
 
-pub struct SetReset {
-    q: DataOutput,
+/// Fires `EO` `N` times, every `DT` after `START`, until `STOP`.
+///
+/// `CV` is the index of the event in the train, starting at 0. `START` starts the train over,
+/// the inputs are sampled with it.
+pub struct Train {
+    dt: DataInput,
+    n: DataInput,
+    start: EventInput,
+    stop: EventInput,
+    eo: EventOutput,
+    cv: DataOutput,
+    /// The period, while running.
+    period: Option<Duration>,
+    /// The number of events to fire, and the number fired so far.
+    events: (u16, u16),
 }
 
-impl SetReset {
+impl Train {
     pub fn new() -> Self {
-        Self { q: DataOutput {} }
+        Self {
+            dt: DataInput::new(IecType::Time),
+            n: DataInput::new(IecType::Uint),
+            start: EventInput::new(),
+            stop: EventInput::new(),
+            eo: EventOutput::new(),
+            cv: DataOutput::new(IecType::Uint),
+            period: None,
+            events: (0, 0),
+        }
+    }
+}
+
+impl Default for Train {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl FunctionBlock for SetReset {
+impl FunctionBlock for Train {
     fn type_name(&self) -> String {
-        "E_SR".to_string()
+        "E_TRAIN".to_string()
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_inputs: vec![
+                EventDeclaration::new("START", ["DT", "N"]),
+                EventDeclaration::new("STOP", none),
+            ],
+            event_outputs: vec![EventDeclaration::new("EO", ["CV"])],
+            data_inputs: vec![
+                VariableDeclaration::new("DT", IecType::Time),
+                VariableDeclaration::new("N", IecType::Uint),
+            ],
+            data_outputs: vec![VariableDeclaration::new("CV", IecType::Uint)],
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.dt.set(IecType::Time.default_value());
+        self.n.set(IecType::Uint.default_value());
+        self.cv.set(IecType::Uint.default_value());
+        self.period = None;
+        self.events = (0, 0);
+        Ok(())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        match name {
+            "DT" => Some(self.dt.clone()),
+            "N" => Some(self.n.clone()),
+            _ => None,
+        }
     }
 
     fn get_data_output(&self, name: &str) -> Option<DataOutput> {
         match name {
-            "Q" => Some(self.q.clone()),
+            "CV" => Some(self.cv.clone()),
             _ => None,
         }
     }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "START" => Some(self.start.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "EO" => Some(self.eo.clone()),
+            _ => None,
+        }
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        match name {
+            "START" => {
+                self.period = None;
+                context.cancel_timer();
+                let Some(period) = duration(&self.dt).filter(|dt| !dt.is_zero()) else {
+                    log::warn!("E_TRAIN: Invalid period: {}", self.dt.get());
+                    return;
+                };
+                self.events = (count(&self.n), 0);
+                if self.events.0 > 0 {
+                    self.period = Some(period);
+                    context.set_timer(context.now() + period);
+                }
+            }
+            "STOP" => {
+                self.period = None;
+                context.cancel_timer();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, deadline: Duration, context: &mut EventContext) {
+        let Some(period) = self.period else {
+            return;
+        };
+        let (n, fired) = self.events;
+        self.cv.set(IecValue::Uint(fired));
+        context.fire("EO");
+
+        self.events.1 += 1;
+        if self.events.1 < n {
+            context.set_timer(deadline + period);
+        } else {
+            self.period = None;
+        }
+    }
+}
+
+/// Fires `EO` up to four times after `START`, each event `DT[i]` after the previous one, until
+/// `STOP`.
+///
+/// `N` is the number of events, `CV` is the index of the event, starting at 0. `DT` is an
+/// `ARRAY[0..3] OF TIME`, which can be written as a whole, but not connected. `START` starts the
+/// table over, the inputs are sampled with it.
+pub struct Table {
+    dt: [DataInput; 4],
+    n: DataInput,
+    start: EventInput,
+    stop: EventInput,
+    eo: EventOutput,
+    cv: DataOutput,
+    /// The delays of the events, while running.
+    delays: Vec<Duration>,
+    /// The index of the next event.
+    next: usize,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self {
+            dt: table_input(),
+            n: DataInput::new(IecType::Uint),
+            start: EventInput::new(),
+            stop: EventInput::new(),
+            eo: EventOutput::new(),
+            cv: DataOutput::new(IecType::Uint),
+            delays: vec![],
+            next: 0,
+        }
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionBlock for Table {
+    fn type_name(&self) -> String {
+        "E_TABLE".to_string()
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_inputs: vec![
+                EventDeclaration::new("START", ["DT", "N"]),
+                EventDeclaration::new("STOP", none),
+            ],
+            event_outputs: vec![EventDeclaration::new("EO", ["CV"])],
+            data_inputs: vec![
+                VariableDeclaration::array("DT", IecType::Time, self.dt.len()),
+                VariableDeclaration::new("N", IecType::Uint),
+            ],
+            data_outputs: vec![VariableDeclaration::new("CV", IecType::Uint)],
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        for dt in &self.dt {
+            dt.set(IecType::Time.default_value());
+        }
+        self.n.set(IecType::Uint.default_value());
+        self.cv.set(IecType::Uint.default_value());
+        self.delays.clear();
+        self.next = 0;
+        Ok(())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        match name {
+            "N" => Some(self.n.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_array_input(&self, name: &str) -> Option<Vec<DataInput>> {
+        match name {
+            "DT" => Some(self.dt.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+        match name {
+            "CV" => Some(self.cv.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "START" => Some(self.start.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "EO" => Some(self.eo.clone()),
+            _ => None,
+        }
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        match name {
+            "START" => {
+                self.delays.clear();
+                self.next = 0;
+                context.cancel_timer();
+                let Some(delays) = table_delays(&self.dt, &self.n) else {
+                    log::warn!("E_TABLE: Invalid delays");
+                    return;
+                };
+                if let Some(delay) = delays.first() {
+                    context.set_timer(context.now() + *delay);
+                }
+                self.delays = delays;
+            }
+            "STOP" => {
+                self.delays.clear();
+                context.cancel_timer();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, deadline: Duration, context: &mut EventContext) {
+        if self.next >= self.delays.len() {
+            return;
+        }
+        self.cv.set(IecValue::Uint(self.next as u16));
+        context.fire("EO");
+
+        self.next += 1;
+        match self.delays.get(self.next) {
+            Some(delay) => context.set_timer(deadline + *delay),
+            None => self.delays.clear(),
+        }
+    }
+}
+
+/// Fires `EO0` to `EO(N-1)` after `START`, each event `DT[i]` after the previous one, until
+/// `STOP`.
+///
+/// Like `E_TABLE`, with one event output per event instead of `CV`. `START` starts the table
+/// over, the inputs are sampled with it.
+pub struct NTable {
+    dt: [DataInput; 4],
+    n: DataInput,
+    start: EventInput,
+    stop: EventInput,
+    eo: [EventOutput; 4],
+    /// The delays of the events, while running.
+    delays: Vec<Duration>,
+    /// The index of the next event.
+    next: usize,
+}
+
+impl NTable {
+    pub fn new() -> Self {
+        Self {
+            dt: table_input(),
+            n: DataInput::new(IecType::Uint),
+            start: EventInput::new(),
+            stop: EventInput::new(),
+            eo: OUTPUTS.map(|_| EventOutput::new()),
+            delays: vec![],
+            next: 0,
+        }
+    }
+}
+
+impl Default for NTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionBlock for NTable {
+    fn type_name(&self) -> String {
+        "E_N_TABLE".to_string()
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_inputs: vec![
+                EventDeclaration::new("START", ["DT", "N"]),
+                EventDeclaration::new("STOP", none),
+            ],
+            event_outputs: OUTPUTS
+                .into_iter()
+                .map(|name| EventDeclaration::new(name, none))
+                .collect(),
+            data_inputs: vec![
+                VariableDeclaration::array("DT", IecType::Time, self.dt.len()),
+                VariableDeclaration::new("N", IecType::Uint),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        for dt in &self.dt {
+            dt.set(IecType::Time.default_value());
+        }
+        self.n.set(IecType::Uint.default_value());
+        self.delays.clear();
+        self.next = 0;
+        Ok(())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        match name {
+            "N" => Some(self.n.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_array_input(&self, name: &str) -> Option<Vec<DataInput>> {
+        match name {
+            "DT" => Some(self.dt.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        match name {
+            "START" => Some(self.start.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        OUTPUTS
+            .iter()
+            .position(|output| *output == name)
+            .map(|i| self.eo[i].clone())
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        match name {
+            "START" => {
+                self.delays.clear();
+                self.next = 0;
+                context.cancel_timer();
+                let Some(delays) = table_delays(&self.dt, &self.n) else {
+                    log::warn!("E_N_TABLE: Invalid delays");
+                    return;
+                };
+                if let Some(delay) = delays.first() {
+                    context.set_timer(context.now() + *delay);
+                }
+                self.delays = delays;
+            }
+            "STOP" => {
+                self.delays.clear();
+                context.cancel_timer();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, deadline: Duration, context: &mut EventContext) {
+        if self.next >= self.delays.len() {
+            return;
+        }
+        context.fire(OUTPUTS[self.next]);
+
+        self.next += 1;
+        match self.delays.get(self.next) {
+            Some(delay) => context.set_timer(deadline + *delay),
+            None => self.delays.clear(),
+        }
+    }
+}
+
+/// The elements of the `DT` array of `E_TABLE` and `E_N_TABLE`.
+fn table_input() -> [DataInput; 4] {
+    OUTPUTS.map(|_| DataInput::new(IecType::Time))
+}
+
+/// The delays of the first `N` events of a table, if all of them are valid.
+fn table_delays(dt: &[DataInput], n: &DataInput) -> Option<Vec<Duration>> {
+    let n = usize::from(count(n)).min(dt.len());
+    dt[..n].iter().map(duration).collect()
+}
+
+/// Signals the start and stop of its resource: `COLD` on the first start, `WARM` on a start after
+/// a stop, and `STOP` before the resource stops.
+pub struct Restart {
+    cold: EventOutput,
+    warm: EventOutput,
+    stop: EventOutput,
+    /// If the resource was started since the block was created or reset.
+    started: bool,
+}
+
+impl Restart {
+    pub fn new() -> Self {
+        Self {
+            cold: EventOutput::new(),
+            warm: EventOutput::new(),
+            stop: EventOutput::new(),
+            started: false,
+        }
+    }
+}
+
+impl Default for Restart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionBlock for Restart {
+    fn type_name(&self) -> String {
+        "E_RESTART".to_string()
+    }
+
+    fn interface(&self) -> Interface {
+        let none: [&str; 0] = [];
+        Interface {
+            event_outputs: vec![
+                EventDeclaration::new("COLD", none),
+                EventDeclaration::new("WARM", none),
+                EventDeclaration::new("STOP", none),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn start(&mut self, context: &mut EventContext) -> Result<(), Error> {
+        context.fire(if self.started { "WARM" } else { "COLD" });
+        self.started = true;
+        Ok(())
+    }
+
+    fn stop(&mut self, context: &mut EventContext) -> Result<(), Error> {
+        context.fire("STOP");
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.started = false;
+        Ok(())
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        match name {
+            "COLD" => Some(self.cold.clone()),
+            "WARM" => Some(self.warm.clone()),
+            "STOP" => Some(self.stop.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::container::{Container, PortDestination, SimpleContainer, WriteError};
+    use crate::runtime::factory::{FunctionBlockFactory, StandardFactory};
+    use crate::runtime::fb::FunctionBlock;
+    use crate::runtime::literal::LiteralError;
+    use crate::typelib::interface_list;
+    use std::time::Duration;
+
+    fn port(name: &str) -> PortDestination {
+        name.parse().unwrap()
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// The number of `EO` events fired by `c`.
+    fn count(container: &SimpleContainer<StandardFactory>) -> String {
+        container.read_watches()[0].ports[0].data.value.clone()
+    }
+
+    /// A container with the given blocks, watching the given ports.
+    fn container(blocks: &[(&str, &str)], watches: &[&str]) -> SimpleContainer<StandardFactory> {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut container = SimpleContainer::new(factory);
+        for (name, r#type) in blocks {
+            container.add_child(name.to_string(), r#type).unwrap();
+        }
+        for watch in watches {
+            container.add_watch(port(watch)).unwrap();
+        }
+        container
+    }
+
+    /// The value of a watched port, the number of events for event ports.
+    fn value(container: &SimpleContainer<StandardFactory>, name: &str) -> String {
+        let port = port(name);
+        container
+            .read_watches()
+            .into_iter()
+            .filter(|block| block.name == port.block())
+            .flat_map(|block| block.ports)
+            .find(|watched| watched.name == port.port())
+            .map(|watched| watched.data.value)
+            .unwrap()
+    }
+
+    /// The values of watched ports.
+    fn values(container: &SimpleContainer<StandardFactory>, names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| value(container, name)).collect()
+    }
+
+    /// Write the inputs, then send the event and handle all resulting events.
+    fn send(
+        container: &mut SimpleContainer<StandardFactory>,
+        event: &str,
+        inputs: &[(&str, &str)],
+    ) {
+        for (name, value) in inputs {
+            container.write(value, port(name)).unwrap();
+        }
+        container.write("$e", port(event)).unwrap();
+        container.dispatch();
+    }
+
+    #[test]
+    fn standard_types() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let types = factory.types();
+        for r#type in [
+            "E_SPLIT",
+            "E_MERGE",
+            "E_REND",
+            "E_PERMIT",
+            "E_SELECT",
+            "E_SWITCH",
+            "E_SR",
+            "E_RS",
+            "E_D_FF",
+            "E_CTU",
+            "E_CTD",
+            "E_CTUD",
+            "E_R_TRIG",
+            "E_F_TRIG",
+            "E_DELAY",
+            "E_DELAYR",
+            "E_CYCLE",
+            "E_TRAIN",
+            "E_TABLE",
+            "E_DEMUX",
+            "E_N_TABLE",
+            "E_RESTART",
+        ] {
+            assert!(
+                types.iter().any(|t| t == r#type),
+                "{type} is not registered"
+            );
+            let fb = factory.create(r#type).unwrap();
+            assert_eq!(fb.type_name(), r#type);
+        }
+    }
+
+    #[test]
+    fn routing() {
+        let mut container = container(
+            &[
+                ("split", "E_SPLIT"),
+                ("merge", "E_MERGE"),
+                ("permit", "E_PERMIT"),
+                ("select", "E_SELECT"),
+                ("switch", "E_SWITCH"),
+                ("demux", "E_DEMUX"),
+            ],
+            &[
+                "split.EO1",
+                "split.EO2",
+                "merge.EO",
+                "permit.EO",
+                "select.EO",
+                "switch.EO0",
+                "switch.EO1",
+                "demux.EO0",
+                "demux.EO2",
+            ],
+        );
+
+        send(&mut container, "split.EI", &[]);
+        assert_eq!(values(&container, &["split.EO1", "split.EO2"]), ["1", "1"]);
+
+        send(&mut container, "merge.EI1", &[]);
+        send(&mut container, "merge.EI2", &[]);
+        assert_eq!(value(&container, "merge.EO"), "2");
+
+        send(&mut container, "permit.EI", &[]);
+        send(&mut container, "permit.EI", &[("permit.PERMIT", "TRUE")]);
+        assert_eq!(value(&container, "permit.EO"), "1");
+
+        send(&mut container, "select.EI0", &[]);
+        send(&mut container, "select.EI1", &[]);
+        send(&mut container, "select.EI1", &[("select.G", "TRUE")]);
+        send(&mut container, "select.EI0", &[]);
+        assert_eq!(value(&container, "select.EO"), "2");
+
+        send(&mut container, "switch.EI", &[]);
+        send(&mut container, "switch.EI", &[("switch.G", "TRUE")]);
+        send(&mut container, "switch.EI", &[]);
+        assert_eq!(
+            values(&container, &["switch.EO0", "switch.EO1"]),
+            ["1", "2"]
+        );
+
+        send(&mut container, "demux.EI", &[]);
+        send(&mut container, "demux.EI", &[("demux.K", "2")]);
+        send(&mut container, "demux.EI", &[("demux.K", "4")]);
+        assert_eq!(values(&container, &["demux.EO0", "demux.EO2"]), ["1", "1"]);
+    }
+
+    #[test]
+    fn rendezvous() {
+        let mut container = container(&[("r", "E_REND")], &["r.EO"]);
+
+        send(&mut container, "r.EI1", &[]);
+        send(&mut container, "r.EI1", &[]);
+        assert_eq!(value(&container, "r.EO"), "0");
+        send(&mut container, "r.EI2", &[]);
+        assert_eq!(value(&container, "r.EO"), "1");
+
+        send(&mut container, "r.EI2", &[]);
+        send(&mut container, "r.R", &[]);
+        send(&mut container, "r.EI1", &[]);
+        assert_eq!(value(&container, "r.EO"), "1");
+        send(&mut container, "r.EI2", &[]);
+        assert_eq!(value(&container, "r.EO"), "2");
+    }
+
+    #[test]
+    fn bistables() {
+        let mut container = container(
+            &[("sr", "E_SR"), ("rs", "E_RS"), ("ff", "E_D_FF")],
+            &["sr.EO", "sr.Q", "rs.EO", "ff.EO", "ff.Q"],
+        );
+
+        send(&mut container, "sr.R", &[]);
+        assert_eq!(values(&container, &["sr.EO", "sr.Q"]), ["0", "FALSE"]);
+        send(&mut container, "sr.S", &[]);
+        send(&mut container, "sr.S", &[]);
+        assert_eq!(values(&container, &["sr.EO", "sr.Q"]), ["1", "TRUE"]);
+        send(&mut container, "sr.R", &[]);
+        assert_eq!(values(&container, &["sr.EO", "sr.Q"]), ["2", "FALSE"]);
+
+        send(&mut container, "rs.S", &[]);
+        send(&mut container, "rs.R", &[]);
+        assert_eq!(value(&container, "rs.EO"), "2");
+
+        send(&mut container, "ff.CLK", &[]);
+        assert_eq!(values(&container, &["ff.EO", "ff.Q"]), ["0", "FALSE"]);
+        send(&mut container, "ff.CLK", &[("ff.D", "TRUE")]);
+        send(&mut container, "ff.CLK", &[]);
+        assert_eq!(values(&container, &["ff.EO", "ff.Q"]), ["1", "TRUE"]);
+        send(&mut container, "ff.CLK", &[("ff.D", "FALSE")]);
+        assert_eq!(values(&container, &["ff.EO", "ff.Q"]), ["2", "FALSE"]);
+    }
+
+    #[test]
+    fn counters() {
+        let mut container = container(
+            &[("up", "E_CTU"), ("down", "E_CTD"), ("both", "E_CTUD")],
+            &[
+                "up.Q", "up.CV", "down.CDO", "down.Q", "down.CV", "both.QU", "both.QD", "both.CV",
+            ],
+        );
+
+        send(&mut container, "up.CU", &[("up.PV", "2")]);
+        assert_eq!(values(&container, &["up.Q", "up.CV"]), ["FALSE", "1"]);
+        send(&mut container, "up.CU", &[]);
+        assert_eq!(values(&container, &["up.Q", "up.CV"]), ["TRUE", "2"]);
+        send(&mut container, "up.R", &[]);
+        assert_eq!(values(&container, &["up.Q", "up.CV"]), ["FALSE", "0"]);
+
+        // the counter doesn't go below 0
+        send(&mut container, "down.CD", &[]);
+        assert_eq!(value(&container, "down.CDO"), "0");
+        send(&mut container, "down.LD", &[("down.PV", "2")]);
+        send(&mut container, "down.CD", &[]);
+        assert_eq!(values(&container, &["down.Q", "down.CV"]), ["FALSE", "1"]);
+        send(&mut container, "down.CD", &[]);
+        send(&mut container, "down.CD", &[]);
+        assert_eq!(values(&container, &["down.Q", "down.CV"]), ["TRUE", "0"]);
+        assert_eq!(value(&container, "down.CDO"), "2");
+
+        let both = ["both.QU", "both.QD", "both.CV"];
+        send(&mut container, "both.CU", &[("both.PV", "1")]);
+        assert_eq!(values(&container, &both), ["TRUE", "FALSE", "1"]);
+        send(&mut container, "both.CD", &[]);
+        assert_eq!(values(&container, &both), ["FALSE", "TRUE", "0"]);
+        send(&mut container, "both.LD", &[("both.PV", "5")]);
+        assert_eq!(values(&container, &both), ["TRUE", "FALSE", "5"]);
+        send(&mut container, "both.R", &[]);
+        assert_eq!(values(&container, &both), ["FALSE", "TRUE", "0"]);
+    }
+
+    #[test]
+    fn edges() {
+        let mut container = container(
+            &[("rise", "E_R_TRIG"), ("fall", "E_F_TRIG")],
+            &["rise.EO", "fall.EO"],
+        );
+
+        for qi in ["FALSE", "TRUE", "TRUE", "FALSE", "TRUE", "FALSE", "FALSE"] {
+            send(&mut container, "rise.EI", &[("rise.QI", qi)]);
+            send(&mut container, "fall.EI", &[("fall.QI", qi)]);
+        }
+        assert_eq!(values(&container, &["rise.EO", "fall.EO"]), ["2", "2"]);
+    }
+
+    #[test]
+    fn delays() {
+        let mut container = container(&[("d", "E_DELAY"), ("dr", "E_DELAYR")], &["d.EO", "dr.EO"]);
+
+        send(&mut container, "d.START", &[("d.DT", "T#10ms")]);
+        send(&mut container, "dr.START", &[("dr.DT", "T#10ms")]);
+        container.advance(ms(5));
+        send(&mut container, "d.START", &[]);
+        send(&mut container, "dr.START", &[]);
+
+        // the retriggered delay starts over
+        container.advance(ms(10));
+        assert_eq!(values(&container, &["d.EO", "dr.EO"]), ["1", "0"]);
+        container.advance(ms(15));
+        assert_eq!(values(&container, &["d.EO", "dr.EO"]), ["1", "1"]);
+        assert_eq!(container.next_deadline(), None);
+
+        send(&mut container, "d.START", &[]);
+        send(&mut container, "d.STOP", &[]);
+        container.advance(ms(100));
+        assert_eq!(value(&container, "d.EO"), "1");
+    }
+
+    #[test]
+    fn trains() {
+        let mut container = container(
+            &[("t", "E_TRAIN"), ("table", "E_TABLE"), ("n", "E_N_TABLE")],
+            &[
+                "t.EO", "t.CV", "table.EO", "table.CV", "n.EO0", "n.EO1", "n.EO2",
+            ],
+        );
+
+        send(
+            &mut container,
+            "t.START",
+            &[("t.DT", "T#10ms"), ("t.N", "3")],
+        );
+        send(
+            &mut container,
+            "table.START",
+            &[("table.DT", "[T#5ms, T#20ms, T#1ms]"), ("table.N", "2")],
+        );
+        send(
+            &mut container,
+            "n.START",
+            &[("n.DT", "[T#10ms, T#10ms]"), ("n.N", "2")],
+        );
+
+        container.advance(ms(5));
+        assert_eq!(values(&container, &["table.EO", "table.CV"]), ["1", "0"]);
+        container.advance(ms(10));
+        assert_eq!(values(&container, &["t.EO", "t.CV"]), ["1", "0"]);
+        assert_eq!(values(&container, &["n.EO0", "n.EO1"]), ["1", "0"]);
+        container.advance(ms(20));
+        assert_eq!(values(&container, &["t.EO", "t.CV"]), ["2", "1"]);
+        assert_eq!(values(&container, &["n.EO0", "n.EO1"]), ["1", "1"]);
+        container.advance(ms(25));
+        assert_eq!(values(&container, &["table.EO", "table.CV"]), ["2", "1"]);
+        container.advance(ms(30));
+        assert_eq!(values(&container, &["t.EO", "t.CV"]), ["3", "2"]);
+
+        // all trains are done
+        assert_eq!(container.next_deadline(), None);
+        assert_eq!(value(&container, "n.EO2"), "0");
+
+        send(&mut container, "t.START", &[]);
+        container.advance(ms(40));
+        send(&mut container, "t.STOP", &[]);
+        container.advance(ms(100));
+        assert_eq!(value(&container, "t.EO"), "4");
+    }
+
+    #[test]
+    fn table_arrays() {
+        let mut container = container(&[("table", "E_TABLE")], &["table.EO", "table.CV"]);
+
+        // the whole array is written, missing elements are initialized
+        send(
+            &mut container,
+            "table.START",
+            &[
+                ("table.DT", "[T#5ms, T#5ms, T#5ms, T#5ms]"),
+                ("table.N", "4"),
+            ],
+        );
+        container.advance(ms(5));
+        container.advance(ms(10));
+        assert_eq!(value(&container, "table.EO"), "2");
+        send(&mut container, "table.START", &[("table.DT", "[T#5ms]")]);
+        for _ in 0..4 {
+            container.advance(ms(15));
+        }
+        assert_eq!(values(&container, &["table.EO", "table.CV"]), ["6", "3"]);
+        assert_eq!(container.next_deadline(), None);
+
+        assert!(matches!(
+            container.write("[T#1ms, T#1ms, T#1ms, T#1ms, T#1ms]", port("table.DT")),
+            Err(WriteError::InvalidValue(LiteralError::ArrayLength(4)))
+        ));
+        assert!(matches!(
+            container.write("[T#1ms, 5]", port("table.DT")),
+            Err(WriteError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            container.write("T#1ms", port("table.DT")),
+            Err(WriteError::InvalidValue(LiteralError::InvalidArray(_)))
+        ));
+        assert!(matches!(
+            container.write("[T#1ms]$F", port("table.DT")),
+            Err(WriteError::ArrayForced)
+        ));
+        assert!(matches!(
+            container.write("T#1ms", port("table.DT0")),
+            Err(WriteError::UnknownPort)
+        ));
+
+        // reported to 4diac IDE as an array
+        let interface = interface_list(&super::NTable::new().interface());
+        assert_eq!(interface.input_vars.variables[0].name, "DT");
+        assert_eq!(
+            interface.input_vars.variables[0].array_size.as_deref(),
+            Some("4")
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut container = SimpleContainer::new(factory);
+        container.add_child("c".into(), "E_CYCLE").unwrap();
+        container.add_watch(port("c.EO")).unwrap();
+
+        container.write("T#100ms", port("c.DT")).unwrap();
+        container.write("$e", port("c.START")).unwrap();
+        container.dispatch();
+        assert_eq!(container.next_deadline(), Some(ms(100)));
+
+        container.advance(ms(99));
+        assert_eq!(count(&container), "0");
+        container.advance(ms(100));
+        assert_eq!(count(&container), "1");
+
+        // a late timer doesn't delay the following ones
+        container.advance(ms(250));
+        assert_eq!(count(&container), "2");
+        assert_eq!(container.next_deadline(), Some(ms(300)));
+
+        // missed periods are skipped
+        container.advance(ms(1010));
+        assert_eq!(count(&container), "3");
+        assert_eq!(container.next_deadline(), Some(ms(1100)));
+
+        container.write("$e", port("c.STOP")).unwrap();
+        container.dispatch();
+        assert_eq!(container.next_deadline(), None);
+        container.advance(ms(2000));
+        assert_eq!(count(&container), "3");
+
+        // the period must be positive
+        container.write("T#0s", port("c.DT")).unwrap();
+        container.write("$e", port("c.START")).unwrap();
+        container.dispatch();
+        assert_eq!(container.next_deadline(), None);
+    }
 }
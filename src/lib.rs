@@ -1,3 +1,5 @@
 pub mod blocks;
 pub mod protocol;
 pub mod runtime;
+pub mod st;
+pub mod typelib;
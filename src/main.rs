@@ -11,6 +11,10 @@ async fn main() -> anyhow::Result<()> {
 
     let mut factory = StandardFactory::new();
     factory.register_standard_types();
+    if let Ok(path) = std::env::var("TOREF_TYPE_LIBRARY") {
+        let types = toref::typelib::load_directory(&mut factory, &path)?;
+        log::info!("Loaded {} types from {path}", types.len());
+    }
     let runtime = Runtime::new(factory);
     let requests = runtime.requests();
 
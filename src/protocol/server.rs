@@ -1,5 +1,7 @@
 use crate::protocol::RequestTarget;
-use crate::runtime::container::{AddError, ConnectError};
+use crate::runtime::container::{AddError, ConnectError, WatchError, WriteError};
+use crate::runtime::emb_res::StateError;
+use crate::typelib::{fbt, LoadError};
 use bytes::{Buf, BytesMut};
 use std::io::{Cursor, ErrorKind};
 use std::str::from_utf8;
@@ -77,9 +79,19 @@ pub enum Data {
     #[serde(rename_all = "PascalCase")]
     FunctionBlock {
         name: String,
+        /// Not given when starting or stopping a single function block.
+        #[serde(default)]
         r#type: String,
     },
-    Watches,
+    #[serde(rename_all = "PascalCase")]
+    Watch {
+        source: String,
+        destination: String,
+    },
+    Watches {
+        #[serde(default, rename = "Resource")]
+        resources: Vec<WatchedResource>,
+    },
     #[serde(rename_all = "PascalCase")]
     Connection {
         source: String,
@@ -87,13 +99,59 @@ pub enum Data {
     },
     #[serde(rename = "FBList")]
     FunctionBlockList(Vec<FunctionBlock>),
+    ConnectionList(Vec<ConnectionInfo>),
+    #[serde(rename = "DTList")]
+    DataTypeList(Vec<fbt::DataType>),
+    #[serde(rename = "AdapterList")]
+    AdapterTypeList(Vec<fbt::AdapterType>),
+    #[serde(rename = "FBType")]
+    FunctionBlockType(fbt::FbType),
+    AdapterType(fbt::AdapterType),
+    DataType(fbt::DataType),
 }
 
+/// A function block instance, or a function block type when listing the available types.
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename = "FB")]
 pub struct FunctionBlock {
     pub name: String,
-    pub r#type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+}
+
+/// A connection between two ports, as "block.port".
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Connection", rename_all = "PascalCase")]
+pub struct ConnectionInfo {
+    pub source: String,
+    pub destination: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WatchedResource {
+    pub name: String,
+    #[serde(default, rename = "FB")]
+    pub function_blocks: Vec<WatchedFunctionBlock>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WatchedFunctionBlock {
+    pub name: String,
+    #[serde(default, rename = "Port")]
+    pub ports: Vec<WatchedPort>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WatchedPort {
+    pub name: String,
+    #[serde(rename = "Data")]
+    pub data: WatchedData,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WatchedData {
+    pub value: String,
+    pub forced: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -154,6 +212,55 @@ impl From<ConnectError> for Error {
     fn from(err: ConnectError) -> Self {
         match err {
             ConnectError::UnknownBlock | ConnectError::UnknownPort => Error::InvalidDestination,
+            ConnectError::AlreadyConnected => Error::InvalidState,
+            ConnectError::NotConnected => Error::NoSuchObject,
+            ConnectError::TypeMismatch { .. } | ConnectError::GenericMismatch { .. } => {
+                Error::InvalidObject
+            }
+            ConnectError::UnresolvedType { .. } | ConnectError::Unconvertible(_) => {
+                Error::InvalidState
+            }
+        }
+    }
+}
+
+impl From<WriteError> for Error {
+    fn from(err: WriteError) -> Self {
+        match err {
+            WriteError::UnknownBlock | WriteError::UnknownPort => Error::NoSuchObject,
+            WriteError::InvalidValue(_) => Error::InvalidObject,
+            WriteError::ArrayForced => Error::InvalidOperation,
+        }
+    }
+}
+
+impl From<WatchError> for Error {
+    fn from(err: WatchError) -> Self {
+        match err {
+            WatchError::UnknownBlock | WatchError::UnknownPort | WatchError::NotWatched => {
+                Error::NoSuchObject
+            }
+        }
+    }
+}
+
+impl From<StateError> for Error {
+    fn from(err: StateError) -> Self {
+        match err {
+            StateError::InvalidTransition { .. } => Error::InvalidState,
+        }
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(err: LoadError) -> Self {
+        match err {
+            LoadError::Unsupported(_) | LoadError::UnknownType(_) => Error::UnsupportedType,
+            LoadError::Io(_)
+            | LoadError::Xml(_)
+            | LoadError::InvalidType(_)
+            | LoadError::InvalidValue { .. }
+            | LoadError::InvalidCode { .. } => Error::InvalidObject,
         }
     }
 }
@@ -215,12 +322,8 @@ impl Connection {
 
         self.stream.write_u8(TYPE_STRING).await?;
 
-        let buf = quick_xml::se::to_string(&response).map_err(|err| {
-            io::Error::new(
-                ErrorKind::Other,
-                format!("Failed to encode response: {err}"),
-            )
-        })?;
+        let buf = quick_xml::se::to_string(&response)
+            .map_err(|err| io::Error::other(format!("Failed to encode response: {err}")))?;
         if buf.len() > u16::MAX as usize {
             return Err(io::Error::new(
                 ErrorKind::OutOfMemory,
@@ -326,7 +429,7 @@ mod test {
 
     #[test]
     fn decode() {
-        let request: Request = quick_xml::de::from_str(
+        let _request: Request = quick_xml::de::from_str(
             r#"
         <Request ID="1" Action="QUERY">
             <FB Name="*" Type="*"/>
@@ -335,4 +438,136 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    fn decode_read_watches() {
+        let request: Request =
+            quick_xml::de::from_str(r#"<Request ID="2" Action="READ"><Watches/></Request>"#)
+                .unwrap();
+
+        assert_eq!(request.data, Some(Data::Watches { resources: vec![] }));
+    }
+
+    #[test]
+    fn decode_lifecycle() {
+        let request: Request =
+            quick_xml::de::from_str(r#"<Request ID="3" Action="STOP"><FB Name="fb1"/></Request>"#)
+                .unwrap();
+
+        assert_eq!(request.action, Action::Stop);
+        assert_eq!(
+            request.data,
+            Some(Data::FunctionBlock {
+                name: "fb1".into(),
+                r#type: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn decode_type() {
+        let request: Request = quick_xml::de::from_str(
+            r#"
+        <Request ID="4" Action="CREATE">
+            <FBType Name="E_PASS" Comment="Forward an event">
+                <Identification Standard="61499-1"/>
+                <InterfaceList>
+                    <EventInputs><Event Name="EI"/></EventInputs>
+                    <EventOutputs><Event Name="EO"/></EventOutputs>
+                </InterfaceList>
+                <FBNetwork>
+                    <EventConnections><Connection Source="EI" Destination="EO"/></EventConnections>
+                </FBNetwork>
+            </FBType>
+        </Request>
+        "#,
+        )
+        .unwrap();
+
+        let Some(Data::FunctionBlockType(r#type)) = request.data else {
+            panic!("Unexpected data: {:?}", request.data);
+        };
+        assert_eq!(r#type.name, "E_PASS");
+        assert_eq!(r#type.interface_list.event_inputs.events[0].name, "EI");
+        assert_eq!(
+            r#type.network.unwrap().event_connections.connections[0].destination,
+            "EO"
+        );
+
+        let request: Request = quick_xml::de::from_str(
+            r#"<Request ID="5" Action="CREATE"><DataType Name="POINT"/></Request>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request.data,
+            Some(Data::DataType(fbt::DataType {
+                name: "POINT".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn encode_watches() {
+        let response = Response {
+            id: "3".to_string(),
+            reason: None,
+            data: Some(Data::Watches {
+                resources: vec![WatchedResource {
+                    name: "EMB_RES".to_string(),
+                    function_blocks: vec![WatchedFunctionBlock {
+                        name: "sr".to_string(),
+                        ports: vec![WatchedPort {
+                            name: "Q".to_string(),
+                            data: WatchedData {
+                                value: "TRUE".to_string(),
+                                forced: false,
+                            },
+                        }],
+                    }],
+                }],
+            }),
+        };
+
+        assert_eq!(
+            quick_xml::se::to_string(&response).unwrap(),
+            r#"<Response ID="3"><Watches><Resource name="EMB_RES"><FB name="sr"><Port name="Q"><Data value="TRUE" forced="false"/></Port></FB></Resource></Watches></Response>"#
+        );
+    }
+
+    #[test]
+    fn encode_lists() {
+        let response = |data| Response {
+            id: "6".to_string(),
+            reason: None,
+            data: Some(data),
+        };
+
+        assert_eq!(
+            quick_xml::se::to_string(&response(Data::FunctionBlockList(vec![FunctionBlock {
+                name: "E_SR".to_string(),
+                r#type: None,
+            }])))
+            .unwrap(),
+            r#"<Response ID="6"><FBList><FB name="E_SR"/></FBList></Response>"#
+        );
+        assert_eq!(
+            quick_xml::se::to_string(&response(Data::ConnectionList(vec![ConnectionInfo {
+                source: "sr.Q".to_string(),
+                destination: "sw.G".to_string(),
+            }])))
+            .unwrap(),
+            r#"<Response ID="6"><ConnectionList><Connection Source="sr.Q" Destination="sw.G"/></ConnectionList></Response>"#
+        );
+        assert_eq!(
+            quick_xml::se::to_string(&response(Data::DataTypeList(vec![fbt::DataType {
+                name: "BOOL".to_string(),
+            }])))
+            .unwrap(),
+            r#"<Response ID="6"><DTList><DataType Name="BOOL"/></DTList></Response>"#
+        );
+        assert_eq!(
+            quick_xml::se::to_string(&response(Data::AdapterTypeList(vec![]))).unwrap(),
+            r#"<Response ID="6"><AdapterList></AdapterList></Response>"#
+        );
+    }
 }
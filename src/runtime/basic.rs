@@ -0,0 +1,916 @@
+//! Basic function blocks, driven by an Execution Control Chart (ECC).
+//!
+//! A [`BasicType`] declares the interface, internal variables, algorithms and the ECC of a
+//! basic function block type. Instances of the type are created as [`BasicFunctionBlock`]:
+//!
+//! ```
+//! use toref::runtime::basic::{Action, BasicFunctionBlock, BasicType, Guard};
+//! use toref::runtime::value::{IecType, IecValue};
+//! use std::sync::Arc;
+//!
+//! let r#type = BasicType::builder("E_SR")
+//!     .event_input("S", &[])
+//!     .event_input("R", &[])
+//!     .event_output("EO", &["Q"])
+//!     .output("Q", IecType::Bool)
+//!     .algorithm("SET", |vars| vars.set("Q", IecValue::Bool(true)))
+//!     .algorithm("RESET", |vars| vars.set("Q", IecValue::Bool(false)))
+//!     .state("START", [])
+//!     .state("SET", [Action::algorithm("SET").output("EO")])
+//!     .state("RESET", [Action::algorithm("RESET").output("EO")])
+//!     .transition("START", "SET", Guard::event("S"))
+//!     .transition("SET", "RESET", Guard::event("R"))
+//!     .transition("RESET", "SET", Guard::event("S"))
+//!     .build()
+//!     .unwrap();
+//!
+//! let fb = BasicFunctionBlock::new(Arc::new(r#type));
+//! ```
+
+use crate::protocol::server;
+use crate::runtime::fb::{
+    DataInput, DataOutput, EventContext, EventInput, EventOutput, FunctionBlock, GenericType,
+};
+use crate::runtime::interface::{EventDeclaration, Interface, VariableDeclaration};
+use crate::runtime::value::{self, ConversionError, IecType, IecValue};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// Upper limit of transitions taken while handling a single event.
+///
+/// This only guards against an ECC looping through event-less transitions forever.
+const MAX_TRANSITIONS: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ExecutionError {
+    #[error("Unknown variable: {0}")]
+    UnknownVariable(String),
+    #[error("Variable {name} is of type {expected}, not {actual}")]
+    TypeMismatch {
+        name: String,
+        expected: IecType,
+        actual: IecType,
+    },
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<ConversionError> for ExecutionError {
+    fn from(err: ConversionError) -> Self {
+        Self::Failed(err.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TypeError {
+    #[error("Duplicate name: {0}")]
+    DuplicateName(String),
+    #[error("Unknown state: {0}")]
+    UnknownState(String),
+    #[error("Unknown event: {0}")]
+    UnknownEvent(String),
+    #[error("Unknown variable: {0}")]
+    UnknownVariable(String),
+    #[error("Unknown algorithm: {0}")]
+    UnknownAlgorithm(String),
+    #[error("Initial value of {name} is not of type {r#type}")]
+    InvalidInitialValue { name: String, r#type: IecType },
+    #[error("Unknown block: {0}")]
+    UnknownBlock(String),
+    #[error("Unknown port: {0}")]
+    UnknownPort(String),
+    #[error("Invalid name: {0}")]
+    InvalidName(String),
+    #[error("The ECC has no states")]
+    NoStates,
+}
+
+/// The code executed in an ECC action.
+pub trait Algorithm: Send + Sync {
+    fn execute(&self, variables: &mut Variables) -> Result<(), ExecutionError>;
+}
+
+impl<F> Algorithm for F
+where
+    F: Fn(&mut Variables) -> Result<(), ExecutionError> + Send + Sync,
+{
+    fn execute(&self, variables: &mut Variables) -> Result<(), ExecutionError> {
+        (self)(variables)
+    }
+}
+
+/// The boolean condition of an ECC transition.
+pub trait Condition: Send + Sync {
+    fn evaluate(&self, variables: &Variables) -> Result<bool, ExecutionError>;
+}
+
+impl<F> Condition for F
+where
+    F: Fn(&Variables) -> Result<bool, ExecutionError> + Send + Sync,
+{
+    fn evaluate(&self, variables: &Variables) -> Result<bool, ExecutionError> {
+        (self)(variables)
+    }
+}
+
+/// The names and types of the variables of a function block type.
+#[derive(Debug)]
+pub struct Layout {
+    variables: Vec<VariableDeclaration>,
+    index: HashMap<String, usize>,
+}
+
+impl Layout {
+    pub(crate) fn new(variables: Vec<VariableDeclaration>) -> Result<Self, TypeError> {
+        let mut index = HashMap::with_capacity(variables.len());
+        for (i, var) in variables.iter().enumerate() {
+            if index.insert(var.name.clone(), i).is_some() {
+                return Err(TypeError::DuplicateName(var.name.clone()));
+            }
+            if let Some(initial) = &var.initial {
+                if initial.r#type() != var.r#type {
+                    return Err(TypeError::InvalidInitialValue {
+                        name: var.name.clone(),
+                        r#type: var.r#type,
+                    });
+                }
+            }
+        }
+        Ok(Self { variables, index })
+    }
+
+    /// The index of a variable.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    pub fn variables(&self) -> &[VariableDeclaration] {
+        &self.variables
+    }
+}
+
+/// The variables of a function block instance: data inputs, data outputs and internal
+/// variables, in that order.
+#[derive(Clone, Debug)]
+pub struct Variables {
+    layout: Arc<Layout>,
+    values: Vec<IecValue>,
+}
+
+impl Variables {
+    pub fn new(layout: Arc<Layout>) -> Self {
+        let values = layout
+            .variables
+            .iter()
+            .map(VariableDeclaration::initial_value)
+            .collect();
+        Self { layout, values }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn get(&self, name: &str) -> Result<&IecValue, ExecutionError> {
+        self.layout
+            .index(name)
+            .map(|i| &self.values[i])
+            .ok_or_else(|| ExecutionError::UnknownVariable(name.to_string()))
+    }
+
+    /// Get the value of a `BOOL` variable.
+    pub fn get_bool(&self, name: &str) -> Result<bool, ExecutionError> {
+        match self.get(name)? {
+            IecValue::Bool(value) => Ok(*value),
+            value => Err(ExecutionError::TypeMismatch {
+                name: name.to_string(),
+                expected: IecType::Bool,
+                actual: value.r#type(),
+            }),
+        }
+    }
+
+    /// Set a variable, the value must match the declared type of the variable.
+    pub fn set(&mut self, name: &str, value: IecValue) -> Result<(), ExecutionError> {
+        let i = self
+            .layout
+            .index(name)
+            .ok_or_else(|| ExecutionError::UnknownVariable(name.to_string()))?;
+        self.set_at(i, value)
+            .map_err(|value| ExecutionError::TypeMismatch {
+                name: name.to_string(),
+                expected: self.layout.variables[i].r#type,
+                actual: value.r#type(),
+            })
+    }
+
+    pub fn get_at(&self, index: usize) -> &IecValue {
+        &self.values[index]
+    }
+
+    /// Set a variable by index, returning the value if it doesn't match the declared type.
+    pub fn set_at(&mut self, index: usize, value: IecValue) -> Result<(), IecValue> {
+        if value.r#type() != self.layout.variables[index].r#type {
+            return Err(value);
+        }
+        self.values[index] = value;
+        Ok(())
+    }
+
+    /// Restore the initial values of all variables.
+    pub fn reset(&mut self) {
+        for (value, var) in self.values.iter_mut().zip(&self.layout.variables) {
+            *value = var.initial_value();
+        }
+    }
+}
+
+/// An action of an ECC state: an optional algorithm, followed by an optional output event.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Action {
+    pub algorithm: Option<String>,
+    pub output: Option<String>,
+}
+
+impl Action {
+    pub fn algorithm<A: Into<String>>(algorithm: A) -> Self {
+        Self {
+            algorithm: Some(algorithm.into()),
+            output: None,
+        }
+    }
+
+    pub fn output<O: Into<String>>(mut self, output: O) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// An action which only fires an output event.
+    pub fn fire<O: Into<String>>(output: O) -> Self {
+        Self::default().output(output)
+    }
+}
+
+/// The guard of an ECC transition: an optional event, and an optional condition.
+///
+/// A guard without an event is evaluated whenever the ECC settles, a guard without a
+/// condition is always true.
+#[derive(Clone, Default)]
+pub struct Guard {
+    event: Option<String>,
+    condition: Option<Arc<dyn Condition>>,
+}
+
+impl Debug for Guard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Guard")
+            .field("event", &self.event)
+            .field("condition", &self.condition.is_some())
+            .finish()
+    }
+}
+
+impl Guard {
+    /// A guard which is always true.
+    pub fn always() -> Self {
+        Self::default()
+    }
+
+    /// A guard which is true when the event is received.
+    pub fn event<E: Into<String>>(event: E) -> Self {
+        Self {
+            event: Some(event.into()),
+            condition: None,
+        }
+    }
+
+    /// Add a condition, which must be true as well.
+    pub fn when<F>(self, condition: F) -> Self
+    where
+        F: Fn(&Variables) -> Result<bool, ExecutionError> + Send + Sync + 'static,
+    {
+        self.with_condition(Arc::new(condition))
+    }
+
+    pub fn with_condition(mut self, condition: Arc<dyn Condition>) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+}
+
+struct State {
+    name: String,
+    /// Pairs of algorithm index and event output index.
+    actions: Vec<(Option<usize>, Option<usize>)>,
+    transitions: Vec<Transition>,
+}
+
+struct Transition {
+    destination: usize,
+    event: Option<usize>,
+    condition: Option<Arc<dyn Condition>>,
+}
+
+/// A basic function block type.
+pub struct BasicType {
+    name: String,
+    interface: Interface,
+    layout: Arc<Layout>,
+    /// Indices of the data inputs sampled by each event input.
+    with: Vec<Vec<usize>>,
+    algorithms: Vec<(String, Arc<dyn Algorithm>)>,
+    states: Vec<State>,
+}
+
+impl BasicType {
+    pub fn builder<N: Into<String>>(name: N) -> BasicTypeBuilder {
+        BasicTypeBuilder {
+            name: name.into(),
+            interface: Default::default(),
+            internals: vec![],
+            algorithms: vec![],
+            states: vec![],
+            transitions: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn interface(&self) -> &Interface {
+        &self.interface
+    }
+
+    pub fn layout(&self) -> &Arc<Layout> {
+        &self.layout
+    }
+}
+
+/// Builds a [`BasicType`], checking all references when building.
+pub struct BasicTypeBuilder {
+    name: String,
+    interface: Interface,
+    internals: Vec<VariableDeclaration>,
+    algorithms: Vec<(String, Arc<dyn Algorithm>)>,
+    states: Vec<(String, Vec<Action>)>,
+    transitions: Vec<(String, String, Guard)>,
+}
+
+impl BasicTypeBuilder {
+    pub fn event_input<N: Into<String>>(mut self, name: N, with: &[&str]) -> Self {
+        self.interface
+            .event_inputs
+            .push(EventDeclaration::new(name, with.iter().copied()));
+        self
+    }
+
+    pub fn event_output<N: Into<String>>(mut self, name: N, with: &[&str]) -> Self {
+        self.interface
+            .event_outputs
+            .push(EventDeclaration::new(name, with.iter().copied()));
+        self
+    }
+
+    pub fn input<N: Into<String>>(self, name: N, r#type: IecType) -> Self {
+        self.input_declaration(VariableDeclaration::new(name, r#type))
+    }
+
+    pub fn input_declaration(mut self, declaration: VariableDeclaration) -> Self {
+        self.interface.data_inputs.push(declaration);
+        self
+    }
+
+    pub fn output<N: Into<String>>(self, name: N, r#type: IecType) -> Self {
+        self.output_declaration(VariableDeclaration::new(name, r#type))
+    }
+
+    pub fn output_declaration(mut self, declaration: VariableDeclaration) -> Self {
+        self.interface.data_outputs.push(declaration);
+        self
+    }
+
+    pub fn internal<N: Into<String>>(self, name: N, r#type: IecType) -> Self {
+        self.internal_declaration(VariableDeclaration::new(name, r#type))
+    }
+
+    pub fn internal_declaration(mut self, declaration: VariableDeclaration) -> Self {
+        self.internals.push(declaration);
+        self
+    }
+
+    /// The variables declared so far, in the order of the [`Layout`] of the type.
+    pub fn variables(&self) -> impl Iterator<Item = &VariableDeclaration> {
+        self.interface
+            .data_inputs
+            .iter()
+            .chain(&self.interface.data_outputs)
+            .chain(&self.internals)
+    }
+
+    pub fn algorithm<N, F>(self, name: N, algorithm: F) -> Self
+    where
+        N: Into<String>,
+        F: Fn(&mut Variables) -> Result<(), ExecutionError> + Send + Sync + 'static,
+    {
+        self.algorithm_impl(name, Arc::new(algorithm))
+    }
+
+    pub fn algorithm_impl<N: Into<String>>(
+        mut self,
+        name: N,
+        algorithm: Arc<dyn Algorithm>,
+    ) -> Self {
+        self.algorithms.push((name.into(), algorithm));
+        self
+    }
+
+    /// Add a state, the first state is the initial state.
+    pub fn state<N, A>(mut self, name: N, actions: A) -> Self
+    where
+        N: Into<String>,
+        A: IntoIterator<Item = Action>,
+    {
+        self.states
+            .push((name.into(), actions.into_iter().collect()));
+        self
+    }
+
+    /// Add a transition, transitions leaving a state are evaluated in the order they are added.
+    pub fn transition<S, D>(mut self, source: S, destination: D, guard: Guard) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.transitions
+            .push((source.into(), destination.into(), guard));
+        self
+    }
+
+    pub fn build(self) -> Result<BasicType, TypeError> {
+        let mut names = HashSet::new();
+        for name in self
+            .interface
+            .event_inputs
+            .iter()
+            .chain(&self.interface.event_outputs)
+            .map(|event| &event.name)
+        {
+            if !names.insert(name) {
+                return Err(TypeError::DuplicateName(name.clone()));
+            }
+        }
+
+        let layout = Arc::new(Layout::new(self.variables().cloned().collect())?);
+        let interface = self.interface;
+
+        let with = interface
+            .event_inputs
+            .iter()
+            .map(|event| {
+                event
+                    .with
+                    .iter()
+                    .map(|var| {
+                        interface
+                            .data_inputs
+                            .iter()
+                            .position(|input| &input.name == var)
+                            .ok_or_else(|| TypeError::UnknownVariable(var.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for var in interface.event_outputs.iter().flat_map(|event| &event.with) {
+            if interface.data_output(var).is_none() {
+                return Err(TypeError::UnknownVariable(var.clone()));
+            }
+        }
+
+        let algorithm = |name: &String| {
+            self.algorithms
+                .iter()
+                .position(|(n, _)| n == name)
+                .ok_or_else(|| TypeError::UnknownAlgorithm(name.clone()))
+        };
+        let output = |name: &String| {
+            interface
+                .event_outputs
+                .iter()
+                .position(|event| &event.name == name)
+                .ok_or_else(|| TypeError::UnknownEvent(name.clone()))
+        };
+        let state = |name: &String| {
+            self.states
+                .iter()
+                .position(|(n, _)| n == name)
+                .ok_or_else(|| TypeError::UnknownState(name.clone()))
+        };
+
+        if self.states.is_empty() {
+            return Err(TypeError::NoStates);
+        }
+
+        let mut states = Vec::with_capacity(self.states.len());
+        for (i, (name, actions)) in self.states.iter().enumerate() {
+            if state(name)? != i {
+                return Err(TypeError::DuplicateName(name.clone()));
+            }
+            let actions = actions
+                .iter()
+                .map(|action| {
+                    Ok((
+                        action.algorithm.as_ref().map(algorithm).transpose()?,
+                        action.output.as_ref().map(output).transpose()?,
+                    ))
+                })
+                .collect::<Result<_, TypeError>>()?;
+            states.push(State {
+                name: name.clone(),
+                actions,
+                transitions: vec![],
+            });
+        }
+
+        for (source, destination, guard) in &self.transitions {
+            let event = guard
+                .event
+                .as_ref()
+                .map(|name| {
+                    interface
+                        .event_inputs
+                        .iter()
+                        .position(|event| &event.name == name)
+                        .ok_or_else(|| TypeError::UnknownEvent(name.clone()))
+                })
+                .transpose()?;
+            let transition = Transition {
+                destination: state(destination)?,
+                event,
+                condition: guard.condition.clone(),
+            };
+            states[state(source)?].transitions.push(transition);
+        }
+
+        Ok(BasicType {
+            name: self.name,
+            interface,
+            layout,
+            with,
+            algorithms: self.algorithms,
+            states,
+        })
+    }
+}
+
+/// An instance of a [`BasicType`].
+pub struct BasicFunctionBlock {
+    r#type: Arc<BasicType>,
+    state: usize,
+    variables: Variables,
+    event_inputs: Vec<EventInput>,
+    event_outputs: Vec<EventOutput>,
+    data_inputs: Vec<DataInput>,
+    data_outputs: Vec<DataOutput>,
+}
+
+impl BasicFunctionBlock {
+    pub fn new(r#type: Arc<BasicType>) -> Self {
+        let interface = &r#type.interface;
+        let data_inputs = interface
+            .data_inputs
+            .iter()
+            .map(|var| {
+                let input = match var.generic {
+                    Some(generic) => DataInput::generic(GenericType::new(generic)),
+                    None => DataInput::new(var.r#type),
+                };
+                input.set(var.initial_value());
+                input
+            })
+            .collect();
+        let data_outputs = interface
+            .data_outputs
+            .iter()
+            .map(|var| {
+                let output = match var.generic {
+                    Some(generic) => DataOutput::generic(GenericType::new(generic)),
+                    None => DataOutput::new(var.r#type),
+                };
+                output.set(var.initial_value());
+                output
+            })
+            .collect();
+
+        Self {
+            state: 0,
+            variables: Variables::new(r#type.layout.clone()),
+            event_inputs: interface
+                .event_inputs
+                .iter()
+                .map(|_| EventInput::new())
+                .collect(),
+            event_outputs: interface
+                .event_outputs
+                .iter()
+                .map(|_| EventOutput::new())
+                .collect(),
+            data_inputs,
+            data_outputs,
+            r#type,
+        }
+    }
+
+    /// Convert a value between a variable and its port, their types differ once a generic port
+    /// is resolved.
+    fn converted(&self, value: IecValue, r#type: IecType) -> IecValue {
+        if value.r#type() == r#type {
+            return value;
+        }
+        value::convert(&value, r#type).unwrap_or_else(|err| {
+            log::warn!("{}: {err}", self.r#type.name);
+            r#type.default_value()
+        })
+    }
+
+    /// The name of the current ECC state.
+    pub fn state(&self) -> &str {
+        &self.r#type.states[self.state].name
+    }
+
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Find the first transition of the current state which can be taken.
+    fn next_state(&self, event: Option<usize>) -> Option<usize> {
+        for transition in &self.r#type.states[self.state].transitions {
+            if transition.event.is_some() && transition.event != event {
+                continue;
+            }
+            let enabled = match &transition.condition {
+                None => true,
+                Some(condition) => match condition.evaluate(&self.variables) {
+                    Ok(enabled) => enabled,
+                    Err(err) => {
+                        log::warn!("{}: Failed to evaluate condition: {err}", self.r#type.name);
+                        false
+                    }
+                },
+            };
+            if enabled {
+                return Some(transition.destination);
+            }
+        }
+        None
+    }
+
+    fn enter_state(&mut self, state: usize, context: &mut EventContext) {
+        self.state = state;
+        let r#type = self.r#type.clone();
+        for (algorithm, output) in &r#type.states[state].actions {
+            if let Some(algorithm) = algorithm {
+                let (name, algorithm) = &r#type.algorithms[*algorithm];
+                if let Err(err) = algorithm.execute(&mut self.variables) {
+                    log::warn!("{}: Algorithm {name} failed: {err}", r#type.name);
+                }
+            }
+            if let Some(output) = output {
+                context.fire(r#type.interface.event_outputs[*output].name.as_str());
+            }
+        }
+    }
+}
+
+impl FunctionBlock for BasicFunctionBlock {
+    fn type_name(&self) -> String {
+        self.r#type.name.clone()
+    }
+
+    fn interface(&self) -> Interface {
+        self.r#type.interface.clone()
+    }
+
+    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+        self.r#type
+            .interface
+            .data_outputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_outputs[i].clone())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        self.r#type
+            .interface
+            .data_inputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_inputs[i].clone())
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        self.r#type
+            .interface
+            .event_outputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_outputs[i].clone())
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        self.r#type
+            .interface
+            .event_inputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_inputs[i].clone())
+    }
+
+    fn reset(&mut self) -> Result<(), server::Error> {
+        let interface = &self.r#type.interface;
+        for (input, var) in self.data_inputs.iter().zip(&interface.data_inputs) {
+            input.set(var.initial_value_as(input.r#type()));
+        }
+        for (output, var) in self.data_outputs.iter().zip(&interface.data_outputs) {
+            output.set(var.initial_value_as(output.r#type()));
+        }
+        self.variables.reset();
+        self.state = 0;
+        Ok(())
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        let Some(event) = self
+            .r#type
+            .interface
+            .event_inputs
+            .iter()
+            .position(|event| event.name == name)
+        else {
+            log::warn!("{}: Unknown event input: {name}", self.r#type.name);
+            return;
+        };
+
+        // sample the data inputs associated with the event, data inputs come first in the layout
+        for &i in &self.r#type.with[event] {
+            let r#type = self.variables.layout().variables[i].r#type;
+            let value = self.converted(self.data_inputs[i].get(), r#type);
+            let _ = self.variables.set_at(i, value);
+        }
+
+        let mut event = Some(event);
+        let mut transitions = 0;
+        while let Some(state) = self.next_state(event) {
+            event = None;
+            self.enter_state(state, context);
+
+            transitions += 1;
+            if transitions >= MAX_TRANSITIONS {
+                log::error!(
+                    "{}: ECC did not settle after {transitions} transitions",
+                    self.r#type.name
+                );
+                break;
+            }
+        }
+
+        let offset = self.data_inputs.len();
+        for (i, output) in self.data_outputs.iter().enumerate() {
+            let value = self.converted(self.variables.get_at(offset + i).clone(), output.r#type());
+            output.set(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn uint(vars: &Variables, name: &str) -> Result<u16, ExecutionError> {
+        match vars.get(name)? {
+            IecValue::Uint(value) => Ok(*value),
+            _ => Err(ExecutionError::Failed(format!("{name} is not a UINT"))),
+        }
+    }
+
+    fn counter() -> BasicFunctionBlock {
+        // E_CTU
+        let r#type = BasicType::builder("E_CTU")
+            .event_input("CU", &["PV"])
+            .event_input("R", &[])
+            .event_output("CUO", &["Q", "CV"])
+            .event_output("RO", &["Q", "CV"])
+            .input("PV", IecType::Uint)
+            .output("Q", IecType::Bool)
+            .output("CV", IecType::Uint)
+            .algorithm("CU", |vars| {
+                let cv = uint(vars, "CV")? + 1;
+                vars.set("CV", IecValue::Uint(cv))?;
+                vars.set("Q", IecValue::Bool(cv >= uint(vars, "PV")?))
+            })
+            .algorithm("R", |vars| {
+                vars.set("CV", IecValue::Uint(0))?;
+                vars.set("Q", IecValue::Bool(false))
+            })
+            .state("START", [])
+            .state("CU", [Action::algorithm("CU").output("CUO")])
+            .state("R", [Action::algorithm("R").output("RO")])
+            .transition(
+                "START",
+                "CU",
+                Guard::event("CU").when(|vars| Ok(uint(vars, "CV")? < 65535)),
+            )
+            .transition("START", "R", Guard::event("R"))
+            .transition("CU", "START", Guard::always())
+            .transition("R", "START", Guard::always())
+            .build()
+            .unwrap();
+
+        BasicFunctionBlock::new(Arc::new(r#type))
+    }
+
+    fn send(fb: &mut BasicFunctionBlock, event: &str) -> Vec<String> {
+        let mut context = EventContext::default();
+        fb.receive_event(event, &mut context);
+        context.take_fired()
+    }
+
+    #[test]
+    fn ecc() {
+        let mut fb = counter();
+        let pv = fb.get_data_input("PV").unwrap();
+        let q = fb.get_data_output("Q").unwrap();
+        let cv = fb.get_data_output("CV").unwrap();
+
+        pv.set(IecValue::Uint(2));
+        assert_eq!(send(&mut fb, "CU"), vec!["CUO"]);
+        assert_eq!(cv.get(), IecValue::Uint(1));
+        assert_eq!(q.get(), IecValue::Bool(false));
+        assert_eq!(fb.state(), "START");
+
+        assert_eq!(send(&mut fb, "CU"), vec!["CUO"]);
+        assert_eq!(cv.get(), IecValue::Uint(2));
+        assert_eq!(q.get(), IecValue::Bool(true));
+
+        assert_eq!(send(&mut fb, "R"), vec!["RO"]);
+        assert_eq!(cv.get(), IecValue::Uint(0));
+        assert_eq!(q.get(), IecValue::Bool(false));
+
+        assert!(send(&mut fb, "X").is_empty());
+    }
+
+    #[test]
+    fn samples_with_inputs_only() {
+        let mut fb = counter();
+        let pv = fb.get_data_input("PV").unwrap();
+
+        pv.set(IecValue::Uint(1));
+        send(&mut fb, "R");
+        assert_eq!(fb.variables().get("PV"), Ok(&IecValue::Uint(0)));
+
+        send(&mut fb, "CU");
+        assert_eq!(fb.variables().get("PV"), Ok(&IecValue::Uint(1)));
+    }
+
+    #[test]
+    fn invalid_types() {
+        let build = |builder: BasicTypeBuilder| builder.build().err();
+
+        assert_eq!(
+            build(BasicType::builder("X").state("START", [Action::algorithm("A")])),
+            Some(TypeError::UnknownAlgorithm("A".into()))
+        );
+        assert_eq!(
+            build(BasicType::builder("X").state("START", [Action::fire("EO")])),
+            Some(TypeError::UnknownEvent("EO".into()))
+        );
+        assert_eq!(
+            build(BasicType::builder("X").state("START", []).transition(
+                "START",
+                "FOO",
+                Guard::always()
+            )),
+            Some(TypeError::UnknownState("FOO".into()))
+        );
+        assert_eq!(
+            build(
+                BasicType::builder("X")
+                    .event_input("EI", &["IN"])
+                    .state("START", [])
+            ),
+            Some(TypeError::UnknownVariable("IN".into()))
+        );
+        assert_eq!(
+            build(
+                BasicType::builder("X")
+                    .input("A", IecType::Bool)
+                    .output("A", IecType::Bool)
+                    .state("START", [])
+            ),
+            Some(TypeError::DuplicateName("A".into()))
+        );
+        assert_eq!(build(BasicType::builder("X")), Some(TypeError::NoStates));
+    }
+}
@@ -0,0 +1,867 @@
+//! Composite function blocks, whose interface is implemented by a network of function blocks.
+//!
+//! A [`CompositeType`] declares the interface, the internal function blocks and the
+//! connections between them. Connection endpoints are written as `block.PORT` for ports of
+//! internal blocks, and as `PORT` for ports of the composite's own interface:
+//!
+//! ```
+//! use toref::runtime::composite::CompositeType;
+//! use toref::runtime::factory::StandardFactory;
+//! use toref::runtime::value::IecType;
+//!
+//! let r#type = CompositeType::builder("SWITCH_ON")
+//!     .input("ON", IecType::Bool)
+//!     .child("sw", "E_SWITCH")
+//!     .connection("ON", "sw.G")
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut factory = StandardFactory::new();
+//! factory.register_standard_types();
+//! factory.register_composite_type(r#type);
+//! ```
+
+use crate::protocol::server::Error;
+use crate::runtime::basic::{Layout, TypeError};
+use crate::runtime::container::{Container, PortDestination, SimpleContainer};
+use crate::runtime::factory::{CreationError, Creator, FunctionBlockFactory};
+use crate::runtime::fb::{
+    DataInput, DataOutput, EventContext, EventInput, EventOutput, FunctionBlock, GenericType,
+};
+use crate::runtime::interface::{EventDeclaration, Interface, VariableDeclaration};
+use crate::runtime::value::{self, IecType, IecValue};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// The name of the internal block representing the composite's interface.
+///
+/// Its outputs are the inputs of the composite, and its inputs are the outputs.
+const INTERFACE: &str = "";
+
+/// Upper limit of nested composite blocks.
+///
+/// This guards against types which contain themselves, directly or through other types.
+const MAX_DEPTH: usize = 32;
+
+thread_local! {
+    /// The number of composite blocks currently being created on this thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A composite function block type.
+#[derive(Debug)]
+pub struct CompositeType {
+    name: String,
+    interface: Interface,
+    children: Vec<(String, String)>,
+    parameters: Vec<(PortDestination, String)>,
+    connections: Vec<(PortDestination, PortDestination)>,
+}
+
+impl CompositeType {
+    pub fn builder<N: Into<String>>(name: N) -> CompositeTypeBuilder {
+        CompositeTypeBuilder {
+            name: name.into(),
+            interface: Default::default(),
+            children: vec![],
+            parameters: vec![],
+            connections: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn interface(&self) -> &Interface {
+        &self.interface
+    }
+}
+
+/// Builds a [`CompositeType`], checking all references to the interface and the internal
+/// blocks when building.
+///
+/// Ports of internal blocks can only be checked when the blocks are created.
+pub struct CompositeTypeBuilder {
+    name: String,
+    interface: Interface,
+    children: Vec<(String, String)>,
+    parameters: Vec<(String, String)>,
+    connections: Vec<(String, String)>,
+}
+
+impl CompositeTypeBuilder {
+    pub fn event_input<N: Into<String>>(mut self, name: N, with: &[&str]) -> Self {
+        self.interface
+            .event_inputs
+            .push(EventDeclaration::new(name, with.iter().copied()));
+        self
+    }
+
+    pub fn event_output<N: Into<String>>(mut self, name: N, with: &[&str]) -> Self {
+        self.interface
+            .event_outputs
+            .push(EventDeclaration::new(name, with.iter().copied()));
+        self
+    }
+
+    pub fn input<N: Into<String>>(self, name: N, r#type: IecType) -> Self {
+        self.input_declaration(VariableDeclaration::new(name, r#type))
+    }
+
+    pub fn input_declaration(mut self, declaration: VariableDeclaration) -> Self {
+        self.interface.data_inputs.push(declaration);
+        self
+    }
+
+    pub fn output<N: Into<String>>(self, name: N, r#type: IecType) -> Self {
+        self.output_declaration(VariableDeclaration::new(name, r#type))
+    }
+
+    pub fn output_declaration(mut self, declaration: VariableDeclaration) -> Self {
+        self.interface.data_outputs.push(declaration);
+        self
+    }
+
+    /// Add an internal function block.
+    pub fn child<N, T>(mut self, name: N, r#type: T) -> Self
+    where
+        N: Into<String>,
+        T: Into<String>,
+    {
+        self.children.push((name.into(), r#type.into()));
+        self
+    }
+
+    /// Set a data input of an internal block to a literal value when it is created.
+    pub fn parameter<D, V>(mut self, destination: D, value: V) -> Self
+    where
+        D: Into<String>,
+        V: Into<String>,
+    {
+        self.parameters.push((destination.into(), value.into()));
+        self
+    }
+
+    /// Connect two ports, connections are created in the order they are added.
+    pub fn connection<S, D>(mut self, source: S, destination: D) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.connections.push((source.into(), destination.into()));
+        self
+    }
+
+    pub fn build(self) -> Result<CompositeType, TypeError> {
+        let interface = self.interface;
+
+        let mut names = HashSet::new();
+        for name in interface
+            .event_inputs
+            .iter()
+            .chain(&interface.event_outputs)
+            .map(|event| &event.name)
+        {
+            if !names.insert(name) {
+                return Err(TypeError::DuplicateName(name.clone()));
+            }
+        }
+
+        // checks names and initial values of the data ports
+        Layout::new(
+            interface
+                .data_inputs
+                .iter()
+                .chain(&interface.data_outputs)
+                .cloned()
+                .collect(),
+        )?;
+
+        for var in interface.event_inputs.iter().flat_map(|event| &event.with) {
+            if interface.data_input(var).is_none() {
+                return Err(TypeError::UnknownVariable(var.clone()));
+            }
+        }
+        for var in interface.event_outputs.iter().flat_map(|event| &event.with) {
+            if interface.data_output(var).is_none() {
+                return Err(TypeError::UnknownVariable(var.clone()));
+            }
+        }
+
+        let mut children = HashSet::new();
+        for (name, _) in &self.children {
+            if name.is_empty() || name.contains('.') {
+                return Err(TypeError::InvalidName(name.clone()));
+            }
+            if !children.insert(name.as_str()) {
+                return Err(TypeError::DuplicateName(name.clone()));
+            }
+        }
+
+        let child_port = |endpoint: &str| match endpoint.parse::<PortDestination>() {
+            Ok(port) if children.contains(port.block()) => Ok(port),
+            Ok(port) => Err(TypeError::UnknownBlock(port.block().to_string())),
+            Err(()) => Err(TypeError::UnknownPort(endpoint.to_string())),
+        };
+
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|(destination, value)| Ok((child_port(&destination)?, value)))
+            .collect::<Result<Vec<_>, TypeError>>()?;
+
+        let connections = self
+            .connections
+            .iter()
+            .map(|(source, destination)| {
+                let source = if source.contains('.') {
+                    child_port(source)?
+                } else if interface.event_input(source).is_some()
+                    || interface.data_input(source).is_some()
+                {
+                    PortDestination::new(INTERFACE, source)
+                } else {
+                    return Err(TypeError::UnknownPort(source.clone()));
+                };
+                let destination = if destination.contains('.') {
+                    child_port(destination)?
+                } else if interface.event_output(destination).is_some()
+                    || interface.data_output(destination).is_some()
+                {
+                    PortDestination::new(INTERFACE, destination)
+                } else {
+                    return Err(TypeError::UnknownPort(destination.clone()));
+                };
+                Ok((source, destination))
+            })
+            .collect::<Result<Vec<_>, TypeError>>()?;
+
+        Ok(CompositeType {
+            name: self.name,
+            interface,
+            children: self.children,
+            parameters,
+            connections,
+        })
+    }
+}
+
+/// Creates instances of a [`CompositeType`], along with their internal blocks.
+pub struct CompositeCreator<F> {
+    r#type: Arc<CompositeType>,
+    factory: F,
+}
+
+impl<F> CompositeCreator<F>
+where
+    F: FunctionBlockFactory,
+{
+    pub fn new(r#type: Arc<CompositeType>, factory: F) -> Self {
+        Self { r#type, factory }
+    }
+}
+
+impl<F> Creator for CompositeCreator<F>
+where
+    F: FunctionBlockFactory + Clone + Send + Sync + 'static,
+{
+    fn create(&self) -> Result<Box<dyn FunctionBlock>, CreationError> {
+        let depth = DEPTH.with(|depth| depth.get());
+        if depth >= MAX_DEPTH {
+            log::warn!("{}: Composite blocks nested too deeply", self.r#type.name);
+            return Err(CreationError::InvalidType(format!(
+                "{}: Composite blocks nested too deeply",
+                self.r#type.name
+            )));
+        }
+
+        DEPTH.with(|d| d.set(depth + 1));
+        let result = CompositeFunctionBlock::new(self.r#type.clone(), self.factory.clone());
+        DEPTH.with(|d| d.set(depth));
+
+        Ok(Box::new(result?))
+    }
+}
+
+/// The ports of the composite's interface, as seen from the internal network.
+struct InterfaceBlock {
+    r#type: Arc<CompositeType>,
+    event_inputs: Vec<EventInput>,
+    event_outputs: Vec<EventOutput>,
+    data_inputs: Vec<DataInput>,
+    data_outputs: Vec<DataOutput>,
+    /// The composite's output events fired by the network, in the order they were received.
+    fired: Arc<Mutex<Vec<String>>>,
+}
+
+impl FunctionBlock for InterfaceBlock {
+    fn type_name(&self) -> String {
+        self.r#type.name.clone()
+    }
+
+    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+        let interface = &self.r#type.interface;
+        interface
+            .data_inputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_outputs[i].clone())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        let interface = &self.r#type.interface;
+        interface
+            .data_outputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_inputs[i].clone())
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        let interface = &self.r#type.interface;
+        interface
+            .event_inputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_outputs[i].clone())
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        let interface = &self.r#type.interface;
+        interface
+            .event_outputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_inputs[i].clone())
+    }
+
+    fn receive_event(&mut self, name: &str, _context: &mut EventContext) {
+        self.fired
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(name.to_string());
+    }
+}
+
+/// An instance of a [`CompositeType`].
+pub struct CompositeFunctionBlock<F>
+where
+    F: FunctionBlockFactory,
+{
+    r#type: Arc<CompositeType>,
+    container: SimpleContainer<F>,
+    event_inputs: Vec<EventInput>,
+    event_outputs: Vec<EventOutput>,
+    /// The data inputs, along with the matching outputs of the interface block.
+    data_inputs: Vec<(DataInput, DataOutput)>,
+    /// The data outputs, along with the matching inputs of the interface block.
+    data_outputs: Vec<(DataOutput, DataInput)>,
+    fired: Arc<Mutex<Vec<String>>>,
+}
+
+impl<F> CompositeFunctionBlock<F>
+where
+    F: FunctionBlockFactory,
+{
+    /// Create a new instance, creating the internal blocks from the factory.
+    pub fn new(r#type: Arc<CompositeType>, factory: F) -> Result<Self, CreationError> {
+        let interface = &r#type.interface;
+        let invalid = |message: String| {
+            log::warn!("{}: {message}", r#type.name);
+            CreationError::InvalidType(format!("{}: {message}", r#type.name))
+        };
+
+        let data_inputs = interface
+            .data_inputs
+            .iter()
+            .map(|var| {
+                // the internal network sees the default type of a generic port
+                let input = match var.generic {
+                    Some(generic) => DataInput::generic(GenericType::new(generic)),
+                    None => DataInput::new(var.r#type),
+                };
+                input.set(var.initial_value());
+                let output = DataOutput::new(var.r#type);
+                output.set(var.initial_value());
+                (input, output)
+            })
+            .collect::<Vec<_>>();
+        let data_outputs = interface
+            .data_outputs
+            .iter()
+            .map(|var| {
+                let output = match var.generic {
+                    Some(generic) => DataOutput::generic(GenericType::new(generic)),
+                    None => DataOutput::new(var.r#type),
+                };
+                output.set(var.initial_value());
+                let input = DataInput::new(var.r#type);
+                input.set(var.initial_value());
+                (output, input)
+            })
+            .collect::<Vec<_>>();
+        let fired = Arc::new(Mutex::new(vec![]));
+
+        let mut container = SimpleContainer::new(factory);
+        container
+            .insert_child(
+                INTERFACE.to_string(),
+                Box::new(InterfaceBlock {
+                    r#type: r#type.clone(),
+                    event_inputs: interface
+                        .event_outputs
+                        .iter()
+                        .map(|_| EventInput::new())
+                        .collect(),
+                    event_outputs: interface
+                        .event_inputs
+                        .iter()
+                        .map(|_| EventOutput::new())
+                        .collect(),
+                    data_inputs: data_outputs
+                        .iter()
+                        .map(|(_, input)| input.clone())
+                        .collect(),
+                    data_outputs: data_inputs
+                        .iter()
+                        .map(|(_, output)| output.clone())
+                        .collect(),
+                    fired: fired.clone(),
+                }),
+            )
+            .map_err(|err| invalid(err.to_string()))?;
+
+        for (name, child_type) in &r#type.children {
+            container
+                .add_child(name.clone(), child_type)
+                .map_err(|err| invalid(format!("Failed to create {name} ({child_type}): {err}")))?;
+        }
+        for (destination, value) in &r#type.parameters {
+            container
+                .write(value, destination.clone())
+                .map_err(|err| invalid(format!("Failed to set {destination}: {err}")))?;
+        }
+        for (source, destination) in &r#type.connections {
+            container
+                .connect(source.clone(), destination.clone())
+                .map_err(|err| {
+                    invalid(format!(
+                        "Failed to connect {source} -> {destination}: {err}"
+                    ))
+                })?;
+        }
+
+        Ok(Self {
+            event_inputs: interface
+                .event_inputs
+                .iter()
+                .map(|_| EventInput::new())
+                .collect(),
+            event_outputs: interface
+                .event_outputs
+                .iter()
+                .map(|_| EventOutput::new())
+                .collect(),
+            data_inputs,
+            data_outputs,
+            container,
+            fired,
+            r#type,
+        })
+    }
+}
+
+impl<F> FunctionBlock for CompositeFunctionBlock<F>
+where
+    F: FunctionBlockFactory + Send,
+{
+    fn type_name(&self) -> String {
+        self.r#type.name.clone()
+    }
+
+    fn interface(&self) -> Interface {
+        self.r#type.interface.clone()
+    }
+
+    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+        self.r#type
+            .interface
+            .data_outputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_outputs[i].0.clone())
+    }
+
+    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+        self.r#type
+            .interface
+            .data_inputs
+            .iter()
+            .position(|var| var.name == name)
+            .map(|i| self.data_inputs[i].0.clone())
+    }
+
+    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+        self.r#type
+            .interface
+            .event_outputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_outputs[i].clone())
+    }
+
+    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+        self.r#type
+            .interface
+            .event_inputs
+            .iter()
+            .position(|event| event.name == name)
+            .map(|i| self.event_inputs[i].clone())
+    }
+
+    fn start(&mut self, context: &mut EventContext) -> Result<(), Error> {
+        self.container.set_time(context.now());
+        self.container.start();
+        self.container.dispatch();
+        self.forward_outputs(context);
+        Ok(())
+    }
+
+    fn stop(&mut self, context: &mut EventContext) -> Result<(), Error> {
+        self.container.set_time(context.now());
+        self.container.stop();
+        self.container.dispatch();
+        self.forward_outputs(context);
+        Ok(())
+    }
+
+    fn kill(&mut self) -> Result<(), Error> {
+        self.container.kill();
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        let interface = &self.r#type.interface;
+        for ((input, output), var) in self.data_inputs.iter().zip(&interface.data_inputs) {
+            input.set(var.initial_value_as(input.r#type()));
+            output.set(var.initial_value());
+        }
+        for ((output, input), var) in self.data_outputs.iter().zip(&interface.data_outputs) {
+            output.set(var.initial_value_as(output.r#type()));
+            input.set(var.initial_value());
+        }
+        self.container.reset();
+        for (destination, value) in &self.r#type.parameters {
+            if let Err(err) = self.container.write(value, destination.clone()) {
+                log::warn!("{}: Failed to set {destination}: {err}", self.r#type.name);
+            }
+        }
+        self.fired
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        Ok(())
+    }
+
+    fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+        let interface = &self.r#type.interface;
+        let Some(event) = interface.event_input(name) else {
+            log::warn!("{}: Unknown event input: {name}", self.r#type.name);
+            return;
+        };
+
+        // sample the data inputs associated with the event into the network
+        for var in &event.with {
+            if let Some(i) = interface.data_inputs.iter().position(|v| &v.name == var) {
+                let (input, output) = &self.data_inputs[i];
+                output.set(self.converted(input.get(), output.r#type()));
+            }
+        }
+
+        self.container.set_time(context.now());
+        self.container.fire(&PortDestination::new(INTERFACE, name));
+        self.container.dispatch();
+        self.forward_outputs(context);
+    }
+
+    fn on_timer(&mut self, _deadline: Duration, context: &mut EventContext) {
+        self.container.advance(context.now());
+        self.container.dispatch();
+        self.forward_outputs(context);
+    }
+}
+
+impl<F> CompositeFunctionBlock<F>
+where
+    F: FunctionBlockFactory,
+{
+    /// Convert a value between an interface port and the internal network, their types differ
+    /// once a generic port is resolved.
+    fn converted(&self, value: IecValue, r#type: IecType) -> IecValue {
+        if value.r#type() == r#type {
+            return value;
+        }
+        value::convert(&value, r#type).unwrap_or_else(|err| {
+            log::warn!("{}: {err}", self.r#type.name);
+            r#type.default_value()
+        })
+    }
+
+    /// Fire the output events which reached the interface block, along with the data outputs.
+    ///
+    /// The timer is set to the earliest timer of the internal blocks.
+    fn forward_outputs(&mut self, context: &mut EventContext) {
+        match self.container.next_deadline() {
+            Some(deadline) => context.set_timer(deadline),
+            None => context.cancel_timer(),
+        }
+
+        let fired = std::mem::take(&mut *self.fired.lock().unwrap_or_else(PoisonError::into_inner));
+        if fired.is_empty() {
+            return;
+        }
+
+        for (output, input) in &self.data_outputs {
+            output.set(self.converted(input.get(), output.r#type()));
+        }
+        for event in fired {
+            context.fire(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::basic::{Action, BasicFunctionBlock, BasicType, Guard};
+    use crate::runtime::factory::StandardFactory;
+    use crate::runtime::value::IecValue;
+
+    /// Registers the standard types, and `PASS`, copying `IN` to `OUT`.
+    fn factory() -> StandardFactory {
+        let pass = Arc::new(
+            BasicType::builder("PASS")
+                .event_input("REQ", &["IN"])
+                .event_output("CNF", &["OUT"])
+                .input("IN", IecType::Bool)
+                .output("OUT", IecType::Bool)
+                .algorithm("REQ", |vars| vars.set("OUT", vars.get("IN")?.clone()))
+                .state("START", [])
+                .state("REQ", [Action::algorithm("REQ").output("CNF")])
+                .transition("START", "REQ", Guard::event("REQ"))
+                .transition("REQ", "START", Guard::always())
+                .build()
+                .unwrap(),
+        );
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        factory.register_type("PASS", move || BasicFunctionBlock::new(pass.clone()));
+        factory
+    }
+
+    /// Sets `Q` on `S`, then forwards it through a second latch.
+    fn latch() -> CompositeTypeBuilder {
+        CompositeType::builder("LATCH")
+            .event_input("S", &[])
+            .event_input("R", &[])
+            .event_output("EO", &["Q"])
+            .output("Q", IecType::Bool)
+            .child("sr1", "E_SR")
+            .child("sr2", "E_SR")
+            .connection("S", "sr1.S")
+            .connection("R", "sr1.R")
+            .connection("sr1.EO", "sr2.S")
+            .connection("sr2.EO", "EO")
+            .connection("sr2.Q", "Q")
+    }
+
+    fn send<F: FunctionBlockFactory + Send>(
+        fb: &mut CompositeFunctionBlock<F>,
+        event: &str,
+    ) -> Vec<String> {
+        let mut context = EventContext::default();
+        fb.receive_event(event, &mut context);
+        context.take_fired()
+    }
+
+    #[test]
+    fn network() {
+        let mut fb =
+            CompositeFunctionBlock::new(Arc::new(latch().build().unwrap()), factory()).unwrap();
+        let q = fb.get_data_output("Q").unwrap();
+
+        assert_eq!(send(&mut fb, "S"), vec!["EO"]);
+        assert_eq!(q.get(), IecValue::Bool(true));
+
+        // the second latch is only set, never reset
+        assert!(send(&mut fb, "R").is_empty());
+        assert_eq!(q.get(), IecValue::Bool(true));
+    }
+
+    #[test]
+    fn data_inputs() {
+        let r#type = CompositeType::builder("FORWARD")
+            .event_input("REQ", &["IN"])
+            .event_output("CNF", &["OUT"])
+            .input("IN", IecType::Bool)
+            .output("OUT", IecType::Bool)
+            .child("p", "PASS")
+            .connection("REQ", "p.REQ")
+            .connection("IN", "p.IN")
+            .connection("p.CNF", "CNF")
+            .connection("p.OUT", "OUT")
+            .build()
+            .unwrap();
+
+        let mut factory = factory();
+        factory.register_composite_type(r#type);
+        let mut container = SimpleContainer::new(factory);
+        let port = |name: &str| name.parse::<PortDestination>().unwrap();
+        container.add_child("a".into(), "FORWARD").unwrap();
+        container.add_child("b".into(), "FORWARD").unwrap();
+        container.connect(port("a.CNF"), port("b.REQ")).unwrap();
+        container.connect(port("a.OUT"), port("b.IN")).unwrap();
+        container.add_watch(port("b.OUT")).unwrap();
+
+        container.write("TRUE", port("a.IN")).unwrap();
+        container.write("$e", port("a.REQ")).unwrap();
+        container.dispatch();
+
+        let watches = container.read_watches();
+        assert_eq!(watches[0].ports[0].data.value, "TRUE");
+    }
+
+    #[test]
+    fn parameters() {
+        let r#type = CompositeType::builder("CONSTANT")
+            .event_input("REQ", &[])
+            .event_output("CNF", &["OUT"])
+            .output("OUT", IecType::Bool)
+            .child("p", "PASS")
+            .parameter("p.IN", "TRUE")
+            .connection("REQ", "p.REQ")
+            .connection("p.CNF", "CNF")
+            .connection("p.OUT", "OUT")
+            .build()
+            .unwrap();
+
+        let mut fb = CompositeFunctionBlock::new(Arc::new(r#type), factory()).unwrap();
+        assert_eq!(send(&mut fb, "REQ"), vec!["CNF"]);
+        assert_eq!(
+            fb.get_data_output("OUT").unwrap().get(),
+            IecValue::Bool(true)
+        );
+
+        // parameters survive a reset
+        fb.reset().unwrap();
+        assert_eq!(
+            fb.get_data_output("OUT").unwrap().get(),
+            IecValue::Bool(false)
+        );
+        assert_eq!(send(&mut fb, "REQ"), vec!["CNF"]);
+        assert_eq!(
+            fb.get_data_output("OUT").unwrap().get(),
+            IecValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn invalid_types() {
+        let build = |builder: CompositeTypeBuilder| builder.build().err();
+
+        assert_eq!(
+            build(latch().connection("sr3.EO", "EO")),
+            Some(TypeError::UnknownBlock("sr3".into()))
+        );
+        assert_eq!(
+            build(latch().connection("S", "FOO")),
+            Some(TypeError::UnknownPort("FOO".into()))
+        );
+        assert_eq!(
+            build(latch().connection("EO", "sr1.S")),
+            Some(TypeError::UnknownPort("EO".into()))
+        );
+        assert_eq!(
+            build(latch().event_input("X", &["IN"])),
+            Some(TypeError::UnknownVariable("IN".into()))
+        );
+        assert_eq!(
+            build(latch().child("sr1", "E_SR")),
+            Some(TypeError::DuplicateName("sr1".into()))
+        );
+        assert_eq!(
+            build(latch().child("", "E_SR")),
+            Some(TypeError::InvalidName("".into()))
+        );
+        assert_eq!(
+            build(latch().parameter("sr3.Q", "TRUE")),
+            Some(TypeError::UnknownBlock("sr3".into()))
+        );
+    }
+
+    #[test]
+    fn recursive_type() {
+        let mut factory = factory();
+        factory
+            .register_composite_type(CompositeType::builder("A").child("b", "B").build().unwrap());
+        factory
+            .register_composite_type(CompositeType::builder("B").child("a", "A").build().unwrap());
+
+        assert!(matches!(
+            factory.create("A"),
+            Err(CreationError::InvalidType(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_network() {
+        let create = |builder: CompositeTypeBuilder| {
+            CompositeFunctionBlock::new(Arc::new(builder.build().unwrap()), factory()).err()
+        };
+
+        assert!(matches!(
+            create(latch().child("x", "FOO")),
+            Some(CreationError::InvalidType(_))
+        ));
+        assert!(matches!(
+            create(latch().connection("sr1.Q", "sr2.X")),
+            Some(CreationError::InvalidType(_))
+        ));
+        assert!(matches!(
+            create(latch().parameter("sr1.Q", "TRUE")),
+            Some(CreationError::InvalidType(_))
+        ));
+    }
+
+    #[test]
+    fn timers() {
+        let r#type = CompositeType::builder("TICKER")
+            .event_input("START", &[])
+            .event_output("EO", &[])
+            .child("c", "E_CYCLE")
+            .parameter("c.DT", "T#10ms")
+            .connection("START", "c.START")
+            .connection("c.EO", "EO")
+            .build()
+            .unwrap();
+
+        let mut factory = factory();
+        factory.register_composite_type(r#type);
+        let mut container = SimpleContainer::new(factory);
+        let port = |name: &str| name.parse::<PortDestination>().unwrap();
+        container.add_child("t".into(), "TICKER").unwrap();
+        container.add_watch(port("t.EO")).unwrap();
+
+        container.write("$e", port("t.START")).unwrap();
+        container.dispatch();
+        assert_eq!(container.next_deadline(), Some(Duration::from_millis(10)));
+
+        container.advance(Duration::from_millis(10));
+        container.dispatch();
+        assert_eq!(container.read_watches()[0].ports[0].data.value, "1");
+        assert_eq!(container.next_deadline(), Some(Duration::from_millis(20)));
+    }
+}
@@ -1,11 +1,20 @@
 use crate::protocol::server;
 use crate::protocol::server::{Action, Data, Error};
 use crate::runtime::factory::FunctionBlockFactory;
-use crate::runtime::fb::FunctionBlock;
+use crate::runtime::fb::{DataInput, DataOutput, EventContext, FunctionBlock, TimerChange};
+use crate::runtime::literal::{self, LiteralError};
+use crate::runtime::timer::TimerService;
+use crate::runtime::value::{AnyType, IecType, IecValue};
 use crate::runtime::Request;
+use crate::typelib::{fbt, interface_list};
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Upper limit of events delivered by a single call to [`SimpleContainer::dispatch`].
+const MAX_EVENTS: usize = 16 * 1024;
 
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum AddError {
@@ -21,6 +30,43 @@ pub enum ConnectError {
     UnknownBlock,
     #[error("Unknown port")]
     UnknownPort,
+    #[error("Destination is already connected")]
+    AlreadyConnected,
+    #[error("Not connected")]
+    NotConnected,
+    #[error("Incompatible types: {output} -> {input}")]
+    TypeMismatch { output: IecType, input: IecType },
+    #[error("Incompatible types: {elementary} is not {generic}")]
+    GenericMismatch {
+        elementary: IecType,
+        generic: AnyType,
+    },
+    #[error("Unresolved generic types: {output} -> {input}")]
+    UnresolvedType { output: AnyType, input: AnyType },
+    #[error("Current values can't be converted to {0}")]
+    Unconvertible(IecType),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum WriteError {
+    #[error("Unknown block")]
+    UnknownBlock,
+    #[error("Unknown port")]
+    UnknownPort,
+    #[error("Invalid value: {0}")]
+    InvalidValue(#[from] LiteralError),
+    #[error("Array ports can't be forced")]
+    ArrayForced,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("Unknown block")]
+    UnknownBlock,
+    #[error("Unknown port")]
+    UnknownPort,
+    #[error("Port is not watched")]
+    NotWatched,
 }
 
 pub trait Container {
@@ -43,6 +89,12 @@ pub trait Container {
         source: PortDestination,
         destination: PortDestination,
     ) -> Result<(), ConnectError>;
+
+    /// Write a literal value to a data input
+    ///
+    /// A value ending with `$F` forces a data input or output to the value, `$C` clears the force.
+    /// The value `$e` triggers an event input instead.
+    fn write(&mut self, value: &str, destination: PortDestination) -> Result<(), WriteError>;
 }
 
 pub struct SimpleContainer<F>
@@ -51,6 +103,23 @@ where
 {
     factory: F,
     children: HashMap<String, Box<dyn FunctionBlock>>,
+    data_connections: HashMap<PortDestination, DataConnection>,
+    event_connections: HashMap<PortDestination, Vec<PortDestination>>,
+    watches: BTreeSet<PortDestination>,
+    forced: BTreeSet<PortDestination>,
+    events: VecDeque<PortDestination>,
+    /// Children which were stopped individually, they don't receive events.
+    stopped: HashSet<String>,
+    /// The current time, relative to the start of the resource.
+    now: Duration,
+    /// The timers set by the children.
+    timers: TimerService,
+}
+
+/// A data output, bound to one or more data inputs.
+struct DataConnection {
+    output: DataOutput,
+    inputs: Vec<(PortDestination, DataInput)>,
 }
 
 impl<F> SimpleContainer<F>
@@ -71,36 +140,158 @@ where
         next: String,
         request: Request,
     ) -> Result<Option<Data>, Error> {
+        // a lifecycle request for a function block of the child carries it as data
+        if request.destination.is_empty() && request.data.is_none() {
+            match request.action {
+                Action::Start | Action::Stop | Action::Kill | Action::Reset => {
+                    return self.child_lifecycle(&next, request.action).map(|_| None);
+                }
+                _ => {}
+            }
+        }
+
         match self.children.get_mut(&next) {
             Some(child) => child.request(request),
             None => Err(Error::InvalidDestination),
         }
     }
 
+    /// Start, stop, kill or reset a single child.
+    fn child_lifecycle(&mut self, name: &str, action: Action) -> Result<(), Error> {
+        if !self.children.contains_key(name) {
+            return Err(Error::InvalidDestination);
+        }
+        if matches!(action, Action::Reset) {
+            // a forced value would otherwise stay in place of the initial one
+            self.clear_child_forces(name);
+        }
+
+        let fb = self
+            .children
+            .get_mut(name)
+            .ok_or(Error::InvalidDestination)?;
+
+        let mut context = EventContext::at(self.now);
+        match action {
+            Action::Start => {
+                log::info!("Starting {name}");
+                fb.start(&mut context)?;
+                self.stopped.remove(name);
+            }
+            Action::Stop => {
+                log::info!("Stopping {name}");
+                fb.stop(&mut context)?;
+                self.stopped.insert(name.to_string());
+            }
+            Action::Kill => {
+                log::info!("Killing {name}");
+                fb.kill()?;
+                self.stopped.insert(name.to_string());
+                self.events.retain(|event| event.block != name);
+                self.timers.cancel(name);
+            }
+            Action::Reset => {
+                log::info!("Resetting {name}");
+                fb.reset()?;
+            }
+            _ => return Err(Error::InvalidOperation),
+        }
+
+        self.update_timer(name, &mut context);
+        self.fire_all(name, context.take_fired());
+        Ok(())
+    }
+
     fn local_process_request(&mut self, request: Request) -> Result<Option<Data>, Error> {
         Ok(match (request.action, request.data) {
             (Action::Query, Some(Data::FunctionBlock { name, r#type })) => {
-                if name == "*" && r#type == "*" {
-                    if self.children.is_empty() {
-                        None
-                    } else {
-                        Some(Data::FunctionBlockList(
-                            self.children
-                                .iter()
-                                .map(|(name, fb)| server::FunctionBlock {
-                                    name: name.to_string(),
-                                    r#type: fb.type_name(),
-                                })
-                                .collect(),
-                        ))
-                    }
-                } else {
+                let mut blocks: Vec<_> = self
+                    .children
+                    .iter()
+                    .map(|(name, fb)| (name, fb.type_name()))
+                    .filter(|(child, child_type)| {
+                        matches(&name, child) && matches(&r#type, child_type)
+                    })
+                    .map(|(name, r#type)| server::FunctionBlock {
+                        name: name.to_string(),
+                        r#type: Some(r#type),
+                    })
+                    .collect();
+                blocks.sort_by(|a, b| a.name.cmp(&b.name));
+
+                if !blocks.is_empty() {
+                    Some(Data::FunctionBlockList(blocks))
+                } else if name == "*" && r#type == "*" {
+                    // an empty container is not an error
                     None
+                } else {
+                    return Err(Error::NoSuchObject);
                 }
             }
+            (
+                Action::Query,
+                Some(Data::Connection {
+                    source,
+                    destination,
+                }),
+            ) => Some(Data::ConnectionList(
+                self.connections()
+                    .into_iter()
+                    .map(|(src, dst)| (src.to_string(), dst.to_string()))
+                    .filter(|(src, dst)| matches(&source, src) && matches(&destination, dst))
+                    .map(|(source, destination)| server::ConnectionInfo {
+                        source,
+                        destination,
+                    })
+                    .collect(),
+            )),
+            (Action::Query, Some(Data::FunctionBlockType(r#type))) if r#type.name == "*" => {
+                Some(Data::FunctionBlockList(
+                    self.factory
+                        .types()
+                        .into_iter()
+                        .map(|name| server::FunctionBlock { name, r#type: None })
+                        .collect(),
+                ))
+            }
+            (Action::Query, Some(Data::FunctionBlockType(r#type))) => {
+                let interface = self
+                    .factory
+                    .interface(&r#type.name)
+                    .ok_or(Error::NoSuchObject)?;
+                Some(Data::FunctionBlockType(fbt::FbType {
+                    name: r#type.name,
+                    interface_list: interface_list(&interface),
+                    ..Default::default()
+                }))
+            }
+            (Action::Query, Some(Data::DataType(r#type))) => {
+                let types: Vec<_> = IecType::ALL
+                    .iter()
+                    .filter(|t| matches(&r#type.name, t.name()))
+                    .map(|t| fbt::DataType {
+                        name: t.name().to_string(),
+                    })
+                    .collect();
+                if types.is_empty() {
+                    return Err(Error::NoSuchObject);
+                }
+                Some(Data::DataTypeList(types))
+            }
+            (Action::Query, Some(Data::AdapterType(r#type))) => {
+                // no adapter types are supported, so there is nothing to report
+                if r#type.name != "*" {
+                    return Err(Error::NoSuchObject);
+                }
+                Some(Data::AdapterTypeList(vec![]))
+            }
             (Action::Create, Some(Data::FunctionBlock { name, r#type })) => {
                 self.add_child(name, &r#type).map(|_| None)?
             }
+            (
+                action @ (Action::Start | Action::Stop | Action::Kill | Action::Reset),
+                Some(Data::FunctionBlock { name, .. }),
+            ) => self.child_lifecycle(&name, action).map(|_| None)?,
             (Action::Delete, Some(Data::FunctionBlock { name, .. })) => {
                 self.remove_child(&name);
                 None
@@ -133,7 +324,45 @@ where
                         .map_err(|()| Error::InvalidDestination)?,
                 )
                 .map(|_| None)?,
-            (Action::Read, Some(Data::Watches)) => None,
+            (
+                Action::Write,
+                Some(Data::Connection {
+                    source,
+                    destination,
+                }),
+            ) => self
+                .write(
+                    &source,
+                    destination
+                        .try_into()
+                        .map_err(|()| Error::InvalidDestination)?,
+                )
+                .map(|_| None)?,
+            (Action::Create, Some(Data::Watch { source, .. })) => self
+                .add_watch(source.try_into().map_err(|()| Error::InvalidDestination)?)
+                .map(|_| None)?,
+            (Action::Delete, Some(Data::Watch { source, .. })) => self
+                .remove_watch(&source.try_into().map_err(|()| Error::InvalidDestination)?)
+                .map(|_| None)?,
+            (Action::Read, Some(Data::Watches { .. })) => Some(Data::Watches {
+                resources: self.read_child_watches(),
+            }),
+            (Action::Create, Some(Data::FunctionBlockType(r#type))) => {
+                log::info!("Registering type: {}", r#type.name);
+                self.factory.register(&r#type).map_err(|err| {
+                    log::warn!("Failed to register type {}: {err}", r#type.name);
+                    err
+                })?;
+                None
+            }
+            (Action::Create, Some(Data::AdapterType(r#type))) => {
+                log::warn!("Adapter types are not supported: {}", r#type.name);
+                return Err(Error::UnsupportedType);
+            }
+            (Action::Create, Some(Data::DataType(r#type))) => {
+                log::warn!("Data types are not supported: {}", r#type.name);
+                return Err(Error::UnsupportedType);
+            }
             _ => return Err(Error::InvalidOperation),
         })
     }
@@ -147,8 +376,434 @@ where
         Self {
             factory,
             children: HashMap::new(),
+            data_connections: HashMap::new(),
+            event_connections: HashMap::new(),
+            watches: BTreeSet::new(),
+            forced: BTreeSet::new(),
+            events: VecDeque::new(),
+            stopped: HashSet::new(),
+            now: Duration::ZERO,
+            timers: TimerService::new(),
+        }
+    }
+
+    /// The current time, relative to the start of the resource.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Set the current time, used for handling the following events.
+    pub fn set_time(&mut self, now: Duration) {
+        self.now = now;
+    }
+
+    /// Set the current time, and handle all timers which expired by then.
+    pub fn advance(&mut self, now: Duration) {
+        self.set_time(now);
+        self.fire_timers();
+    }
+
+    /// The earliest deadline of the timers set by the children.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.timers.next_deadline()
+    }
+
+    /// Handle the timers which expired by the current time, in the order of their deadlines.
+    ///
+    /// Each timer fires at most once, a timer which is set again to a deadline which already
+    /// passed fires with the next call. Events fired by the children are queued.
+    pub fn fire_timers(&mut self) {
+        for (deadline, name) in self.timers.take_expired(self.now) {
+            if self.stopped.contains(&name) {
+                log::debug!("Dropping timer of stopped block: {name}");
+                continue;
+            }
+            let Some(fb) = self.children.get_mut(&name) else {
+                continue;
+            };
+            let mut context = EventContext::at(self.now);
+            fb.on_timer(deadline, &mut context);
+            self.update_timer(&name, &mut context);
+            self.fire_all(&name, context.take_fired());
+        }
+    }
+
+    /// Apply the change to the timer of a child, made while handling an event.
+    fn update_timer(&mut self, block: &str, context: &mut EventContext) {
+        match context.take_timer() {
+            Some(TimerChange::Set(deadline)) => {
+                self.timers.set(block, deadline);
+            }
+            Some(TimerChange::Cancel) => {
+                self.timers.cancel(block);
+            }
+            None => {}
+        }
+    }
+
+    /// Queue an event for an event input, it will be delivered by the next call to
+    /// [`Self::dispatch`].
+    pub fn send_event(&mut self, event: PortDestination) {
+        self.events.push_back(event);
+    }
+
+    /// Deliver queued events to their receiving blocks, until the queue is empty.
+    ///
+    /// Events are processed in the order they were queued. Output events fired by a block are
+    /// appended to the queue, in the order they were fired and, for each event, in the order
+    /// its connections were created. Before that, the values of the block's data outputs are
+    /// transferred to the connected inputs.
+    ///
+    /// A limited number of events is delivered, the remaining ones are dropped. This guards
+    /// against cycles of event connections, which would never empty the queue.
+    pub fn dispatch(&mut self) {
+        let mut events = 0;
+        while self.dispatch_next() {
+            events += 1;
+            if events >= MAX_EVENTS && !self.events.is_empty() {
+                log::error!(
+                    "Events did not settle after {events} events, dropping {} queued events",
+                    self.events.len()
+                );
+                self.clear_events();
+                break;
+            }
+        }
+    }
+
+    /// Deliver the next queued event, returns `false` if the queue was empty.
+    ///
+    /// See [`Self::dispatch`] for the order of events.
+    pub fn dispatch_next(&mut self) -> bool {
+        let Some(event) = self.events.pop_front() else {
+            return false;
+        };
+        if self.stopped.contains(&event.block) {
+            log::debug!("Dropping event for stopped block: {event}");
+            return true;
+        }
+
+        let mut context = EventContext::at(self.now);
+        let fired = match self.children.get_mut(&event.block) {
+            Some(fb) => {
+                log::debug!("Delivering event: {event}");
+                if let Some(input) = fb.get_event_input(&event.port) {
+                    input.count();
+                }
+                fb.receive_event(&event.port, &mut context);
+                let fired = context.take_fired();
+                for port in &fired {
+                    if let Some(output) = fb.get_event_output(port) {
+                        output.count();
+                    }
+                }
+                fired
+            }
+            None => {
+                log::warn!("Dropping event for unknown block: {event}");
+                return true;
+            }
+        };
+        self.update_timer(&event.block, &mut context);
+
+        if !fired.is_empty() {
+            self.transfer(&event.block);
+
+            for port in fired {
+                self.queue_connected(&PortDestination::new(event.block.clone(), port));
+            }
+        }
+
+        true
+    }
+
+    /// Drop all queued events.
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// Take all queued events, leaving the queue empty.
+    pub fn take_events(&mut self) -> VecDeque<PortDestination> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Start all children, except the ones which were stopped individually.
+    ///
+    /// Events fired by the children are queued.
+    pub fn start(&mut self) {
+        self.notify_children("start", |fb, context| fb.start(context));
+    }
+
+    /// Stop all children, except the ones which were stopped individually.
+    ///
+    /// Events fired by the children are queued.
+    pub fn stop(&mut self) {
+        self.notify_children("stop", |fb, context| fb.stop(context));
+    }
+
+    /// Kill all children, including the ones which were stopped individually, dropping queued
+    /// events.
+    pub fn kill(&mut self) {
+        let stopped = std::mem::take(&mut self.stopped);
+        self.notify_children("kill", |fb, _| fb.kill());
+        self.stopped = stopped;
+        self.clear_events();
+        self.timers.clear();
+    }
+
+    /// Restore all children to their initial state, dropping queued events and forces.
+    ///
+    /// Children which were stopped individually are no longer considered stopped.
+    pub fn reset(&mut self) {
+        self.stopped.clear();
+        self.clear_events();
+        self.clear_forces();
+        self.timers.clear();
+        self.notify_children("reset", |fb, _| fb.reset());
+    }
+
+    /// Call a lifecycle hook of all children which are not stopped, in the order of their names.
+    fn notify_children<H>(&mut self, hook: &str, f: H)
+    where
+        H: Fn(&mut dyn FunctionBlock, &mut EventContext) -> Result<(), Error>,
+    {
+        let mut names: Vec<_> = self
+            .children
+            .keys()
+            .filter(|name| !self.stopped.contains(*name))
+            .cloned()
+            .collect();
+        names.sort_unstable();
+
+        for name in names {
+            let Some(fb) = self.children.get_mut(&name) else {
+                continue;
+            };
+            let mut context = EventContext::at(self.now);
+            if let Err(err) = f(fb.as_mut(), &mut context) {
+                log::warn!("Failed to {hook} {name}: {err}");
+            }
+            self.update_timer(&name, &mut context);
+            self.fire_all(&name, context.take_fired());
+        }
+    }
+
+    /// Fire output events of a child, outside of its event handling.
+    fn fire_all(&mut self, block: &str, fired: Vec<String>) {
+        for port in fired {
+            let source = PortDestination::new(block, port);
+            if let Some(output) = self
+                .children
+                .get(block)
+                .and_then(|fb| fb.get_event_output(&source.port))
+            {
+                output.count();
+            }
+            self.fire(&source);
+        }
+    }
+
+    /// Fire an event output of a child from outside of its event handling.
+    ///
+    /// The values of the child's data outputs are transferred, and the event is queued for all
+    /// connected event inputs. It will be delivered by the next call to [`Self::dispatch`].
+    pub fn fire(&mut self, source: &PortDestination) {
+        self.transfer(&source.block);
+        self.queue_connected(source);
+    }
+
+    fn queue_connected(&mut self, source: &PortDestination) {
+        if let Some(destinations) = self.event_connections.get(source) {
+            self.events.extend(destinations.iter().cloned());
         }
     }
+
+    /// Transfer the current values of all connected outputs of a block to their inputs.
+    pub fn transfer(&self, block: &str) {
+        for connection in self
+            .data_connections
+            .iter()
+            .filter(|(source, _)| source.block == block)
+            .map(|(_, connection)| connection)
+        {
+            let value = connection.output.get();
+            for (_, input) in &connection.inputs {
+                input.set(value.clone());
+            }
+        }
+    }
+
+    /// Add an already created child.
+    pub fn insert_child(
+        &mut self,
+        name: String,
+        child: Box<dyn FunctionBlock>,
+    ) -> Result<(), AddError> {
+        match self.children.entry(name) {
+            Entry::Vacant(entry) => {
+                entry.insert(child);
+                Ok(())
+            }
+            Entry::Occupied(_) => Err(AddError::ItemAlreadyExist),
+        }
+    }
+
+    /// Clear all forces on ports of the children.
+    pub fn clear_forces(&mut self) {
+        for port in std::mem::take(&mut self.forced) {
+            self.clear_force(&port);
+        }
+    }
+
+    /// Clear all forces on ports of a single child.
+    fn clear_child_forces(&mut self, block: &str) {
+        let (forced, others) = std::mem::take(&mut self.forced)
+            .into_iter()
+            .partition(|port| port.block == block);
+        self.forced = others;
+        for port in forced {
+            self.clear_force(&port);
+        }
+    }
+
+    fn clear_force(&self, port: &PortDestination) {
+        if let Some(fb) = self.children.get(&port.block) {
+            if let Some(input) = fb.get_data_input(&port.port) {
+                input.clear_force();
+            }
+            if let Some(output) = fb.get_data_output(&port.port) {
+                output.clear_force();
+            }
+        }
+    }
+
+    /// Start watching a data or event port.
+    pub fn add_watch(&mut self, port: PortDestination) -> Result<(), WatchError> {
+        log::info!("Add watch: {port}");
+
+        let fb = self
+            .children
+            .get(&port.block)
+            .ok_or(WatchError::UnknownBlock)?;
+        if fb.get_data_output(&port.port).is_none()
+            && fb.get_data_input(&port.port).is_none()
+            && fb.get_event_output(&port.port).is_none()
+            && fb.get_event_input(&port.port).is_none()
+        {
+            return Err(WatchError::UnknownPort);
+        }
+
+        self.watches.insert(port);
+
+        Ok(())
+    }
+
+    /// Stop watching a data or event port.
+    pub fn remove_watch(&mut self, port: &PortDestination) -> Result<(), WatchError> {
+        log::info!("Remove watch: {port}");
+
+        if self.watches.remove(port) {
+            Ok(())
+        } else {
+            Err(WatchError::NotWatched)
+        }
+    }
+
+    /// The current state of all watched ports, grouped by block.
+    pub fn read_watches(&self) -> Vec<server::WatchedFunctionBlock> {
+        let mut result: Vec<server::WatchedFunctionBlock> = vec![];
+
+        for watch in &self.watches {
+            let Some(fb) = self.children.get(&watch.block) else {
+                continue;
+            };
+            let (value, forced) = if let Some(output) = fb.get_data_output(&watch.port) {
+                (output.get().to_string(), output.is_forced())
+            } else if let Some(input) = fb.get_data_input(&watch.port) {
+                (input.get().to_string(), input.is_forced())
+            } else if let Some(output) = fb.get_event_output(&watch.port) {
+                (output.get_count().to_string(), false)
+            } else if let Some(input) = fb.get_event_input(&watch.port) {
+                (input.get_count().to_string(), false)
+            } else {
+                continue;
+            };
+
+            let port = server::WatchedPort {
+                name: watch.port.clone(),
+                data: server::WatchedData { value, forced },
+            };
+
+            match result.last_mut() {
+                Some(last) if last.name == watch.block => last.ports.push(port),
+                _ => result.push(server::WatchedFunctionBlock {
+                    name: watch.block.clone(),
+                    ports: vec![port],
+                }),
+            }
+        }
+
+        result
+    }
+
+    /// Collect the watches of all children, which are expected to be resources.
+    fn read_child_watches(&mut self) -> Vec<server::WatchedResource> {
+        let mut names = self.children.keys().cloned().collect::<Vec<_>>();
+        names.sort_unstable();
+
+        let mut result = vec![];
+        for name in names {
+            let request = Request {
+                destination: Default::default(),
+                action: Action::Read,
+                data: Some(Data::Watches { resources: vec![] }),
+            };
+            if let Some(child) = self.children.get_mut(&name) {
+                if let Ok(Some(Data::Watches { resources })) = child.request(request) {
+                    result.extend(
+                        resources
+                            .into_iter()
+                            .filter(|resource| !resource.function_blocks.is_empty())
+                            .map(|resource| server::WatchedResource {
+                                name: name.clone(),
+                                ..resource
+                            }),
+                    );
+                }
+            }
+        }
+
+        result
+    }
+
+    fn is_connected(&self, destination: &PortDestination) -> bool {
+        self.data_connections
+            .values()
+            .flat_map(|connection| &connection.inputs)
+            .any(|(input, _)| input == destination)
+    }
+
+    /// All event and data connections, sorted by source and destination.
+    fn connections(&self) -> Vec<(&PortDestination, &PortDestination)> {
+        let events = self
+            .event_connections
+            .iter()
+            .flat_map(|(source, destinations)| destinations.iter().map(move |dst| (source, dst)));
+        let data = self
+            .data_connections
+            .iter()
+            .flat_map(|(source, connection)| {
+                connection.inputs.iter().map(move |(dst, _)| (source, dst))
+            });
+        let mut result: Vec<_> = events.chain(data).collect();
+        result.sort();
+        result
+    }
+}
+
+/// Check if a name matches a query pattern, which is either `*` or the exact name.
+fn matches(pattern: &str, value: &str) -> bool {
+    pattern == "*" || pattern == value
 }
 
 impl<F> Container for SimpleContainer<F>
@@ -180,6 +835,29 @@ where
     fn remove_child(&mut self, name: &str) {
         log::info!("Removing: {name}");
         self.children.remove(name);
+
+        self.data_connections
+            .retain(|source, _| source.block != name);
+        for connection in self.data_connections.values_mut() {
+            connection
+                .inputs
+                .retain(|(destination, _)| destination.block != name);
+        }
+        self.data_connections
+            .retain(|_, connection| !connection.inputs.is_empty());
+
+        self.event_connections
+            .retain(|source, _| source.block != name);
+        for destinations in self.event_connections.values_mut() {
+            destinations.retain(|destination| destination.block != name);
+        }
+        self.event_connections
+            .retain(|_, destinations| !destinations.is_empty());
+
+        self.watches.retain(|watch| watch.block != name);
+        self.forced.retain(|port| port.block != name);
+        self.stopped.remove(name);
+        self.timers.cancel(name);
     }
 
     fn connect(
@@ -191,25 +869,90 @@ where
 
         let source_fb = self
             .children
-            .get_mut(&source.block)
+            .get(&source.block)
             .ok_or(ConnectError::UnknownBlock)?;
 
-        let source_port = source_fb
-            .get_data_output(&source.port)
-            .ok_or(ConnectError::UnknownPort)?;
-
         let destination_fb = self
             .children
-            .get_mut(&destination.block)
+            .get(&destination.block)
             .ok_or(ConnectError::UnknownBlock)?;
 
+        if source_fb.get_event_output(&source.port).is_some() {
+            destination_fb
+                .get_event_input(&destination.port)
+                .ok_or(ConnectError::UnknownPort)?;
+
+            let destinations = self.event_connections.entry(source).or_default();
+            if destinations.contains(&destination) {
+                log::warn!("Connection already exists");
+                return Err(ConnectError::AlreadyConnected);
+            }
+
+            log::info!("Creating new event connection");
+            destinations.push(destination);
+
+            return Ok(());
+        }
+
+        let source_port = source_fb
+            .get_data_output(&source.port)
+            .ok_or(ConnectError::UnknownPort)?;
+
         let destination_port = destination_fb
             .get_data_input(&destination.port)
             .ok_or(ConnectError::UnknownPort)?;
 
-        log::info!("Creating new connection");
+        // a generic port takes the type of the port it gets connected to
+        let (r#type, generic) = match (source_port.generic_type(), destination_port.generic_type())
+        {
+            (None, None) if source_port.r#type() != destination_port.r#type() => {
+                log::warn!(
+                    "Incompatible types: {} -> {}",
+                    source_port.r#type(),
+                    destination_port.r#type()
+                );
+                return Err(ConnectError::TypeMismatch {
+                    output: source_port.r#type(),
+                    input: destination_port.r#type(),
+                });
+            }
+            (None, None) => (source_port.r#type(), None),
+            (None, Some(generic)) => (source_port.r#type(), Some(generic)),
+            (Some(generic), None) => (destination_port.r#type(), Some(generic)),
+            (Some(output), Some(input)) => {
+                log::warn!("Unresolved generic types: {output} -> {input}");
+                return Err(ConnectError::UnresolvedType { output, input });
+            }
+        };
+        if let Some(generic) = generic.filter(|generic| !generic.accepts(r#type)) {
+            log::warn!("Incompatible types: {type} is not {generic}");
+            return Err(ConnectError::GenericMismatch {
+                elementary: r#type,
+                generic,
+            });
+        }
 
-        // FIXME: implement actual binding
+        if self.is_connected(&destination) {
+            log::warn!("Destination is already connected");
+            return Err(ConnectError::AlreadyConnected);
+        }
+
+        // resolving fails if values written before can't be converted
+        if !source_port.resolve(r#type) || !destination_port.resolve(r#type) {
+            log::warn!("Unable to resolve generic type to {type}");
+            return Err(ConnectError::Unconvertible(r#type));
+        }
+
+        log::info!("Creating new data connection");
+
+        self.data_connections
+            .entry(source)
+            .or_insert_with(|| DataConnection {
+                output: source_port,
+                inputs: vec![],
+            })
+            .inputs
+            .push((destination, destination_port));
 
         Ok(())
     }
@@ -220,24 +963,188 @@ where
         destination: PortDestination,
     ) -> Result<(), ConnectError> {
         log::info!("Disconnect: {source} -> {destination}");
-        todo!();
+
+        if let Some(destinations) = self.event_connections.get_mut(&source) {
+            let len = destinations.len();
+            destinations.retain(|d| d != &destination);
+            if destinations.len() != len {
+                if destinations.is_empty() {
+                    self.event_connections.remove(&source);
+                }
+                log::info!("Event connection removed");
+                return Ok(());
+            }
+        }
+
+        let connection = self
+            .data_connections
+            .get_mut(&source)
+            .ok_or(ConnectError::NotConnected)?;
+
+        let len = connection.inputs.len();
+        connection.inputs.retain(|(input, _)| input != &destination);
+        if connection.inputs.len() == len {
+            log::warn!("Connection does not exist");
+            return Err(ConnectError::NotConnected);
+        }
+
+        if connection.inputs.is_empty() {
+            self.data_connections.remove(&source);
+        }
+
+        log::info!("Connection removed");
+
+        Ok(())
+    }
+
+    fn write(&mut self, value: &str, destination: PortDestination) -> Result<(), WriteError> {
+        log::info!("Write: {value} -> {destination}");
+
+        let fb = self
+            .children
+            .get(&destination.block)
+            .ok_or(WriteError::UnknownBlock)?;
+
+        if value == "$e" {
+            fb.get_event_input(&destination.port)
+                .ok_or(WriteError::UnknownPort)?;
+            log::info!("Triggering event: {destination}");
+            self.send_event(destination);
+        } else if let Some(value) = value.strip_suffix("$F") {
+            if let Some(input) = fb.get_data_input(&destination.port) {
+                input.force(parse_for(
+                    value,
+                    input.r#type(),
+                    input.generic_type(),
+                    |r#type| input.resolve(r#type),
+                )?)?;
+            } else if let Some(output) = fb.get_data_output(&destination.port) {
+                output.force(parse_for(
+                    value,
+                    output.r#type(),
+                    output.generic_type(),
+                    |r#type| output.resolve(r#type),
+                )?)?;
+            } else if fb.get_array_input(&destination.port).is_some() {
+                return Err(WriteError::ArrayForced);
+            } else {
+                return Err(WriteError::UnknownPort);
+            }
+            log::info!("Forced: {destination}");
+            self.forced.insert(destination);
+        } else if value.ends_with("$C") {
+            if let Some(input) = fb.get_data_input(&destination.port) {
+                input.clear_force();
+            } else if let Some(output) = fb.get_data_output(&destination.port) {
+                output.clear_force();
+            } else if fb.get_array_input(&destination.port).is_some() {
+                return Err(WriteError::ArrayForced);
+            } else {
+                return Err(WriteError::UnknownPort);
+            }
+            log::info!("Cleared force: {destination}");
+            self.forced.remove(&destination);
+        } else if let Some(input) = fb.get_data_input(&destination.port) {
+            input.set(parse_for(
+                value,
+                input.r#type(),
+                input.generic_type(),
+                |r#type| input.resolve(r#type),
+            )?);
+        } else {
+            let elements = fb
+                .get_array_input(&destination.port)
+                .ok_or(WriteError::UnknownPort)?;
+            let literals = literal::split_array(value)?;
+            if literals.len() > elements.len() {
+                return Err(LiteralError::ArrayLength(elements.len()).into());
+            }
+            // parse all elements first, so that an invalid element leaves the array unchanged
+            let values = literals
+                .into_iter()
+                .zip(&elements)
+                .map(|(literal, element)| literal::parse_as(literal, element.r#type()))
+                .collect::<Result<Vec<_>, _>>()?;
+            // the elements which are not given are initialized
+            let mut values = values.into_iter();
+            for element in &elements {
+                element.set(
+                    values
+                        .next()
+                        .unwrap_or_else(|| element.r#type().default_value()),
+                );
+            }
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Parse a literal written to a port.
+///
+/// A typed literal written to a generic port, which is not resolved yet, resolves its type
+/// first. Untyped literals are parsed as the current type of the port.
+fn parse_for<R>(
+    value: &str,
+    r#type: IecType,
+    generic: Option<AnyType>,
+    resolve: R,
+) -> Result<IecValue, LiteralError>
+where
+    R: FnOnce(IecType) -> bool,
+{
+    if generic.is_some() {
+        if let Some(literal) = literal::prefix_type(value)? {
+            let value = literal::parse_as(value, literal)?;
+            if !resolve(literal) {
+                return Err(LiteralError::TypeMismatch {
+                    literal,
+                    expected: r#type,
+                });
+            }
+            return Ok(value);
+        }
+    }
+    literal::parse_as(value, r#type)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PortDestination {
     block: String,
     port: String,
 }
 
+impl PortDestination {
+    pub fn new<B, P>(block: B, port: P) -> Self
+    where
+        B: Into<String>,
+        P: Into<String>,
+    {
+        Self {
+            block: block.into(),
+            port: port.into(),
+        }
+    }
+
+    pub fn block(&self) -> &str {
+        &self.block
+    }
+
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+}
+
 impl Display for PortDestination {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{0}.{1}", self.block, self.port)
     }
 }
 
-impl PortDestination {
-    pub fn from_str(name: &str) -> Result<Self, ()> {
+impl FromStr for PortDestination {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
         let s = name.split('.').collect::<Vec<_>>();
         if s.len() != 2 {
             Err(())
@@ -254,6 +1161,979 @@ impl TryFrom<String> for PortDestination {
     type Error = ();
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        PortDestination::from_str(&value)
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::factory::StandardFactory;
+    use crate::runtime::fb::{EventInput, EventOutput};
+    use crate::runtime::root::RootFactory;
+    use crate::runtime::value::{IecType, IecValue};
+    use crate::runtime::Destination;
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::Arc;
+
+    /// Fires `EO1` and `EO2` for every `EI`, recording the order of execution in `N`. Fires `EO2`
+    /// when started.
+    struct Relay {
+        sequence: Arc<AtomicI64>,
+        ei: EventInput,
+        eo1: EventOutput,
+        eo2: EventOutput,
+        input: DataInput,
+        n: DataOutput,
+    }
+
+    impl FunctionBlock for Relay {
+        fn type_name(&self) -> String {
+            "RELAY".to_string()
+        }
+
+        fn get_data_input(&self, name: &str) -> Option<DataInput> {
+            match name {
+                "IN" => Some(self.input.clone()),
+                _ => None,
+            }
+        }
+
+        fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+            match name {
+                "N" => Some(self.n.clone()),
+                _ => None,
+            }
+        }
+
+        fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+            match name {
+                "EO1" => Some(self.eo1.clone()),
+                "EO2" => Some(self.eo2.clone()),
+                _ => None,
+            }
+        }
+
+        fn get_event_input(&self, name: &str) -> Option<EventInput> {
+            match name {
+                "EI" => Some(self.ei.clone()),
+                _ => None,
+            }
+        }
+
+        fn start(&mut self, context: &mut EventContext) -> Result<(), Error> {
+            context.fire("EO2");
+            Ok(())
+        }
+
+        fn receive_event(&mut self, name: &str, context: &mut EventContext) {
+            if name == "EI" {
+                let n = self.sequence.fetch_add(1, Ordering::SeqCst);
+                self.n.set(IecValue::Time(n));
+                context.fire("EO1");
+                context.fire("EO2");
+            }
+        }
+    }
+
+    fn container() -> SimpleContainer<StandardFactory> {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let sequence = Arc::new(AtomicI64::new(1));
+        factory.register_type("RELAY", move || Relay {
+            sequence: sequence.clone(),
+            ei: EventInput::new(),
+            eo1: EventOutput::new(),
+            eo2: EventOutput::new(),
+            input: DataInput::new(IecType::Time),
+            n: DataOutput::new(IecType::Time),
+        });
+        let mut container = SimpleContainer::new(factory);
+        container.add_child("sr".into(), "E_SR").unwrap();
+        container.add_child("sw".into(), "E_SWITCH").unwrap();
+        container
+    }
+
+    fn port(name: &str) -> PortDestination {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn connect_transfers_value() {
+        let mut container = container();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+
+        let output = container.children["sr"].get_data_output("Q").unwrap();
+        let input = container.children["sw"].get_data_input("G").unwrap();
+
+        output.set(IecValue::Bool(true));
+        assert_eq!(input.get(), IecValue::Bool(false));
+
+        container.transfer("sr");
+        assert_eq!(input.get(), IecValue::Bool(true));
+    }
+
+    #[test]
+    fn connect_unknown_port() {
+        let mut container = container();
+        assert!(matches!(
+            container.connect(port("sr.X"), port("sw.G")),
+            Err(ConnectError::UnknownPort)
+        ));
+        assert!(matches!(
+            container.connect(port("sw.G"), port("sr.Q")),
+            Err(ConnectError::UnknownPort)
+        ));
+        assert!(matches!(
+            container.connect(port("foo.Q"), port("sw.G")),
+            Err(ConnectError::UnknownBlock)
+        ));
+    }
+
+    #[test]
+    fn connect_type_mismatch() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        assert!(matches!(
+            container.connect(port("a.N"), port("sw.G")),
+            Err(ConnectError::TypeMismatch {
+                output: IecType::Time,
+                input: IecType::Bool
+            })
+        ));
+    }
+
+    #[test]
+    fn connect_generic() {
+        let mut container = container();
+        container.add_child("add".into(), "F_ADD").unwrap();
+        container.add_child("mul".into(), "F_MUL").unwrap();
+        container.add_child("and".into(), "F_AND").unwrap();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("ctu".into(), "E_CTU").unwrap();
+
+        // both types need to be known
+        assert!(matches!(
+            container.connect(port("add.OUT"), port("mul.IN1")),
+            Err(ConnectError::UnresolvedType {
+                output: AnyType::AnyMagnitude,
+                input: AnyType::AnyNum
+            })
+        ));
+        assert!(matches!(
+            container.connect(port("a.N"), port("and.IN1")),
+            Err(ConnectError::GenericMismatch {
+                elementary: IecType::Time,
+                generic: AnyType::AnyBit
+            })
+        ));
+
+        // connecting one port resolves all ports sharing its type
+        container.connect(port("a.N"), port("add.IN1")).unwrap();
+        let add = &container.children["add"];
+        assert_eq!(add.get_data_input("IN2").unwrap().r#type(), IecType::Time);
+        assert_eq!(add.get_data_output("OUT").unwrap().r#type(), IecType::Time);
+        assert!(matches!(
+            container.connect(port("ctu.CV"), port("add.IN2")),
+            Err(ConnectError::TypeMismatch {
+                output: IecType::Uint,
+                input: IecType::Time
+            })
+        ));
+
+        container.connect(port("ctu.CV"), port("mul.IN2")).unwrap();
+        container.connect(port("mul.OUT"), port("mul.IN1")).unwrap();
+        assert!(matches!(
+            container.connect(port("add.OUT"), port("mul.IN1")),
+            Err(ConnectError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn write_generic() {
+        let mut container = container();
+        container.add_child("add".into(), "F_ADD").unwrap();
+        container.add_child("mul".into(), "F_MUL").unwrap();
+        container.add_child("sub".into(), "F_SUB").unwrap();
+        container.add_child("and".into(), "F_AND").unwrap();
+        container.add_child("ctu".into(), "E_CTU").unwrap();
+
+        // a typed literal resolves the type
+        container.write("INT#5", port("add.IN1")).unwrap();
+        let add = &container.children["add"];
+        assert_eq!(add.get_data_input("IN1").unwrap().get(), IecValue::Int(5));
+        assert_eq!(add.get_data_output("OUT").unwrap().r#type(), IecType::Int);
+        assert!(matches!(
+            container.write("DINT#5", port("add.IN2")),
+            Err(WriteError::InvalidValue(LiteralError::TypeMismatch {
+                literal: IecType::Dint,
+                expected: IecType::Int
+            }))
+        ));
+        assert!(matches!(
+            container.write("INT#5", port("and.IN1")),
+            Err(WriteError::InvalidValue(LiteralError::TypeMismatch {
+                literal: IecType::Int,
+                ..
+            }))
+        ));
+
+        // untyped values are kept when connecting resolves the type
+        container.write("5", port("mul.IN1")).unwrap();
+        container.connect(port("ctu.CV"), port("mul.IN2")).unwrap();
+        let mul = &container.children["mul"];
+        assert_eq!(mul.get_data_input("IN1").unwrap().get(), IecValue::Uint(5));
+
+        // unless they can't be converted
+        container.write("5.5", port("sub.IN1")).unwrap();
+        assert!(matches!(
+            container.connect(port("ctu.CV"), port("sub.IN2")),
+            Err(ConnectError::Unconvertible(IecType::Uint))
+        ));
+        let sub = &container.children["sub"];
+        assert_eq!(
+            sub.get_data_input("IN2").unwrap().generic_type(),
+            Some(AnyType::AnyMagnitude)
+        );
+    }
+
+    #[test]
+    fn connect_destination_only_once() {
+        let mut container = container();
+        container.add_child("sr2".into(), "E_SR").unwrap();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+        assert!(matches!(
+            container.connect(port("sr2.Q"), port("sw.G")),
+            Err(ConnectError::AlreadyConnected)
+        ));
+    }
+
+    #[test]
+    fn disconnect() {
+        let mut container = container();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+        container.disconnect(port("sr.Q"), port("sw.G")).unwrap();
+        assert!(container.data_connections.is_empty());
+
+        // the destination may be connected again
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+    }
+
+    #[test]
+    fn disconnect_not_connected() {
+        let mut container = container();
+        assert!(matches!(
+            container.disconnect(port("sr.Q"), port("sw.G")),
+            Err(ConnectError::NotConnected)
+        ));
+        assert!(matches!(
+            container.disconnect(port("foo.Q"), port("bar.G")),
+            Err(ConnectError::NotConnected)
+        ));
+    }
+
+    fn sequence(container: &SimpleContainer<StandardFactory>, block: &str) -> IecValue {
+        container.children[block]
+            .get_data_output("N")
+            .unwrap()
+            .get()
+    }
+
+    #[test]
+    fn dispatch_in_order() {
+        let mut container = container();
+        for name in ["a", "b", "c", "d"] {
+            container.add_child(name.into(), "RELAY").unwrap();
+        }
+        container.connect(port("a.EO1"), port("b.EI")).unwrap();
+        container.connect(port("a.EO2"), port("c.EI")).unwrap();
+        container.connect(port("b.EO1"), port("d.EI")).unwrap();
+
+        container.send_event(port("a.EI"));
+        container.dispatch();
+
+        assert_eq!(sequence(&container, "a"), IecValue::Time(1));
+        assert_eq!(sequence(&container, "b"), IecValue::Time(2));
+        assert_eq!(sequence(&container, "c"), IecValue::Time(3));
+        assert_eq!(sequence(&container, "d"), IecValue::Time(4));
+    }
+
+    #[test]
+    fn dispatch_cycle() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO1"), port("b.EI")).unwrap();
+        container.connect(port("b.EO1"), port("a.EI")).unwrap();
+
+        container.send_event(port("a.EI"));
+        container.dispatch();
+
+        assert!(container.events.is_empty());
+        assert_eq!(sequence(&container, "b"), IecValue::Time(MAX_EVENTS as i64));
+    }
+
+    #[test]
+    fn dispatch_transfers_data() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO1"), port("b.EI")).unwrap();
+        container.connect(port("a.N"), port("b.IN")).unwrap();
+
+        container.send_event(port("a.EI"));
+        container.dispatch();
+        let input = container.children["b"].get_data_input("IN").unwrap();
+        assert_eq!(input.get(), IecValue::Time(1));
+    }
+
+    #[test]
+    fn event_connection_mismatch() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        assert!(matches!(
+            container.connect(port("a.EO1"), port("sw.G")),
+            Err(ConnectError::UnknownPort)
+        ));
+        assert!(matches!(
+            container.connect(port("a.N"), port("a.EI")),
+            Err(ConnectError::UnknownPort)
+        ));
+    }
+
+    #[test]
+    fn event_disconnect() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO1"), port("b.EI")).unwrap();
+        container.disconnect(port("a.EO1"), port("b.EI")).unwrap();
+
+        container.send_event(port("a.EI"));
+        container.dispatch();
+        assert!(!container.children["b"]
+            .get_data_output("N")
+            .unwrap()
+            .is_forced());
+        assert!(container.event_connections.is_empty());
+    }
+
+    #[test]
+    fn write() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.write("T#1s", port("a.IN")).unwrap();
+
+        let input = container.children["a"].get_data_input("IN").unwrap();
+        assert_eq!(input.get(), IecValue::Time(1_000_000_000));
+
+        assert!(matches!(
+            container.write("TRUE", port("a.IN")),
+            Err(WriteError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            container.write("T#1s", port("a.N")),
+            Err(WriteError::UnknownPort)
+        ));
+        assert!(matches!(
+            container.write("T#1s", port("b.IN")),
+            Err(WriteError::UnknownBlock)
+        ));
+    }
+
+    #[test]
+    fn trigger_event() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO2"), port("b.EI")).unwrap();
+        container.add_watch(port("a.EI")).unwrap();
+        container.add_watch(port("a.EO1")).unwrap();
+        container.add_watch(port("b.EI")).unwrap();
+
+        container.write("$e", port("a.EI")).unwrap();
+        container.write("$e", port("a.EI")).unwrap();
+        container.dispatch();
+
+        assert_eq!(sequence(&container, "b"), IecValue::Time(4));
+
+        let counts = container
+            .read_watches()
+            .into_iter()
+            .flat_map(|fb| {
+                fb.ports
+                    .into_iter()
+                    .map(move |port| (format!("{}.{}", fb.name, port.name), port.data.value))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![
+                ("a.EI".to_string(), "2".to_string()),
+                ("a.EO1".to_string(), "2".to_string()),
+                ("b.EI".to_string(), "2".to_string()),
+            ]
+        );
+
+        assert!(matches!(
+            container.write("$e", port("a.EO1")),
+            Err(WriteError::UnknownPort)
+        ));
+    }
+
+    #[test]
+    fn force() {
+        let mut container = container();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+        container.add_watch(port("sw.G")).unwrap();
+
+        let output = container.children["sr"].get_data_output("Q").unwrap();
+        let input = container.children["sw"].get_data_input("G").unwrap();
+
+        container.write("TRUE$F", port("sw.G")).unwrap();
+        container.transfer("sr");
+        assert_eq!(input.get(), IecValue::Bool(true));
+        assert!(container.read_watches()[0].ports[0].data.forced);
+
+        container.write("$C", port("sw.G")).unwrap();
+        container.transfer("sr");
+        assert_eq!(input.get(), IecValue::Bool(false));
+        assert!(!container.read_watches()[0].ports[0].data.forced);
+
+        container.write("TRUE$F", port("sr.Q")).unwrap();
+        output.set(IecValue::Bool(false));
+        container.transfer("sr");
+        assert_eq!(input.get(), IecValue::Bool(true));
+
+        container.clear_forces();
+        output.set(IecValue::Bool(false));
+        assert!(!output.is_forced());
+        assert_eq!(output.get(), IecValue::Bool(false));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let container = container();
+        let input = container.children["sw"].get_data_input("G").unwrap();
+
+        input.set(IecValue::Int(1));
+        assert_eq!(input.get(), IecValue::Bool(false));
+        assert_eq!(
+            input.force(IecValue::Int(1)),
+            Err(LiteralError::TypeMismatch {
+                literal: IecType::Int,
+                expected: IecType::Bool,
+            })
+        );
+        assert!(!input.is_forced());
+    }
+
+    #[test]
+    fn reset_forced() {
+        let mut container = container();
+        let input = container.children["sw"].get_data_input("G").unwrap();
+
+        container.write("TRUE$F", port("sw.G")).unwrap();
+        container.reset();
+        assert!(!input.is_forced());
+        assert_eq!(input.get(), IecValue::Bool(false));
+    }
+
+    #[test]
+    fn watches() {
+        let mut container = container();
+        container.add_watch(port("sw.G")).unwrap();
+        container.add_watch(port("sr.Q")).unwrap();
+        container.write("TRUE", port("sw.G")).unwrap();
+
+        assert_eq!(
+            container.read_watches(),
+            vec![
+                server::WatchedFunctionBlock {
+                    name: "sr".into(),
+                    ports: vec![server::WatchedPort {
+                        name: "Q".into(),
+                        data: server::WatchedData {
+                            value: "FALSE".into(),
+                            forced: false
+                        }
+                    }]
+                },
+                server::WatchedFunctionBlock {
+                    name: "sw".into(),
+                    ports: vec![server::WatchedPort {
+                        name: "G".into(),
+                        data: server::WatchedData {
+                            value: "TRUE".into(),
+                            forced: false
+                        }
+                    }]
+                },
+            ]
+        );
+
+        container.remove_watch(&port("sr.Q")).unwrap();
+        container.remove_child("sw");
+        assert!(container.read_watches().is_empty());
+
+        assert!(matches!(
+            container.remove_watch(&port("sr.Q")),
+            Err(WatchError::NotWatched)
+        ));
+        assert!(matches!(
+            container.add_watch(port("sr.X")),
+            Err(WatchError::UnknownPort)
+        ));
+    }
+
+    #[test]
+    fn device_watches() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut device = SimpleContainer::new(RootFactory::new(factory));
+
+        let request = |destination: &str, action, data| Request {
+            destination: Destination::from(destination),
+            action,
+            data: Some(data),
+        };
+        let fb = |name: &str, r#type: &str| Data::FunctionBlock {
+            name: name.into(),
+            r#type: r#type.into(),
+        };
+        let watch = |source: &str| Data::Watch {
+            source: source.into(),
+            destination: "*".into(),
+        };
+
+        for (destination, action, data) in [
+            ("", Action::Create, fb("RES1", "EMB_RES")),
+            ("", Action::Create, fb("RES2", "EMB_RES")),
+            ("RES2", Action::Create, fb("sr", "E_SR")),
+            ("RES2", Action::Create, watch("sr.Q")),
+        ] {
+            device
+                .process_request(request(destination, action, data))
+                .unwrap();
+        }
+
+        let result = device
+            .process_request(request(
+                "",
+                Action::Read,
+                Data::Watches { resources: vec![] },
+            ))
+            .unwrap();
+
+        assert_eq!(
+            result,
+            Some(Data::Watches {
+                resources: vec![server::WatchedResource {
+                    name: "RES2".into(),
+                    function_blocks: vec![server::WatchedFunctionBlock {
+                        name: "sr".into(),
+                        ports: vec![server::WatchedPort {
+                            name: "Q".into(),
+                            data: server::WatchedData {
+                                value: "FALSE".into(),
+                                forced: false
+                            }
+                        }]
+                    }]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn upload_type() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut device = SimpleContainer::new(RootFactory::new(factory));
+
+        let request = |destination: &str, data| Request {
+            destination: Destination::from(destination),
+            action: Action::Create,
+            data: Some(data),
+        };
+        let r#type = crate::typelib::parse(
+            r#"<FBType Name="SWITCH_ON">
+                <InterfaceList>
+                    <InputVars><VarDeclaration Name="ON" Type="BOOL"/></InputVars>
+                </InterfaceList>
+                <FBNetwork>
+                    <FB Name="sw" Type="E_SWITCH"/>
+                    <DataConnections><Connection Source="ON" Destination="sw.G"/></DataConnections>
+                </FBNetwork>
+            </FBType>"#,
+        )
+        .unwrap();
+
+        device
+            .process_request(request(
+                "",
+                Data::FunctionBlock {
+                    name: "RES".into(),
+                    r#type: "EMB_RES".into(),
+                },
+            ))
+            .unwrap();
+        device
+            .process_request(request("", Data::FunctionBlockType(r#type)))
+            .unwrap();
+        device
+            .process_request(request(
+                "RES",
+                Data::FunctionBlock {
+                    name: "a".into(),
+                    r#type: "SWITCH_ON".into(),
+                },
+            ))
+            .unwrap();
+
+        assert_eq!(
+            device.process_request(request(
+                "",
+                Data::FunctionBlockType(crate::typelib::fbt::FbType {
+                    name: "EMPTY".into(),
+                    ..Default::default()
+                })
+            )),
+            Err(Error::UnsupportedType)
+        );
+        assert_eq!(
+            device.process_request(request(
+                "",
+                Data::DataType(crate::typelib::fbt::DataType {
+                    name: "POINT".into()
+                })
+            )),
+            Err(Error::UnsupportedType)
+        );
+    }
+
+    #[test]
+    fn query_function_blocks() {
+        let mut container = container();
+        container.add_child("sw2".into(), "E_SWITCH").unwrap();
+
+        let query = |container: &mut SimpleContainer<_>, name: &str, r#type: &str| {
+            container.local_process_request(Request {
+                destination: Destination::from(""),
+                action: Action::Query,
+                data: Some(Data::FunctionBlock {
+                    name: name.into(),
+                    r#type: r#type.into(),
+                }),
+            })
+        };
+        let names = |result: Result<Option<Data>, Error>| match result {
+            Ok(Some(Data::FunctionBlockList(blocks))) => blocks
+                .into_iter()
+                .map(|fb| format!("{}:{}", fb.name, fb.r#type.unwrap_or_default()))
+                .collect::<Vec<_>>(),
+            result => panic!("Unexpected result: {result:?}"),
+        };
+
+        assert_eq!(
+            names(query(&mut container, "*", "*")),
+            ["sr:E_SR", "sw:E_SWITCH", "sw2:E_SWITCH"]
+        );
+        assert_eq!(
+            names(query(&mut container, "*", "E_SWITCH")),
+            ["sw:E_SWITCH", "sw2:E_SWITCH"]
+        );
+        assert_eq!(names(query(&mut container, "sr", "*")), ["sr:E_SR"]);
+        assert_eq!(
+            names(query(&mut container, "sw", "E_SWITCH")),
+            ["sw:E_SWITCH"]
+        );
+
+        assert_eq!(
+            query(&mut container, "*", "E_CYCLE"),
+            Err(Error::NoSuchObject)
+        );
+        assert_eq!(
+            query(&mut container, "sr", "E_SWITCH"),
+            Err(Error::NoSuchObject)
+        );
+        assert_eq!(query(&mut container, "x", "*"), Err(Error::NoSuchObject));
+
+        let mut empty = SimpleContainer::new(StandardFactory::new());
+        assert_eq!(query(&mut empty, "*", "*"), Ok(None));
+    }
+
+    #[test]
+    fn query_connections() {
+        let mut container = container();
+        container.add_child("r".into(), "RELAY").unwrap();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+        container.connect(port("r.EO1"), port("r.EI")).unwrap();
+
+        let query = |container: &mut SimpleContainer<_>, source: &str, destination: &str| {
+            container.local_process_request(Request {
+                destination: Destination::from(""),
+                action: Action::Query,
+                data: Some(Data::Connection {
+                    source: source.into(),
+                    destination: destination.into(),
+                }),
+            })
+        };
+        let connection = |source: &str, destination: &str| server::ConnectionInfo {
+            source: source.into(),
+            destination: destination.into(),
+        };
+
+        assert_eq!(
+            query(&mut container, "*", "*"),
+            Ok(Some(Data::ConnectionList(vec![
+                connection("r.EO1", "r.EI"),
+                connection("sr.Q", "sw.G"),
+            ])))
+        );
+        assert_eq!(
+            query(&mut container, "*", "sw.G"),
+            Ok(Some(Data::ConnectionList(vec![connection("sr.Q", "sw.G")])))
+        );
+        assert_eq!(
+            query(&mut container, "sw.G", "*"),
+            Ok(Some(Data::ConnectionList(vec![])))
+        );
+    }
+
+    #[test]
+    fn query_types() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut device = SimpleContainer::new(RootFactory::new(factory));
+
+        let query = |device: &mut SimpleContainer<_>, data| {
+            device.local_process_request(Request {
+                destination: Destination::from(""),
+                action: Action::Query,
+                data: Some(data),
+            })
+        };
+        let fb_type = |name: &str| {
+            Data::FunctionBlockType(crate::typelib::fbt::FbType {
+                name: name.into(),
+                ..Default::default()
+            })
+        };
+        let data_type =
+            |name: &str| Data::DataType(crate::typelib::fbt::DataType { name: name.into() });
+
+        let Ok(Some(Data::FunctionBlockList(types))) = query(&mut device, fb_type("*")) else {
+            panic!("Expected a list of types");
+        };
+        let names: Vec<_> = types.iter().map(|fb| fb.name.as_str()).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(names.contains(&"E_SR") && names.contains(&"E_SWITCH"));
+        assert!(types.iter().all(|fb| fb.r#type.is_none()));
+
+        let Ok(Some(Data::FunctionBlockType(r#type))) = query(&mut device, fb_type("E_SWITCH"))
+        else {
+            panic!("Expected a type");
+        };
+        let inputs = &r#type.interface_list.input_vars.variables;
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(
+            (inputs[0].name.as_str(), inputs[0].r#type.as_str()),
+            ("G", "BOOL")
+        );
+        assert_eq!(
+            query(&mut device, fb_type("UNKNOWN")),
+            Err(Error::NoSuchObject)
+        );
+
+        let Ok(Some(Data::DataTypeList(types))) = query(&mut device, data_type("*")) else {
+            panic!("Expected a list of data types");
+        };
+        assert_eq!(types.len(), IecType::ALL.len());
+        assert_eq!(
+            query(&mut device, data_type("LREAL")),
+            Ok(Some(Data::DataTypeList(vec![
+                crate::typelib::fbt::DataType {
+                    name: "LREAL".into()
+                }
+            ])))
+        );
+        assert_eq!(
+            query(&mut device, data_type("POINT")),
+            Err(Error::NoSuchObject)
+        );
+
+        assert_eq!(
+            query(
+                &mut device,
+                Data::AdapterType(crate::typelib::fbt::AdapterType {
+                    name: "*".into(),
+                    ..Default::default()
+                })
+            ),
+            Ok(Some(Data::AdapterTypeList(vec![])))
+        );
+    }
+
+    fn lifecycle(
+        container: &mut SimpleContainer<StandardFactory>,
+        destination: &str,
+        action: Action,
+    ) -> Result<Option<Data>, Error> {
+        container.process_request(Request {
+            destination: Destination::from(destination),
+            action,
+            data: None,
+        })
+    }
+
+    #[test]
+    fn child_lifecycle() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO1"), port("b.EI")).unwrap();
+
+        assert_eq!(lifecycle(&mut container, "b", Action::Stop), Ok(None));
+        assert_eq!(
+            lifecycle(&mut container, "x", Action::Stop),
+            Err(Error::InvalidDestination)
+        );
+
+        // a stopped block doesn't receive events
+        container.send_event(port("a.EI"));
+        container.dispatch();
+        assert_eq!(sequence(&container, "a"), IecValue::Time(1));
+        assert!(!container.children["b"]
+            .get_data_output("N")
+            .unwrap()
+            .is_forced());
+
+        // the start hook fires EO2, which is not connected
+        assert_eq!(lifecycle(&mut container, "b", Action::Start), Ok(None));
+        assert!(container.events.is_empty());
+        container.send_event(port("a.EI"));
+        container.dispatch();
+        assert_eq!(sequence(&container, "b"), IecValue::Time(3));
+
+        // resetting drops the forces of the block only
+        container.write("T#5s$F", port("a.N")).unwrap();
+        container.write("T#5s$F", port("b.N")).unwrap();
+        assert_eq!(lifecycle(&mut container, "b", Action::Reset), Ok(None));
+        assert_eq!(container.forced, BTreeSet::from([port("a.N")]));
+        assert!(!container.children["b"]
+            .get_data_output("N")
+            .unwrap()
+            .is_forced());
+
+        // killing drops queued events
+        container.send_event(port("b.EI"));
+        assert_eq!(lifecycle(&mut container, "b", Action::Kill), Ok(None));
+        assert!(container.events.is_empty());
+    }
+
+    #[test]
+    fn device_child_lifecycle() {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let mut device = SimpleContainer::new(RootFactory::new(factory));
+
+        let request = |destination: &str, action, data| Request {
+            destination: Destination::from(destination),
+            action,
+            data,
+        };
+        let fb = |name: &str, r#type: &str| {
+            Some(Data::FunctionBlock {
+                name: name.into(),
+                r#type: r#type.into(),
+            })
+        };
+        let write = |value: &str, destination: &str| {
+            Some(Data::Connection {
+                source: value.into(),
+                destination: destination.into(),
+            })
+        };
+
+        for (destination, action, data) in [
+            ("", Action::Create, fb("RES", "EMB_RES")),
+            ("RES", Action::Create, fb("sr", "E_SR")),
+            (
+                "RES",
+                Action::Create,
+                Some(Data::Watch {
+                    source: "sr.Q".into(),
+                    destination: "*".into(),
+                }),
+            ),
+            ("RES", Action::Write, write("TRUE$F", "sr.Q")),
+        ] {
+            device
+                .process_request(request(destination, action, data))
+                .unwrap();
+        }
+
+        // the block is stopped, not the resource
+        assert_eq!(
+            device.process_request(request("RES", Action::Stop, fb("sr", ""))),
+            Ok(None)
+        );
+        assert!(device.stopped.is_empty());
+        assert_eq!(
+            device.process_request(request("RES", Action::Stop, fb("x", ""))),
+            Err(Error::InvalidDestination)
+        );
+
+        // resetting the block clears its forces
+        assert_eq!(
+            device.process_request(request("RES", Action::Reset, fb("sr", ""))),
+            Ok(None)
+        );
+        let watches = device
+            .process_request(request(
+                "",
+                Action::Read,
+                Some(Data::Watches { resources: vec![] }),
+            ))
+            .unwrap();
+        let Some(Data::Watches { resources }) = watches else {
+            panic!("Expected watches");
+        };
+        assert_eq!(
+            resources[0].function_blocks[0].ports[0].data,
+            server::WatchedData {
+                value: "FALSE".into(),
+                forced: false,
+            }
+        );
+    }
+
+    #[test]
+    fn start_children() {
+        let mut container = container();
+        container.add_child("a".into(), "RELAY").unwrap();
+        container.add_child("b".into(), "RELAY").unwrap();
+        container.connect(port("a.EO2"), port("b.EI")).unwrap();
+
+        container.start();
+        container.dispatch();
+        assert_eq!(sequence(&container, "b"), IecValue::Time(1));
+        assert_eq!(
+            container.children["a"]
+                .get_event_output("EO2")
+                .unwrap()
+                .get_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn remove_child_drops_connections() {
+        let mut container = container();
+        container.connect(port("sr.Q"), port("sw.G")).unwrap();
+        container.remove_child("sw");
+        assert!(container.data_connections.is_empty());
     }
 }
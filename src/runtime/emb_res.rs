@@ -1,64 +1,620 @@
-use crate::protocol::server::{Action, Data, Error};
-use crate::runtime::container::SimpleContainer;
+use crate::protocol::server::{Action, Data, Error, WatchedResource};
+use crate::runtime::container::{PortDestination, SimpleContainer};
 use crate::runtime::factory::FunctionBlockFactory;
-use crate::runtime::fb::FunctionBlock;
+use crate::runtime::fb::{EventContext, FunctionBlock};
+use crate::runtime::timer::{Clock, TokioClock};
 use crate::runtime::Request;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// The lifecycle state of a resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceState {
+    /// Created or reset, but never started.
+    Idle,
+    Running,
+    Stopped,
+    /// Killed, needs to be reset before it can be started again.
+    Killed,
+}
+
+impl Display for ResourceState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Idle => f.write_str("idle"),
+            Self::Running => f.write_str("running"),
+            Self::Stopped => f.write_str("stopped"),
+            Self::Killed => f.write_str("killed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum StateError {
+    #[error("Cannot {action} a resource which is {state}")]
+    InvalidTransition {
+        action: &'static str,
+        state: ResourceState,
+    },
+}
 
 pub struct EmbeddedResource<F>
 where
     F: FunctionBlockFactory,
 {
-    container: SimpleContainer<F>,
+    container: Arc<Mutex<SimpleContainer<F>>>,
+    state: ResourceState,
+    /// The event processing task, while running.
+    task: Option<JoinHandle<()>>,
+    /// Cleared when the event processing task gets aborted.
+    running: Arc<AtomicBool>,
+    /// Wakes up the event processing task when new events are queued.
+    wakeup: Arc<Notify>,
+    /// The time source for the timers of the function blocks.
+    clock: Arc<dyn Clock>,
 }
 
 impl<F> EmbeddedResource<F>
 where
-    F: FunctionBlockFactory,
+    F: FunctionBlockFactory + Send + 'static,
 {
     pub fn new(factory: F) -> Self {
+        Self::with_clock(factory, Arc::new(TokioClock::new()))
+    }
+
+    /// Create a new resource, using a custom clock for its timers.
+    pub fn with_clock(factory: F, clock: Arc<dyn Clock>) -> Self {
         Self {
-            container: SimpleContainer::new(factory),
+            container: Arc::new(Mutex::new(SimpleContainer::new(factory))),
+            state: ResourceState::Idle,
+            task: None,
+            running: Default::default(),
+            wakeup: Default::default(),
+            clock,
         }
     }
 
-    pub fn start(&mut self) {
+    pub fn state(&self) -> ResourceState {
+        self.state
+    }
+
+    /// Queue an event for an event input, it will be processed while the resource is running.
+    pub fn send_event(&mut self, event: PortDestination) {
+        self.lock().send_event(event);
+        self.wakeup.notify_one();
+    }
+
+    /// Start all function blocks, and start processing events, including those queued while the
+    /// resource was not running.
+    ///
+    /// This spawns the event processing task, and so must be called from within a tokio runtime.
+    pub fn start(&mut self) -> Result<(), StateError> {
+        self.transition("start", &[ResourceState::Idle, ResourceState::Stopped])?;
         log::info!("Starting");
+
+        {
+            // events fired when starting, like the ones of E_RESTART, come first
+            let mut container = self.lock();
+            container.set_time(self.clock.now());
+            let pending = container.take_events();
+            container.start();
+            for event in pending {
+                container.send_event(event);
+            }
+        }
+
+        let container = self.container.clone();
+        let wakeup = self.wakeup.clone();
+        let running = Arc::new(AtomicBool::new(true));
+        let clock = self.clock.clone();
+        self.running = running.clone();
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                // deliver one event at a time, so that stopping takes effect between events
+                while dispatch_next(&container, &running, clock.now()) {
+                    tokio::task::yield_now().await;
+                }
+                let deadline = lock(&container).next_deadline();
+                match deadline {
+                    Some(deadline) => tokio::select! {
+                        _ = wakeup.notified() => {}
+                        _ = clock.sleep_until(deadline) => {}
+                    },
+                    None => wakeup.notified().await,
+                }
+            }
+        }));
+        self.state = ResourceState::Running;
+        Ok(())
     }
 
-    pub fn stop(&mut self) {
+    /// Stop processing events after the event currently being processed, and stop all function
+    /// blocks.
+    ///
+    /// Events fired by the function blocks when stopping, like the ones of E_RESTART, are
+    /// processed before this returns. Other queued events are kept, and processed when the
+    /// resource is started again.
+    pub fn stop(&mut self) -> Result<(), StateError> {
+        self.transition("stop", &[ResourceState::Running])?;
         log::info!("Stopping");
+
+        self.abort();
+        {
+            let mut container = self.lock();
+            container.set_time(self.clock.now());
+            let pending = container.take_events();
+            container.stop();
+            container.dispatch();
+            for event in pending {
+                container.send_event(event);
+            }
+        }
+
+        self.state = ResourceState::Stopped;
+        Ok(())
+    }
+
+    /// Abort processing events immediately, dropping all queued events, and kill all function
+    /// blocks.
+    pub fn kill(&mut self) -> Result<(), StateError> {
+        self.transition("kill", &[ResourceState::Running, ResourceState::Stopped])?;
+        log::info!("Killing");
+
+        self.abort();
+        self.lock().kill();
+        self.state = ResourceState::Killed;
+        Ok(())
+    }
+
+    /// Restore all function blocks to their initial values, and clear all forces.
+    pub fn reset(&mut self) -> Result<(), StateError> {
+        self.transition(
+            "reset",
+            &[
+                ResourceState::Idle,
+                ResourceState::Stopped,
+                ResourceState::Killed,
+            ],
+        )?;
+        log::info!("Resetting");
+
+        self.lock().reset();
+        self.state = ResourceState::Idle;
+        Ok(())
+    }
+
+    fn transition(&self, action: &'static str, from: &[ResourceState]) -> Result<(), StateError> {
+        if from.contains(&self.state) {
+            Ok(())
+        } else {
+            log::warn!("Cannot {action} a resource which is {}", self.state);
+            Err(StateError::InvalidTransition {
+                action,
+                state: self.state,
+            })
+        }
+    }
+
+    fn abort(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SimpleContainer<F>> {
+        lock(&self.container)
+    }
+}
+
+/// Handle expired timers and deliver the next event, unless the task delivering it was stopped.
+fn dispatch_next<F>(
+    container: &Mutex<SimpleContainer<F>>,
+    running: &AtomicBool,
+    now: Duration,
+) -> bool
+where
+    F: FunctionBlockFactory,
+{
+    let mut container = lock(container);
+    // checked while holding the lock, stopping the resource holds it as well
+    if !running.load(Ordering::Acquire) {
+        return false;
+    }
+    container.advance(now);
+    container.dispatch_next()
+}
+
+fn lock<F>(container: &Mutex<SimpleContainer<F>>) -> MutexGuard<'_, SimpleContainer<F>>
+where
+    F: FunctionBlockFactory,
+{
+    // a block panicking while handling an event must not take down the whole resource
+    container.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<F> Drop for EmbeddedResource<F>
+where
+    F: FunctionBlockFactory,
+{
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
     }
 }
 
 impl<F> FunctionBlock for EmbeddedResource<F>
 where
-    F: FunctionBlockFactory + Send,
+    F: FunctionBlockFactory + Send + 'static,
 {
     fn type_name(&self) -> String {
         "EMB_RES".to_string()
     }
 
+    fn start(&mut self, _context: &mut EventContext) -> Result<(), Error> {
+        Ok(EmbeddedResource::start(self)?)
+    }
+
+    fn stop(&mut self, _context: &mut EventContext) -> Result<(), Error> {
+        Ok(EmbeddedResource::stop(self)?)
+    }
+
+    fn kill(&mut self) -> Result<(), Error> {
+        Ok(EmbeddedResource::kill(self)?)
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        Ok(EmbeddedResource::reset(self)?)
+    }
+
     fn request(&mut self, request: Request) -> Result<Option<Data>, Error> {
         log::info!("Request: {request:?}");
 
-        if !request.destination.is_empty() {
-            return Err(Error::InvalidDestination);
-        }
-
         match (request.action, request.data) {
-            (Action::Start, _) => {
-                self.start();
-                Ok(None)
+            (Action::Read, Some(Data::Watches { .. })) if request.destination.is_empty() => {
+                Ok(Some(Data::Watches {
+                    // the name is filled in by the device
+                    resources: vec![WatchedResource {
+                        name: String::new(),
+                        function_blocks: self.lock().read_watches(),
+                    }],
+                }))
             }
-            (Action::Stop, _) => {
-                self.stop();
-                Ok(None)
+            (action, data) => {
+                // lifecycle requests for the function blocks are routed by the container
+                let result = self.lock().process_request(Request {
+                    destination: request.destination,
+                    action,
+                    data,
+                });
+                // deliver events triggered by the request
+                self.wakeup.notify_one();
+                result
             }
-            (action, data) => self.container.process_request(Request {
-                destination: Default::default(),
-                action,
-                data,
-            }),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::basic::{Action as EccAction, BasicFunctionBlock, BasicType, Guard};
+    use crate::runtime::factory::StandardFactory;
+    use crate::runtime::timer::VirtualClock;
+    use crate::runtime::value::IecType;
+
+    /// A resource with a `PASS` block named `p`, copying `IN` to `OUT`, and `OUT` being watched.
+    fn resource() -> EmbeddedResource<StandardFactory> {
+        resource_with_clock(Arc::new(TokioClock::new()))
+    }
+
+    fn resource_with_clock(clock: Arc<dyn Clock>) -> EmbeddedResource<StandardFactory> {
+        let pass = Arc::new(
+            BasicType::builder("PASS")
+                .event_input("REQ", &["IN"])
+                .event_output("CNF", &["OUT"])
+                .input("IN", IecType::Bool)
+                .output("OUT", IecType::Bool)
+                .algorithm("REQ", |vars| vars.set("OUT", vars.get("IN")?.clone()))
+                .state("START", [])
+                .state("REQ", [EccAction::algorithm("REQ").output("CNF")])
+                .transition("START", "REQ", Guard::event("REQ"))
+                .transition("REQ", "START", Guard::always())
+                .build()
+                .unwrap(),
+        );
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        factory.register_type("PASS", move || BasicFunctionBlock::new(pass.clone()));
+
+        let mut resource = EmbeddedResource::with_clock(factory, clock);
+        request(
+            &mut resource,
+            Action::Create,
+            Data::FunctionBlock {
+                name: "p".into(),
+                r#type: "PASS".into(),
+            },
+        )
+        .unwrap();
+        request(
+            &mut resource,
+            Action::Create,
+            Data::Watch {
+                source: "p.OUT".into(),
+                destination: String::new(),
+            },
+        )
+        .unwrap();
+        resource
+    }
+
+    fn request<F: FunctionBlockFactory + Send + 'static>(
+        resource: &mut EmbeddedResource<F>,
+        action: Action,
+        data: Data,
+    ) -> Result<Option<Data>, Error> {
+        resource.request(Request {
+            destination: Default::default(),
+            action,
+            data: Some(data),
+        })
+    }
+
+    /// Apply a lifecycle action through the hooks, the way the device does.
+    fn action<F: FunctionBlockFactory + Send + 'static>(
+        resource: &mut EmbeddedResource<F>,
+        action: Action,
+    ) -> Result<(), Error> {
+        let mut context = EventContext::default();
+        match action {
+            Action::Start => FunctionBlock::start(resource, &mut context),
+            Action::Stop => FunctionBlock::stop(resource, &mut context),
+            Action::Kill => FunctionBlock::kill(resource),
+            Action::Reset => FunctionBlock::reset(resource),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write `value` to `IN`, and trigger `REQ`.
+    fn pass(resource: &mut EmbeddedResource<StandardFactory>, value: &str) {
+        for (source, destination) in [(value, "p.IN"), ("$e", "p.REQ")] {
+            let data = Data::Connection {
+                source: source.into(),
+                destination: destination.into(),
+            };
+            request(resource, Action::Write, data).unwrap();
+        }
+    }
+
+    fn out(resource: &mut EmbeddedResource<StandardFactory>) -> String {
+        watched(resource, "p", "OUT")
+    }
+
+    fn watched(
+        resource: &mut EmbeddedResource<StandardFactory>,
+        block: &str,
+        port: &str,
+    ) -> String {
+        let data = Data::Watches { resources: vec![] };
+        let Ok(Some(Data::Watches { resources })) = request(resource, Action::Read, data) else {
+            panic!("Failed to read watches");
+        };
+        resources[0]
+            .function_blocks
+            .iter()
+            .filter(|fb| fb.name == block)
+            .flat_map(|fb| &fb.ports)
+            .find(|watched| watched.name == port)
+            .map(|watched| watched.data.value.clone())
+            .unwrap_or_else(|| panic!("{block}.{port} is not watched"))
+    }
+
+    /// Give the event processing task a chance to run.
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn transitions() {
+        let mut resource = resource();
+        assert_eq!(resource.state(), ResourceState::Idle);
+
+        assert_eq!(
+            action(&mut resource, Action::Stop),
+            Err(Error::InvalidState)
+        );
+        assert_eq!(
+            action(&mut resource, Action::Kill),
+            Err(Error::InvalidState)
+        );
+
+        assert_eq!(action(&mut resource, Action::Start), Ok(()));
+        assert_eq!(resource.state(), ResourceState::Running);
+        assert_eq!(
+            action(&mut resource, Action::Start),
+            Err(Error::InvalidState)
+        );
+        assert_eq!(
+            action(&mut resource, Action::Reset),
+            Err(Error::InvalidState)
+        );
+
+        assert_eq!(action(&mut resource, Action::Stop), Ok(()));
+        assert_eq!(resource.state(), ResourceState::Stopped);
+        assert_eq!(action(&mut resource, Action::Start), Ok(()));
+
+        assert_eq!(action(&mut resource, Action::Kill), Ok(()));
+        assert_eq!(resource.state(), ResourceState::Killed);
+        assert_eq!(
+            action(&mut resource, Action::Start),
+            Err(Error::InvalidState)
+        );
+
+        assert_eq!(action(&mut resource, Action::Reset), Ok(()));
+        assert_eq!(resource.state(), ResourceState::Idle);
+        assert_eq!(action(&mut resource, Action::Start), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn events_are_processed_while_running() {
+        let mut resource = resource();
+
+        pass(&mut resource, "TRUE");
+        settle().await;
+        assert_eq!(out(&mut resource), "FALSE");
+
+        // events queued before starting are processed once started
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(out(&mut resource), "TRUE");
+
+        resource.stop().unwrap();
+        pass(&mut resource, "FALSE");
+        settle().await;
+        assert_eq!(out(&mut resource), "TRUE");
+
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(out(&mut resource), "FALSE");
+    }
+
+    #[tokio::test]
+    async fn reset_restores_initial_values() {
+        let mut resource = resource();
+        resource.start().unwrap();
+        pass(&mut resource, "TRUE");
+        settle().await;
+        assert_eq!(out(&mut resource), "TRUE");
+
+        resource.stop().unwrap();
+        resource.reset().unwrap();
+        assert_eq!(out(&mut resource), "FALSE");
+
+        // the input was reset as well
+        resource.start().unwrap();
+        let data = Data::Connection {
+            source: "$e".into(),
+            destination: "p.REQ".into(),
+        };
+        request(&mut resource, Action::Write, data).unwrap();
+        settle().await;
+        assert_eq!(out(&mut resource), "FALSE");
+    }
+
+    #[tokio::test]
+    async fn restart() {
+        let mut resource = resource();
+        let create = |resource: &mut EmbeddedResource<_>, data| {
+            request(resource, Action::Create, data).unwrap();
+        };
+        create(
+            &mut resource,
+            Data::FunctionBlock {
+                name: "r".into(),
+                r#type: "E_RESTART".into(),
+            },
+        );
+        for port in ["r.COLD", "r.WARM"] {
+            create(
+                &mut resource,
+                Data::Watch {
+                    source: port.into(),
+                    destination: String::new(),
+                },
+            );
+        }
+        create(
+            &mut resource,
+            Data::Connection {
+                source: "r.STOP".into(),
+                destination: "p.REQ".into(),
+            },
+        );
+        let data = Data::Connection {
+            source: "TRUE".into(),
+            destination: "p.IN".into(),
+        };
+        request(&mut resource, Action::Write, data).unwrap();
+
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(watched(&mut resource, "r", "COLD"), "1");
+        assert_eq!(watched(&mut resource, "r", "WARM"), "0");
+
+        // STOP is processed before the resource stops
+        resource.stop().unwrap();
+        assert_eq!(out(&mut resource), "TRUE");
+
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(watched(&mut resource, "r", "COLD"), "1");
+        assert_eq!(watched(&mut resource, "r", "WARM"), "1");
+
+        // a reset resource starts cold again
+        resource.stop().unwrap();
+        resource.reset().unwrap();
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(watched(&mut resource, "r", "COLD"), "2");
+    }
+
+    #[tokio::test]
+    async fn timers() {
+        let clock = VirtualClock::new();
+        let mut resource = resource_with_clock(Arc::new(clock.clone()));
+        let create = |resource: &mut EmbeddedResource<_>, data| {
+            request(resource, Action::Create, data).unwrap();
+        };
+        create(
+            &mut resource,
+            Data::FunctionBlock {
+                name: "c".into(),
+                r#type: "E_CYCLE".into(),
+            },
+        );
+        create(
+            &mut resource,
+            Data::Watch {
+                source: "c.EO".into(),
+                destination: String::new(),
+            },
+        );
+        for (source, destination) in [("T#2ms", "c.DT"), ("$e", "c.START")] {
+            let data = Data::Connection {
+                source: source.into(),
+                destination: destination.into(),
+            };
+            request(&mut resource, Action::Write, data).unwrap();
+        }
+
+        resource.start().unwrap();
+        settle().await;
+        assert_eq!(watched(&mut resource, "c", "EO"), "0");
+
+        clock.advance(Duration::from_millis(1));
+        settle().await;
+        assert_eq!(watched(&mut resource, "c", "EO"), "0");
+
+        clock.advance(Duration::from_millis(1));
+        settle().await;
+        assert_eq!(watched(&mut resource, "c", "EO"), "1");
+
+        clock.advance(Duration::from_millis(4));
+        settle().await;
+        assert_eq!(watched(&mut resource, "c", "EO"), "3");
+
+        // timers don't fire while the resource is stopped
+        resource.stop().unwrap();
+        clock.advance(Duration::from_millis(4));
+        settle().await;
+        assert_eq!(watched(&mut resource, "c", "EO"), "3");
+    }
+}
@@ -1,5 +1,11 @@
-use crate::blocks::std::{Cycle, SetReset, Switch};
+use crate::blocks::functions::{function_types, FunctionCall};
+use crate::blocks::std::{basic_types, Cycle, Delay, NTable, Restart, Table, Train};
+use crate::runtime::basic::{BasicFunctionBlock, BasicType};
+use crate::runtime::composite::{CompositeCreator, CompositeType};
 use crate::runtime::fb::FunctionBlock;
+use crate::runtime::interface::Interface;
+use crate::typelib::fbt::FbType;
+use crate::typelib::{self, LoadError};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, RwLock};
@@ -10,14 +16,34 @@ pub enum CreationError {
     UnknownType,
     #[error("Internal error")]
     Internal,
+    #[error("Invalid type: {0}")]
+    InvalidType(String),
 }
 
 pub trait FunctionBlockFactory {
     fn create(&self, r#type: &str) -> Result<Box<dyn FunctionBlock>, CreationError>;
+
+    /// The names of the types [`Self::create`] can create, sorted by name.
+    fn types(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// The interface of a type, or `None` if the type is unknown.
+    fn interface(&self, r#type: &str) -> Option<Interface> {
+        self.create(r#type).ok().map(|fb| fb.interface())
+    }
+
+    /// Register a type definition, making it available to [`Self::create`].
+    fn register(&self, r#type: &FbType) -> Result<(), LoadError> {
+        Err(LoadError::Unsupported(format!(
+            "Registering type {}",
+            r#type.name
+        )))
+    }
 }
 
 pub trait Creator: Send + Sync {
-    fn create(&self) -> Box<dyn FunctionBlock>;
+    fn create(&self) -> Result<Box<dyn FunctionBlock>, CreationError>;
 }
 
 impl<F, T> Creator for F
@@ -25,14 +51,14 @@ where
     F: Fn() -> T + Send + Sync,
     T: FunctionBlock + 'static,
 {
-    fn create(&self) -> Box<dyn FunctionBlock> {
-        Box::new((self)())
+    fn create(&self) -> Result<Box<dyn FunctionBlock>, CreationError> {
+        Ok(Box::new((self)()))
     }
 }
 
 #[derive(Clone)]
 pub struct StandardFactory {
-    types: Arc<RwLock<HashMap<String, Box<dyn Creator>>>>,
+    types: Arc<RwLock<HashMap<String, Arc<dyn Creator>>>>,
 }
 
 impl StandardFactory {
@@ -51,26 +77,75 @@ impl StandardFactory {
         self.types
             .write()
             .unwrap()
-            .insert(name.into(), Box::new(creator));
+            .insert(name.into(), Arc::new(creator));
+    }
+
+    /// Register a basic function block type.
+    pub fn register_basic_type(&mut self, r#type: BasicType) {
+        let name = r#type.name().to_string();
+        let r#type = Arc::new(r#type);
+        self.register_type(name, move || BasicFunctionBlock::new(r#type.clone()));
+    }
+
+    /// Register a composite function block type, creating its internal blocks from this factory.
+    pub fn register_composite_type(&mut self, r#type: CompositeType) {
+        let name = r#type.name().to_string();
+        let creator = CompositeCreator::new(Arc::new(r#type), self.clone());
+        self.register_type(name, creator);
     }
 
     pub fn register_standard_types(&mut self) {
-        self.register_type("E_SR", SetReset::new);
+        for r#type in basic_types() {
+            self.register_basic_type(r#type);
+        }
         self.register_type("E_CYCLE", Cycle::new);
-        self.register_type("E_SWITCH", Switch::new);
+        self.register_type("E_DELAY", Delay::new);
+        self.register_type("E_DELAYR", Delay::retriggerable);
+        self.register_type("E_TRAIN", Train::new);
+        self.register_type("E_TABLE", Table::new);
+        self.register_type("E_N_TABLE", NTable::new);
+        self.register_type("E_RESTART", Restart::new);
+        for r#type in function_types() {
+            let name = r#type.name().to_string();
+            let r#type = Arc::new(r#type);
+            self.register_type(name, move || FunctionCall::new(r#type.clone()));
+        }
+    }
+}
+
+impl Default for StandardFactory {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl FunctionBlockFactory for StandardFactory {
     fn create(&self, r#type: &str) -> Result<Box<dyn FunctionBlock>, CreationError> {
-        match self
+        // release the lock before creating, creators may create nested blocks
+        let creator = self
             .types
             .read()
             .map_err(|_| CreationError::Internal)?
             .get(r#type)
-        {
-            Some(creator) => Ok(creator.create()),
+            .cloned();
+
+        match creator {
+            Some(creator) => creator.create(),
             None => Err(CreationError::UnknownType),
         }
     }
+
+    fn types(&self) -> Vec<String> {
+        let mut types = match self.types.read() {
+            Ok(types) => types.keys().cloned().collect::<Vec<_>>(),
+            Err(_) => vec![],
+        };
+        types.sort_unstable();
+        types
+    }
+
+    fn register(&self, r#type: &FbType) -> Result<(), LoadError> {
+        // clones share the registered types
+        typelib::register(&mut self.clone(), r#type)
+    }
 }
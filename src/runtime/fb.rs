@@ -1,9 +1,20 @@
 use crate::protocol::server::{self, Data};
+use crate::runtime::interface::Interface;
+use crate::runtime::literal::LiteralError;
+use crate::runtime::value::{self, AnyType, IecType, IecValue};
 use crate::runtime::Request;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::Duration;
 
 pub trait FunctionBlock: Send {
     fn type_name(&self) -> String;
 
+    /// The ports of the function block, along with their types.
+    fn interface(&self) -> Interface {
+        Interface::default()
+    }
+
     fn request(&mut self, request: Request) -> Result<Option<Data>, server::Error> {
         if request.destination.is_empty() {
             Err(server::Error::InvalidOperation)
@@ -12,31 +23,473 @@ pub trait FunctionBlock: Send {
         }
     }
 
-    fn get_data_output(&self, name: &str) -> Option<DataOutput> {
+    fn get_data_output(&self, _name: &str) -> Option<DataOutput> {
+        None
+    }
+
+    fn get_data_input(&self, _name: &str) -> Option<DataInput> {
         None
     }
 
-    fn get_data_input(&self, name: &str) -> Option<DataInput> {
+    /// The elements of an array data input, which can only be written as a whole.
+    fn get_array_input(&self, _name: &str) -> Option<Vec<DataInput>> {
         None
     }
 
-    fn get_event_output(&self, name: &str) -> Option<EventOutput> {
+    fn get_event_output(&self, _name: &str) -> Option<EventOutput> {
         None
     }
 
-    fn get_event_input(&self, name: &str) -> Option<EventInput> {
+    fn get_event_input(&self, _name: &str) -> Option<EventInput> {
         None
     }
+
+    /// Called when the function block, or its resource, is started.
+    ///
+    /// Output events fired through the context are delivered once the resource is running.
+    fn start(&mut self, _context: &mut EventContext) -> Result<(), server::Error> {
+        Ok(())
+    }
+
+    /// Called when the function block, or its resource, is stopped.
+    ///
+    /// While stopped, the function block does not receive events. Output events fired through
+    /// the context are still delivered to the connected blocks.
+    fn stop(&mut self, _context: &mut EventContext) -> Result<(), server::Error> {
+        Ok(())
+    }
+
+    /// Called when the function block, or its resource, is killed. Resources held by the
+    /// function block, like sockets or files, should be released.
+    fn kill(&mut self) -> Result<(), server::Error> {
+        Ok(())
+    }
+
+    /// Restore the initial state, as if the function block was just created.
+    fn reset(&mut self) -> Result<(), server::Error> {
+        Ok(())
+    }
+
+    /// Handle an event received on one of the event inputs.
+    ///
+    /// Output events are fired through the context, after the data outputs associated with them
+    /// have been updated.
+    fn receive_event(&mut self, _name: &str, _context: &mut EventContext) {}
+
+    /// Handle the expiry of the timer set through [`EventContext::set_timer`].
+    ///
+    /// The deadline is the time the timer was set to, the current time of the context may be
+    /// later. The timer fires once, it needs to be set again for periodic execution.
+    fn on_timer(&mut self, _deadline: Duration, _context: &mut EventContext) {}
+}
+
+/// A change to the timer of a function block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerChange {
+    Set(Duration),
+    Cancel,
+}
+
+/// The context of a function block handling an input event.
+#[derive(Debug, Default)]
+pub struct EventContext {
+    now: Duration,
+    fired: Vec<String>,
+    timer: Option<TimerChange>,
+}
+
+impl EventContext {
+    /// Create a context for handling an event at the given time of the resource.
+    pub fn at(now: Duration) -> Self {
+        Self {
+            now,
+            ..Default::default()
+        }
+    }
+
+    /// The current time, relative to the start of the resource.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Set the timer of the function block, replacing a timer which was set before.
+    ///
+    /// When the deadline is reached, [`FunctionBlock::on_timer`] gets called.
+    pub fn set_timer(&mut self, deadline: Duration) {
+        self.timer = Some(TimerChange::Set(deadline));
+    }
+
+    /// Cancel the timer of the function block, if it was set.
+    pub fn cancel_timer(&mut self) {
+        self.timer = Some(TimerChange::Cancel);
+    }
+
+    /// Take the change to the timer, if there was one.
+    pub fn take_timer(&mut self) -> Option<TimerChange> {
+        self.timer.take()
+    }
+
+    /// Fire an output event.
+    pub fn fire<S: Into<String>>(&mut self, event: S) {
+        self.fired.push(event.into());
+    }
+
+    /// Take the output events fired so far, in the order they were fired.
+    pub fn take_fired(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.fired)
+    }
 }
 
+/// The type of generic data ports, which gets resolved to one of its elementary types once.
+///
+/// All ports created from clones of the same generic type share it, resolving the type of one
+/// port resolves it for all of them.
 #[derive(Clone, Debug)]
-pub struct DataInput {}
+pub struct GenericType(Arc<Mutex<GenericState>>);
+
+#[derive(Debug)]
+struct GenericState {
+    generic: AnyType,
+    resolved: Option<IecType>,
+    /// The ports of this type, which get converted when it is resolved.
+    ports: Vec<Weak<Mutex<DataPort>>>,
+}
+
+impl GenericType {
+    pub fn new(r#type: AnyType) -> Self {
+        Self(Arc::new(Mutex::new(GenericState {
+            generic: r#type,
+            resolved: None,
+            ports: vec![],
+        })))
+    }
+
+    /// The generic type, while it is not resolved.
+    pub fn generic(&self) -> Option<AnyType> {
+        let state = self.lock();
+        match state.resolved {
+            None => Some(state.generic),
+            Some(_) => None,
+        }
+    }
+
+    /// The resolved type, or the default type of the generic type.
+    pub fn r#type(&self) -> IecType {
+        let state = self.lock();
+        state
+            .resolved
+            .unwrap_or_else(|| state.generic.default_type())
+    }
+
+    /// Resolve the type, unless it is resolved already. Returns if the type is the elementary
+    /// type afterwards.
+    ///
+    /// The values of the ports are converted to the elementary type. If a value can't be
+    /// converted without changing it, the type is not resolved.
+    pub fn resolve(&self, r#type: IecType) -> bool {
+        let ports = {
+            let state = self.lock();
+            match state.resolved {
+                Some(resolved) => return resolved == r#type,
+                None if !state.generic.accepts(r#type) => return false,
+                None => state
+                    .ports
+                    .iter()
+                    .filter_map(Weak::upgrade)
+                    .map(PortHandle)
+                    .collect::<Vec<_>>(),
+            }
+        };
+
+        let Some(values) = ports
+            .iter()
+            .map(|port| port.lock().converted(r#type))
+            .collect::<Option<Vec<_>>>()
+        else {
+            log::warn!("Unable to convert the current values to {type}");
+            return false;
+        };
+        for (port, value) in ports.iter().zip(values) {
+            let mut port = port.lock();
+            port.r#type = r#type;
+            port.value = value;
+        }
+        self.lock().resolved = Some(r#type);
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, GenericState> {
+        // the state is plain data only, there is no invariant a panic could break
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The shared state of a data port.
+#[derive(Debug)]
+struct DataPort {
+    r#type: IecType,
+    /// The generic type of the port, if it is generic.
+    generic: Option<GenericType>,
+    value: IecValue,
+    forced: bool,
+}
+
+impl DataPort {
+    /// Check that a value matches the type of the port.
+    fn check(&self, value: &IecValue) -> Result<(), LiteralError> {
+        match value.r#type() {
+            r#type if r#type == self.r#type => Ok(()),
+            r#type => Err(LiteralError::TypeMismatch {
+                literal: r#type,
+                expected: self.r#type,
+            }),
+        }
+    }
+
+    /// The value of the port converted to another type, unless that changes the value.
+    fn converted(&self, r#type: IecType) -> Option<IecValue> {
+        if self.value == self.r#type.default_value() {
+            return Some(r#type.default_value());
+        }
+        let value = value::convert(&self.value, r#type).ok()?;
+        let reverted = value::convert(&value, self.r#type).ok()?;
+        (reverted == self.value).then_some(value)
+    }
+}
 
 #[derive(Clone, Debug)]
-pub struct DataOutput {}
+struct PortHandle(Arc<Mutex<DataPort>>);
+
+impl PortHandle {
+    fn new(r#type: IecType) -> Self {
+        Self(Arc::new(Mutex::new(DataPort {
+            r#type,
+            generic: None,
+            value: r#type.default_value(),
+            forced: false,
+        })))
+    }
+
+    fn generic(generic: GenericType) -> Self {
+        let r#type = generic.r#type();
+        let port = Arc::new(Mutex::new(DataPort {
+            r#type,
+            generic: Some(generic.clone()),
+            value: r#type.default_value(),
+            forced: false,
+        }));
+        generic.lock().ports.push(Arc::downgrade(&port));
+        Self(port)
+    }
+
+    fn generic_type(&self) -> Option<AnyType> {
+        let generic = self.lock().generic.clone();
+        generic.as_ref().and_then(GenericType::generic)
+    }
+
+    fn resolve(&self, r#type: IecType) -> bool {
+        let generic = self.lock().generic.clone();
+        match generic {
+            Some(generic) => generic.resolve(r#type),
+            None => self.lock().r#type == r#type,
+        }
+    }
+
+    fn set(&self, value: IecValue) {
+        let mut port = self.lock();
+        if let Err(err) = port.check(&value) {
+            log::error!("Refusing value {value}: {err}");
+            return;
+        }
+        if !port.forced {
+            port.value = value;
+        }
+    }
+
+    fn force(&self, value: IecValue) -> Result<(), LiteralError> {
+        let mut port = self.lock();
+        port.check(&value)?;
+        port.value = value;
+        port.forced = true;
+        Ok(())
+    }
+
+    fn clear_force(&self) {
+        self.lock().forced = false;
+    }
+
+    fn is_forced(&self) -> bool {
+        self.lock().forced
+    }
 
+    fn lock(&self) -> MutexGuard<'_, DataPort> {
+        // a port holds plain data only, there is no invariant a panic could break
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A data input of a function block.
+///
+/// Cloning the input creates a new handle to the same port.
 #[derive(Clone, Debug)]
-pub struct EventInput {}
+pub struct DataInput(PortHandle);
+
+impl DataInput {
+    /// Create a new input of the given type, holding the type's initial value.
+    pub fn new(r#type: IecType) -> Self {
+        Self(PortHandle::new(r#type))
+    }
+
+    /// Create a new input of a generic type, which may be shared with other ports.
+    pub fn generic(r#type: GenericType) -> Self {
+        Self(PortHandle::generic(r#type))
+    }
+
+    /// The type of the input, the default type of its generic type while that is not resolved.
+    pub fn r#type(&self) -> IecType {
+        self.0.lock().r#type
+    }
 
+    /// The generic type of the input, while it is not resolved.
+    pub fn generic_type(&self) -> Option<AnyType> {
+        self.0.generic_type()
+    }
+
+    /// Resolve the type of a generic input. Returns if the input is of the type afterwards.
+    pub fn resolve(&self, r#type: IecType) -> bool {
+        self.0.resolve(r#type)
+    }
+
+    /// The current value of the input.
+    pub fn get(&self) -> IecValue {
+        self.0.lock().value.clone()
+    }
+
+    /// Set the value of the input, unless it is forced.
+    ///
+    /// A value which doesn't match the type of the input is logged and dropped.
+    pub fn set(&self, value: IecValue) {
+        self.0.set(value);
+    }
+
+    /// Force the input to a value, ignoring all other updates until the force is cleared.
+    ///
+    /// Fails if the value doesn't match the type of the input.
+    pub fn force(&self, value: IecValue) -> Result<(), LiteralError> {
+        self.0.force(value)
+    }
+
+    /// Clear the force, the input keeps its value until it gets updated.
+    pub fn clear_force(&self) {
+        self.0.clear_force();
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.0.is_forced()
+    }
+}
+
+/// A data output of a function block.
+///
+/// Cloning the output creates a new handle to the same port.
 #[derive(Clone, Debug)]
-pub struct EventOutput {}
+pub struct DataOutput(PortHandle);
+
+impl DataOutput {
+    /// Create a new output of the given type, holding the type's initial value.
+    pub fn new(r#type: IecType) -> Self {
+        Self(PortHandle::new(r#type))
+    }
+
+    /// Create a new output of a generic type, which may be shared with other ports.
+    pub fn generic(r#type: GenericType) -> Self {
+        Self(PortHandle::generic(r#type))
+    }
+
+    /// The type of the output, the default type of its generic type while that is not resolved.
+    pub fn r#type(&self) -> IecType {
+        self.0.lock().r#type
+    }
+
+    /// The generic type of the output, while it is not resolved.
+    pub fn generic_type(&self) -> Option<AnyType> {
+        self.0.generic_type()
+    }
+
+    /// Resolve the type of a generic output. Returns if the output is of the type afterwards.
+    pub fn resolve(&self, r#type: IecType) -> bool {
+        self.0.resolve(r#type)
+    }
+
+    /// The current value of the output.
+    pub fn get(&self) -> IecValue {
+        self.0.lock().value.clone()
+    }
+
+    /// Set the value of the output, unless it is forced.
+    ///
+    /// A value which doesn't match the type of the output is logged and dropped.
+    pub fn set(&self, value: IecValue) {
+        self.0.set(value);
+    }
+
+    /// Force the output to a value, ignoring all other updates until the force is cleared.
+    ///
+    /// Fails if the value doesn't match the type of the output.
+    pub fn force(&self, value: IecValue) -> Result<(), LiteralError> {
+        self.0.force(value)
+    }
+
+    /// Clear the force, the output keeps its value until it gets updated.
+    pub fn clear_force(&self) {
+        self.0.clear_force();
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.0.is_forced()
+    }
+}
+
+/// An event input of a function block.
+///
+/// Cloning the input creates a new handle to the same port.
+#[derive(Clone, Debug, Default)]
+pub struct EventInput(Arc<AtomicU64>);
+
+impl EventInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of events received.
+    pub fn get_count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Count a received event.
+    pub fn count(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// An event output of a function block.
+///
+/// Cloning the output creates a new handle to the same port.
+#[derive(Clone, Debug, Default)]
+pub struct EventOutput(Arc<AtomicU64>);
+
+impl EventOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of events fired.
+    pub fn get_count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Count a fired event.
+    pub fn count(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::runtime::value::{self, AnyType, IecType, IecValue};
+
+/// The interface of a function block type.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interface {
+    pub event_inputs: Vec<EventDeclaration>,
+    pub event_outputs: Vec<EventDeclaration>,
+    pub data_inputs: Vec<VariableDeclaration>,
+    pub data_outputs: Vec<VariableDeclaration>,
+}
+
+impl Interface {
+    pub fn event_input(&self, name: &str) -> Option<&EventDeclaration> {
+        self.event_inputs.iter().find(|event| event.name == name)
+    }
+
+    pub fn event_output(&self, name: &str) -> Option<&EventDeclaration> {
+        self.event_outputs.iter().find(|event| event.name == name)
+    }
+
+    pub fn data_input(&self, name: &str) -> Option<&VariableDeclaration> {
+        self.data_inputs.iter().find(|var| var.name == name)
+    }
+
+    pub fn data_output(&self, name: &str) -> Option<&VariableDeclaration> {
+        self.data_outputs.iter().find(|var| var.name == name)
+    }
+}
+
+/// An event port, along with the data ports associated with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventDeclaration {
+    pub name: String,
+    pub with: Vec<String>,
+}
+
+impl EventDeclaration {
+    pub fn new<N, W, S>(name: N, with: W) -> Self
+    where
+        N: Into<String>,
+        W: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            with: with.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A data port, or an internal variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub r#type: IecType,
+    /// The initial value, if it is not the default value of the type.
+    pub initial: Option<IecValue>,
+    /// The generic type of the port, if it is generic. The port is of the default type of the
+    /// generic type, until its type is resolved by connecting it.
+    pub generic: Option<AnyType>,
+    /// The number of elements, if the port is an array. An array port can only be written as a
+    /// whole, with an array literal.
+    pub array_size: Option<usize>,
+}
+
+impl VariableDeclaration {
+    pub fn new<N: Into<String>>(name: N, r#type: IecType) -> Self {
+        Self {
+            name: name.into(),
+            r#type,
+            initial: None,
+            generic: None,
+            array_size: None,
+        }
+    }
+
+    /// Declare a port of a generic type.
+    pub fn generic<N: Into<String>>(name: N, r#type: AnyType) -> Self {
+        Self {
+            generic: Some(r#type),
+            ..Self::new(name, r#type.default_type())
+        }
+    }
+
+    /// Declare an array port, with elements of the given type.
+    pub fn array<N: Into<String>>(name: N, r#type: IecType, size: usize) -> Self {
+        Self {
+            array_size: Some(size),
+            ..Self::new(name, r#type)
+        }
+    }
+
+    pub fn with_initial(mut self, initial: IecValue) -> Self {
+        self.initial = Some(initial);
+        self
+    }
+
+    /// The value the variable holds after initialization.
+    pub fn initial_value(&self) -> IecValue {
+        self.initial
+            .clone()
+            .unwrap_or_else(|| self.r#type.default_value())
+    }
+
+    /// The initial value of a port, converted to the type its generic type was resolved to.
+    pub fn initial_value_as(&self, r#type: IecType) -> IecValue {
+        value::convert(&self.initial_value(), r#type).unwrap_or_else(|_| r#type.default_value())
+    }
+}
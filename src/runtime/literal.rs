@@ -0,0 +1,982 @@
+//! Parsing and formatting of IEC 61131-3 literals.
+//!
+//! Values are formatted the way 4diac IDE presents them, so that parsing a formatted value with
+//! its type yields the original value again:
+//!
+//! ```
+//! use toref::runtime::literal::parse_as;
+//! use toref::runtime::value::{IecType, IecValue};
+//!
+//! let value = parse_as("T#1h30m", IecType::Time).unwrap();
+//! assert_eq!(value, IecValue::Time(90 * 60 * 1_000_000_000));
+//! assert_eq!(value.to_string(), "T#1h30m");
+//! ```
+
+use crate::runtime::value::{IecType, IecValue};
+use std::fmt::{Display, Formatter, Write};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const NANOS_PER_DAY: i128 = 24 * 60 * 60 * NANOS_PER_SECOND;
+
+/// Units of a duration, ordered from largest to smallest.
+const TIME_UNITS: [(&str, i128); 7] = [
+    ("d", NANOS_PER_DAY),
+    ("h", 60 * 60 * NANOS_PER_SECOND),
+    ("m", 60 * NANOS_PER_SECOND),
+    ("s", NANOS_PER_SECOND),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LiteralError {
+    #[error("Empty literal")]
+    Empty,
+    #[error("Unknown type prefix '{0}'")]
+    UnknownType(String),
+    #[error("Literal of type {literal} cannot be used as {expected}")]
+    TypeMismatch { literal: IecType, expected: IecType },
+    #[error("Unable to determine the type of '{0}'")]
+    Untyped(String),
+    #[error("Invalid {r#type} literal, at offset {offset}: {message}")]
+    Invalid {
+        r#type: IecType,
+        offset: usize,
+        message: String,
+    },
+    #[error("Value out of range for {0}")]
+    OutOfRange(IecType),
+    #[error("Invalid array literal '{0}'")]
+    InvalidArray(String),
+    #[error("Too many elements, the array has {0}")]
+    ArrayLength(usize),
+}
+
+impl LiteralError {
+    fn invalid<M: Into<String>>(r#type: IecType, offset: usize, message: M) -> Self {
+        Self::Invalid {
+            r#type,
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+/// Parse a literal, using the type prefix or the form of the literal to determine its type.
+///
+/// Untyped integer literals result in a `DINT`, or a `LINT` if they exceed its range. Untyped
+/// real literals result in an `LREAL`.
+pub fn parse(literal: &str) -> Result<IecValue, LiteralError> {
+    let literal = literal.trim();
+    let (prefix, body, offset) = split_prefix(literal)?;
+    if let Some(r#type) = prefix {
+        return parse_body(body, offset, r#type);
+    }
+
+    match body.chars().next() {
+        None => Err(LiteralError::Empty),
+        Some('\'') => parse_body(body, offset, IecType::String),
+        Some('"') => parse_body(body, offset, IecType::WString),
+        Some(_) if body.eq_ignore_ascii_case("TRUE") || body.eq_ignore_ascii_case("FALSE") => {
+            parse_body(body, offset, IecType::Bool)
+        }
+        Some(_) if is_real(body) => parse_body(body, offset, IecType::Lreal),
+        Some(_) => match parse_body(body, offset, IecType::Dint) {
+            Err(LiteralError::OutOfRange(_)) => parse_body(body, offset, IecType::Lint),
+            Err(LiteralError::Invalid { .. }) => Err(LiteralError::Untyped(body.to_string())),
+            result => result,
+        },
+    }
+}
+
+/// Parse a literal as a value of the expected type.
+///
+/// A type prefix is optional, but must match the expected type if present.
+pub fn parse_as(literal: &str, expected: IecType) -> Result<IecValue, LiteralError> {
+    let literal = literal.trim();
+    let (prefix, body, offset) = split_prefix(literal)?;
+    match prefix {
+        Some(r#type) if r#type != expected => Err(LiteralError::TypeMismatch {
+            literal: r#type,
+            expected,
+        }),
+        _ => parse_body(body, offset, expected),
+    }
+}
+
+/// The type given by the prefix of a literal, like `INT` for `INT#5`.
+pub fn prefix_type(literal: &str) -> Result<Option<IecType>, LiteralError> {
+    split_prefix(literal.trim()).map(|(r#type, _, _)| r#type)
+}
+
+/// Split an array literal, like `[T#1s, T#2s]`, into the literals of its elements.
+pub fn split_array(literal: &str) -> Result<Vec<&str>, LiteralError> {
+    let literal = literal.trim();
+    let body = literal
+        .strip_prefix('[')
+        .and_then(|body| body.strip_suffix(']'))
+        .ok_or_else(|| LiteralError::InvalidArray(literal.to_string()))?;
+    if body.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut elements = vec![];
+    let mut start = 0;
+    // the quote of the string literal we are in, and whether the next character is escaped
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '$') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ',') => {
+                elements.push(body[start..i].trim());
+                start = i + 1;
+            }
+            (None, _) => {}
+        }
+    }
+    if quote.is_some() {
+        return Err(LiteralError::InvalidArray(literal.to_string()));
+    }
+    elements.push(body[start..].trim());
+    Ok(elements)
+}
+
+/// Split off the type prefix (like `INT#` or `T#`) of a literal.
+///
+/// Returns the type, the remaining body and the offset of the body in the literal. A numeric
+/// prefix is a base (like `16#`), and not split off.
+fn split_prefix(literal: &str) -> Result<(Option<IecType>, &str, usize), LiteralError> {
+    let Some((prefix, body)) = literal.split_once('#') else {
+        return Ok((None, literal, 0));
+    };
+    if prefix.is_empty()
+        || prefix.starts_with(|c: char| c.is_ascii_digit())
+        || !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Ok((None, literal, 0));
+    }
+
+    let r#type = match prefix.to_ascii_uppercase().as_str() {
+        "T" => IecType::Time,
+        "D" => IecType::Date,
+        name => name
+            .parse()
+            .map_err(|_| LiteralError::UnknownType(prefix.to_string()))?,
+    };
+
+    Ok((Some(r#type), body, prefix.len() + 1))
+}
+
+fn parse_body(body: &str, offset: usize, r#type: IecType) -> Result<IecValue, LiteralError> {
+    if body.is_empty() {
+        return Err(LiteralError::Empty);
+    }
+
+    Ok(match r#type {
+        IecType::Bool => IecValue::Bool(parse_bool(body, offset)?),
+        IecType::Sint => IecValue::Sint(parse_int(body, offset, r#type)?),
+        IecType::Int => IecValue::Int(parse_int(body, offset, r#type)?),
+        IecType::Dint => IecValue::Dint(parse_int(body, offset, r#type)?),
+        IecType::Lint => IecValue::Lint(parse_int(body, offset, r#type)?),
+        IecType::Usint => IecValue::Usint(parse_int(body, offset, r#type)?),
+        IecType::Uint => IecValue::Uint(parse_int(body, offset, r#type)?),
+        IecType::Udint => IecValue::Udint(parse_int(body, offset, r#type)?),
+        IecType::Ulint => IecValue::Ulint(parse_int(body, offset, r#type)?),
+        IecType::Byte => IecValue::Byte(parse_int(body, offset, r#type)?),
+        IecType::Word => IecValue::Word(parse_int(body, offset, r#type)?),
+        IecType::Dword => IecValue::Dword(parse_int(body, offset, r#type)?),
+        IecType::Lword => IecValue::Lword(parse_int(body, offset, r#type)?),
+        IecType::Real => {
+            let value = parse_real(body, offset, r#type)?;
+            if value.is_finite() && (value as f32).is_infinite() {
+                return Err(LiteralError::OutOfRange(r#type));
+            }
+            IecValue::Real(value as f32)
+        }
+        IecType::Lreal => IecValue::Lreal(parse_real(body, offset, r#type)?),
+        IecType::String => IecValue::String(parse_string(body, offset, '\'')?),
+        IecType::WString => IecValue::WString(parse_string(body, offset, '"')?),
+        IecType::Time => IecValue::Time(parse_time(body, offset)?),
+        IecType::Date => IecValue::Date(parse_date(body, offset, r#type)?),
+        IecType::TimeOfDay => IecValue::TimeOfDay(parse_time_of_day(body, offset, r#type)? as u64),
+        IecType::DateAndTime => {
+            let (split, _) = body.match_indices('-').nth(2).ok_or_else(|| {
+                LiteralError::invalid(r#type, offset, "expected YYYY-MM-DD-hh:mm:ss")
+            })?;
+            let date = parse_date(&body[..split], offset, r#type)?;
+            let time = parse_time_of_day(&body[split + 1..], offset + split + 1, r#type)?;
+            IecValue::DateAndTime(
+                date.checked_add(time)
+                    .ok_or(LiteralError::OutOfRange(r#type))?,
+            )
+        }
+    })
+}
+
+fn is_real(body: &str) -> bool {
+    body.trim_start_matches(['+', '-'])
+        .starts_with(|c: char| c.is_ascii_digit())
+        && !body.contains('#')
+        && (body.contains('.') || body.contains(['e', 'E']))
+}
+
+fn parse_bool(body: &str, offset: usize) -> Result<bool, LiteralError> {
+    if body.eq_ignore_ascii_case("TRUE") {
+        return Ok(true);
+    }
+    if body.eq_ignore_ascii_case("FALSE") {
+        return Ok(false);
+    }
+    match parse_integer(body, offset, IecType::Bool)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(LiteralError::OutOfRange(IecType::Bool)),
+    }
+}
+
+fn parse_int<T: TryFrom<i128>>(
+    body: &str,
+    offset: usize,
+    r#type: IecType,
+) -> Result<T, LiteralError> {
+    T::try_from(parse_integer(body, offset, r#type)?).map_err(|_| LiteralError::OutOfRange(r#type))
+}
+
+/// Parse an integer literal, with an optional sign and base.
+fn parse_integer(body: &str, offset: usize, r#type: IecType) -> Result<i128, LiteralError> {
+    let (negative, digits, mut position) = match body.as_bytes()[0] {
+        b'-' => (true, &body[1..], offset + 1),
+        b'+' => (false, &body[1..], offset + 1),
+        _ => (false, body, offset),
+    };
+
+    let (radix, digits) = match digits.split_once('#') {
+        Some((base, digits)) => {
+            let radix = match base {
+                "2" => 2,
+                "8" => 8,
+                "16" => 16,
+                _ => {
+                    return Err(LiteralError::invalid(
+                        r#type,
+                        position,
+                        format!("unsupported base '{base}'"),
+                    ))
+                }
+            };
+            position += base.len() + 1;
+            (radix, digits)
+        }
+        None => (10, digits),
+    };
+
+    let mut value: i128 = 0;
+    let mut any = false;
+    for (i, c) in digits.char_indices() {
+        if c == '_' && any {
+            continue;
+        }
+        let digit = c.to_digit(radix).ok_or_else(|| {
+            LiteralError::invalid(r#type, position + i, format!("unexpected character '{c}'"))
+        })?;
+        value = value
+            .checked_mul(radix as i128)
+            .and_then(|v| v.checked_add(digit as i128))
+            .ok_or(LiteralError::OutOfRange(r#type))?;
+        any = true;
+    }
+
+    if !any {
+        return Err(LiteralError::invalid(r#type, position, "expected digits"));
+    }
+
+    Ok(if negative { -value } else { value })
+}
+
+fn parse_real(body: &str, offset: usize, r#type: IecType) -> Result<f64, LiteralError> {
+    // not valid in IEC 61131-3, but values can be formatted like this
+    for (name, value) in [
+        ("NAN", f64::NAN),
+        ("INF", f64::INFINITY),
+        ("-INF", f64::NEG_INFINITY),
+    ] {
+        if body.eq_ignore_ascii_case(name) {
+            return Ok(value);
+        }
+    }
+
+    let mut text = String::with_capacity(body.len());
+    let mut digits = false;
+    let mut exponent = false;
+    let mut point = false;
+    let mut previous = None;
+
+    for (i, c) in body.char_indices() {
+        match c {
+            '0'..='9' => digits = true,
+            '_' if matches!(previous, Some('0'..='9')) => {
+                previous = Some(c);
+                continue;
+            }
+            '+' | '-' if i == 0 || matches!(previous, Some('e' | 'E')) => {}
+            '.' if !point && !exponent && digits => point = true,
+            'e' | 'E' if !exponent && digits => exponent = true,
+            _ => {
+                return Err(LiteralError::invalid(
+                    r#type,
+                    offset + i,
+                    format!("unexpected character '{c}'"),
+                ))
+            }
+        }
+        text.push(c);
+        previous = Some(c);
+    }
+
+    if !matches!(previous, Some('0'..='9')) {
+        return Err(LiteralError::invalid(
+            r#type,
+            offset + body.len(),
+            "expected digits",
+        ));
+    }
+
+    text.parse::<f64>()
+        .map_err(|err| LiteralError::invalid(r#type, offset, err.to_string()))
+        .and_then(|value| {
+            if value.is_infinite() {
+                Err(LiteralError::OutOfRange(r#type))
+            } else {
+                Ok(value)
+            }
+        })
+}
+
+fn parse_string(body: &str, offset: usize, quote: char) -> Result<String, LiteralError> {
+    let (r#type, hex_digits) = match quote {
+        '"' => (IecType::WString, 4),
+        _ => (IecType::String, 2),
+    };
+
+    let inner = body
+        .strip_prefix(quote)
+        .and_then(|s| s.strip_suffix(quote))
+        .filter(|_| body.len() >= 2)
+        .ok_or_else(|| {
+            LiteralError::invalid(r#type, offset, format!("expected {quote}…{quote}"))
+        })?;
+    let offset = offset + 1;
+
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '$' => {
+                let (_, escape) = chars.next().ok_or_else(|| {
+                    LiteralError::invalid(r#type, offset + i, "incomplete escape")
+                })?;
+                match escape.to_ascii_uppercase() {
+                    '$' => result.push('$'),
+                    '\'' => result.push('\''),
+                    '"' => result.push('"'),
+                    'L' | 'N' => result.push('\n'),
+                    'P' => result.push('\u{0c}'),
+                    'R' => result.push('\r'),
+                    'T' => result.push('\t'),
+                    c if c.is_ascii_hexdigit() => {
+                        let mut code = c.to_digit(16).unwrap_or_default();
+                        for _ in 1..hex_digits {
+                            let digit = chars.next().and_then(|(_, c)| c.to_digit(16)).ok_or_else(
+                                || {
+                                    LiteralError::invalid(
+                                        r#type,
+                                        offset + i,
+                                        "invalid character code",
+                                    )
+                                },
+                            )?;
+                            code = code * 16 + digit;
+                        }
+                        result.push(char::from_u32(code).ok_or_else(|| {
+                            LiteralError::invalid(r#type, offset + i, "invalid character code")
+                        })?);
+                    }
+                    _ => {
+                        return Err(LiteralError::invalid(
+                            r#type,
+                            offset + i,
+                            format!("unknown escape '${escape}'"),
+                        ))
+                    }
+                }
+            }
+            c if c == quote => {
+                return Err(LiteralError::invalid(
+                    r#type,
+                    offset + i,
+                    format!("unescaped {quote}"),
+                ))
+            }
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+fn parse_time(body: &str, offset: usize) -> Result<i64, LiteralError> {
+    let r#type = IecType::Time;
+    let (negative, mut rest, mut position) = match body.strip_prefix('-') {
+        Some(rest) => (true, rest, offset + 1),
+        None => (false, body, offset),
+    };
+
+    let mut total: i128 = 0;
+    let mut any = false;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        if number.is_empty() {
+            return Err(LiteralError::invalid(r#type, position, "expected a number"));
+        }
+
+        let unit_len = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let unit = &tail[..unit_len];
+        // the longest unit matching the start of the remaining text, so "ms" is not read as "m"
+        let (unit_name, nanos) = TIME_UNITS
+            .iter()
+            .filter(|(name, _)| {
+                unit.len() >= name.len() && unit[..name.len()].eq_ignore_ascii_case(name)
+            })
+            .max_by_key(|(name, _)| name.len())
+            .ok_or_else(|| {
+                LiteralError::invalid(
+                    r#type,
+                    position + number_len,
+                    "expected a unit (d, h, m, s, ms, us, ns)",
+                )
+            })?;
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        let whole = whole.replace('_', "");
+        let fraction = fraction.replace('_', "");
+        if whole.is_empty() || fraction.contains('.') {
+            return Err(LiteralError::invalid(r#type, position, "invalid number"));
+        }
+
+        let whole: i128 = whole
+            .parse()
+            .map_err(|_| LiteralError::OutOfRange(r#type))?;
+        let mut value = whole
+            .checked_mul(*nanos)
+            .ok_or(LiteralError::OutOfRange(r#type))?;
+        if !fraction.is_empty() {
+            let digits = fraction.len().min(18) as u32;
+            let fraction: i128 = fraction[..digits as usize]
+                .parse()
+                .map_err(|_| LiteralError::invalid(r#type, position, "invalid fraction"))?;
+            value += fraction * nanos / 10i128.pow(digits);
+        }
+
+        total = total
+            .checked_add(value)
+            .ok_or(LiteralError::OutOfRange(r#type))?;
+        any = true;
+
+        let consumed = number_len + unit_name.len();
+        // allow underscores between components, like T#1h_30m
+        let next = rest[consumed..]
+            .strip_prefix('_')
+            .unwrap_or(&rest[consumed..]);
+        position += rest.len() - next.len();
+        rest = next;
+    }
+
+    if !any {
+        return Err(LiteralError::invalid(
+            r#type,
+            position,
+            "expected a duration",
+        ));
+    }
+
+    let total = if negative { -total } else { total };
+    i64::try_from(total).map_err(|_| LiteralError::OutOfRange(r#type))
+}
+
+/// Parse a date, returning nanoseconds since the epoch.
+fn parse_date(text: &str, offset: usize, r#type: IecType) -> Result<i64, LiteralError> {
+    let parts: Vec<_> = text.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(LiteralError::invalid(r#type, offset, "expected YYYY-MM-DD"));
+    };
+
+    // dates beyond four digit years don't fit into nanoseconds anyway
+    let year = parse_field(year, offset, r#type, 0, 9999)?;
+    let month_offset = offset + parts[0].len() + 1;
+    let month = parse_field(month, month_offset, r#type, 1, 12)?;
+    let day_offset = month_offset + parts[1].len() + 1;
+    let day = parse_field(day, day_offset, r#type, 1, days_in_month(year, month))?;
+
+    (days_from_civil(year, month, day) as i128 * NANOS_PER_DAY)
+        .try_into()
+        .map_err(|_| LiteralError::OutOfRange(r#type))
+}
+
+/// Parse a time of day, returning nanoseconds since midnight.
+fn parse_time_of_day(text: &str, offset: usize, r#type: IecType) -> Result<i64, LiteralError> {
+    let parts: Vec<_> = text.split(':').collect();
+    let [hours, minutes, seconds] = parts[..] else {
+        return Err(LiteralError::invalid(r#type, offset, "expected hh:mm:ss"));
+    };
+
+    let hours = parse_field(hours, offset, r#type, 0, 23)?;
+    let minutes_offset = offset + parts[0].len() + 1;
+    let minutes = parse_field(minutes, minutes_offset, r#type, 0, 59)?;
+    let seconds_offset = minutes_offset + parts[1].len() + 1;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let seconds = parse_field(seconds, seconds_offset, r#type, 0, 59)?;
+
+    let mut nanos = 0;
+    if !fraction.is_empty() {
+        let fraction_offset = seconds_offset + parts[2].len() - fraction.len();
+        if fraction.len() > 9 {
+            return Err(LiteralError::invalid(
+                r#type,
+                fraction_offset + 9,
+                "more than nanosecond precision",
+            ));
+        }
+        nanos = parse_field(fraction, fraction_offset, r#type, 0, i64::MAX)?
+            * 10i64.pow(9 - fraction.len() as u32);
+    }
+
+    Ok(((hours * 60 + minutes) * 60 + seconds) * NANOS_PER_SECOND as i64 + nanos)
+}
+
+fn parse_field(
+    text: &str,
+    offset: usize,
+    r#type: IecType,
+    min: i64,
+    max: i64,
+) -> Result<i64, LiteralError> {
+    if let Some(i) = text.find(|c: char| !c.is_ascii_digit()) {
+        return Err(LiteralError::invalid(
+            r#type,
+            offset + i,
+            format!(
+                "unexpected character '{}'",
+                &text[i..].chars().next().unwrap_or_default()
+            ),
+        ));
+    }
+    match text.parse::<i64>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        Ok(_) => Err(LiteralError::invalid(
+            r#type,
+            offset,
+            format!("'{text}' is out of range"),
+        )),
+        Err(_) => Err(LiteralError::invalid(r#type, offset, "expected digits")),
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = (month + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The date of a number of days since 1970-01-01, as year, month, day.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl Display for IecValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(true) => f.write_str("TRUE"),
+            Self::Bool(false) => f.write_str("FALSE"),
+            Self::Sint(v) => write!(f, "{v}"),
+            Self::Int(v) => write!(f, "{v}"),
+            Self::Dint(v) => write!(f, "{v}"),
+            Self::Lint(v) => write!(f, "{v}"),
+            Self::Usint(v) => write!(f, "{v}"),
+            Self::Uint(v) => write!(f, "{v}"),
+            Self::Udint(v) => write!(f, "{v}"),
+            Self::Ulint(v) => write!(f, "{v}"),
+            Self::Real(v) => f.write_str(&format!("{v:?}").to_uppercase()),
+            Self::Lreal(v) => f.write_str(&format!("{v:?}").to_uppercase()),
+            Self::String(v) => format_string(f, v, '\''),
+            Self::WString(v) => format_string(f, v, '"'),
+            Self::Time(v) => format_time(f, *v),
+            Self::Date(v) => {
+                f.write_str("D#")?;
+                format_date(f, v.div_euclid(NANOS_PER_DAY as i64))
+            }
+            Self::TimeOfDay(v) => {
+                f.write_str("TOD#")?;
+                format_time_of_day(f, *v as i64)
+            }
+            Self::DateAndTime(v) => {
+                f.write_str("DT#")?;
+                format_date(f, v.div_euclid(NANOS_PER_DAY as i64))?;
+                f.write_char('-')?;
+                format_time_of_day(f, v.rem_euclid(NANOS_PER_DAY as i64))
+            }
+            Self::Byte(v) => write!(f, "16#{v:X}"),
+            Self::Word(v) => write!(f, "16#{v:X}"),
+            Self::Dword(v) => write!(f, "16#{v:X}"),
+            Self::Lword(v) => write!(f, "16#{v:X}"),
+        }
+    }
+}
+
+fn format_string(f: &mut Formatter<'_>, value: &str, quote: char) -> std::fmt::Result {
+    f.write_char(quote)?;
+    for c in value.chars() {
+        match c {
+            '$' => f.write_str("$$")?,
+            '\n' => f.write_str("$N")?,
+            '\r' => f.write_str("$R")?,
+            '\t' => f.write_str("$T")?,
+            '\u{0c}' => f.write_str("$P")?,
+            c if c == quote => write!(f, "${c}")?,
+            c if c.is_control() && quote == '\'' && (c as u32) < 0x100 => {
+                write!(f, "${:02X}", c as u32)?
+            }
+            c if c.is_control() && (c as u32) < 0x10000 => write!(f, "${:04X}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char(quote)
+}
+
+fn format_time(f: &mut Formatter<'_>, nanos: i64) -> std::fmt::Result {
+    f.write_str("T#")?;
+    if nanos == 0 {
+        return f.write_str("0s");
+    }
+    if nanos < 0 {
+        f.write_char('-')?;
+    }
+
+    let mut rest = (nanos as i128).abs();
+    for (unit, size) in TIME_UNITS {
+        if rest >= size {
+            write!(f, "{}{unit}", rest / size)?;
+            rest %= size;
+        }
+    }
+
+    Ok(())
+}
+
+fn format_date(f: &mut Formatter<'_>, days: i64) -> std::fmt::Result {
+    let (year, month, day) = civil_from_days(days);
+    write!(f, "{year:04}-{month:02}-{day:02}")
+}
+
+fn format_time_of_day(f: &mut Formatter<'_>, nanos: i64) -> std::fmt::Result {
+    let seconds = nanos / NANOS_PER_SECOND as i64;
+    let fraction = nanos % NANOS_PER_SECOND as i64;
+    write!(
+        f,
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )?;
+    if fraction != 0 {
+        let fraction = format!("{fraction:09}");
+        write!(f, ".{}", fraction.trim_end_matches('0'))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_untyped() {
+        assert_eq!(parse("TRUE"), Ok(IecValue::Bool(true)));
+        assert_eq!(parse("false"), Ok(IecValue::Bool(false)));
+        assert_eq!(parse("42"), Ok(IecValue::Dint(42)));
+        assert_eq!(parse("-1_000"), Ok(IecValue::Dint(-1000)));
+        assert_eq!(parse("3000000000"), Ok(IecValue::Lint(3_000_000_000)));
+        assert_eq!(parse("16#FF"), Ok(IecValue::Dint(255)));
+        assert_eq!(parse("1.5"), Ok(IecValue::Lreal(1.5)));
+        assert_eq!(parse("-2.5E3"), Ok(IecValue::Lreal(-2500.0)));
+        assert_eq!(parse("'hello'"), Ok(IecValue::String("hello".into())));
+        assert_eq!(parse("\"hello\""), Ok(IecValue::WString("hello".into())));
+        assert!(matches!(parse("foo"), Err(LiteralError::Untyped(_))));
+        assert!(matches!(parse("free"), Err(LiteralError::Untyped(_))));
+        assert_eq!(parse("'a#b'"), Ok(IecValue::String("a#b".into())));
+    }
+
+    #[test]
+    fn parse_typed() {
+        assert_eq!(parse("INT#-5"), Ok(IecValue::Int(-5)));
+        assert_eq!(parse("BYTE#16#FF"), Ok(IecValue::Byte(255)));
+        assert_eq!(parse("WORD#2#1010_1010"), Ok(IecValue::Word(0xAA)));
+        assert_eq!(parse("USINT#8#17"), Ok(IecValue::Usint(15)));
+        assert_eq!(parse("REAL#1.5"), Ok(IecValue::Real(1.5)));
+        assert_eq!(parse("LREAL#7"), Ok(IecValue::Lreal(7.0)));
+        assert_eq!(parse("BOOL#1"), Ok(IecValue::Bool(true)));
+        assert_eq!(parse("T#250ms"), Ok(IecValue::Time(250_000_000)));
+        assert_eq!(parse("TIME#1.5s"), Ok(IecValue::Time(1_500_000_000)));
+        assert_eq!(
+            parse("t#-1d2h_3m4s5ms6us7ns"),
+            Ok(IecValue::Time(
+                -((((24 + 2) * 60 + 3) * 60 + 4) * 1_000_000_000 + 5_006_007)
+            ))
+        );
+        assert_eq!(
+            parse("D#1970-01-02"),
+            Ok(IecValue::Date(NANOS_PER_DAY as i64))
+        );
+        assert_eq!(
+            parse("TOD#12:00:00.5"),
+            Ok(IecValue::TimeOfDay(43_200_500_000_000))
+        );
+        assert_eq!(
+            parse("DT#2022-01-01-12:00:00"),
+            Ok(IecValue::DateAndTime(1_641_038_400_000_000_000))
+        );
+        assert_eq!(
+            parse("STRING#'it$'s $24 $N'"),
+            Ok(IecValue::String("it's $ \n".into()))
+        );
+        assert_eq!(
+            parse("WSTRING#\"$00E4\""),
+            Ok(IecValue::WString("ä".into()))
+        );
+    }
+
+    #[test]
+    fn parse_expected() {
+        assert_eq!(parse_as("5", IecType::Real), Ok(IecValue::Real(5.0)));
+        assert_eq!(parse_as("16#FF", IecType::Byte), Ok(IecValue::Byte(255)));
+        assert_eq!(parse_as("1", IecType::Bool), Ok(IecValue::Bool(true)));
+        assert_eq!(
+            parse_as("T#1s", IecType::Lint),
+            Err(LiteralError::TypeMismatch {
+                literal: IecType::Time,
+                expected: IecType::Lint
+            })
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse(""), Err(LiteralError::Empty));
+        assert_eq!(parse("T#"), Err(LiteralError::Empty));
+        assert_eq!(parse("FOO#1"), Err(LiteralError::UnknownType("FOO".into())));
+        assert_eq!(
+            parse("SINT#128"),
+            Err(LiteralError::OutOfRange(IecType::Sint))
+        );
+        assert_eq!(
+            parse("UINT#-1"),
+            Err(LiteralError::OutOfRange(IecType::Uint))
+        );
+        assert_eq!(
+            parse("REAL#1E39"),
+            Err(LiteralError::OutOfRange(IecType::Real))
+        );
+        assert_eq!(
+            parse("D#900000000000000000-03-01"),
+            Err(LiteralError::invalid(
+                IecType::Date,
+                2,
+                "'900000000000000000' is out of range"
+            ))
+        );
+        assert_eq!(
+            parse("D#3000-01-01"),
+            Err(LiteralError::OutOfRange(IecType::Date))
+        );
+        assert_eq!(
+            parse("INT#12x4"),
+            Err(LiteralError::invalid(
+                IecType::Int,
+                6,
+                "unexpected character 'x'"
+            ))
+        );
+        assert_eq!(
+            parse("T#5x"),
+            Err(LiteralError::invalid(
+                IecType::Time,
+                3,
+                "expected a unit (d, h, m, s, ms, us, ns)"
+            ))
+        );
+        assert_eq!(
+            parse("D#2022-02-30"),
+            Err(LiteralError::invalid(
+                IecType::Date,
+                10,
+                "'30' is out of range"
+            ))
+        );
+        assert_eq!(
+            parse("DT#2022-01-01-25:00:00"),
+            Err(LiteralError::invalid(
+                IecType::DateAndTime,
+                14,
+                "'25' is out of range"
+            ))
+        );
+        assert_eq!(
+            parse("'abc"),
+            Err(LiteralError::invalid(IecType::String, 0, "expected '…'"))
+        );
+        assert_eq!(
+            parse("'a$Qb'"),
+            Err(LiteralError::invalid(
+                IecType::String,
+                2,
+                "unknown escape '$Q'"
+            ))
+        );
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(IecValue::Bool(true).to_string(), "TRUE");
+        assert_eq!(IecValue::Int(-5).to_string(), "-5");
+        assert_eq!(IecValue::Real(1.0).to_string(), "1.0");
+        assert_eq!(IecValue::Lreal(1e30).to_string(), "1E30");
+        assert_eq!(IecValue::Byte(255).to_string(), "16#FF");
+        assert_eq!(IecValue::Time(0).to_string(), "T#0s");
+        assert_eq!(IecValue::Time(250_000_000).to_string(), "T#250ms");
+        assert_eq!(IecValue::Time(-90_000_000_000).to_string(), "T#-1m30s");
+        assert_eq!(IecValue::Date(0).to_string(), "D#1970-01-01");
+        assert_eq!(
+            IecValue::TimeOfDay(43_200_500_000_000).to_string(),
+            "TOD#12:00:00.5"
+        );
+        assert_eq!(
+            IecValue::DateAndTime(1_641_038_400_000_000_000).to_string(),
+            "DT#2022-01-01-12:00:00"
+        );
+        assert_eq!(
+            IecValue::String("it's $5\n".into()).to_string(),
+            "'it$'s $$5$N'"
+        );
+        assert_eq!(IecValue::WString("\"".into()).to_string(), "\"$\"\"");
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = [
+            IecValue::Bool(false),
+            IecValue::Sint(i8::MIN),
+            IecValue::Int(i16::MAX),
+            IecValue::Dint(i32::MIN),
+            IecValue::Lint(i64::MIN),
+            IecValue::Usint(u8::MAX),
+            IecValue::Uint(u16::MAX),
+            IecValue::Udint(u32::MAX),
+            IecValue::Ulint(u64::MAX),
+            IecValue::Real(-0.1),
+            IecValue::Real(f32::MAX),
+            IecValue::Real(f32::INFINITY),
+            IecValue::Lreal(f64::NEG_INFINITY),
+            IecValue::Lreal(f64::MIN_POSITIVE),
+            IecValue::Lreal(12345.678),
+            IecValue::String("$'\"\t\u{1}".into()),
+            IecValue::WString("ä'\"\u{1}".into()),
+            IecValue::Time(i64::MIN + 1),
+            IecValue::Time(i64::MAX),
+            IecValue::Time(1),
+            IecValue::Date(-NANOS_PER_DAY as i64 * 1000),
+            IecValue::Date(NANOS_PER_DAY as i64 * 20000),
+            IecValue::TimeOfDay(NANOS_PER_DAY as u64 - 1),
+            IecValue::DateAndTime(-1),
+            IecValue::DateAndTime(1_641_038_400_123_456_789),
+            IecValue::Byte(0),
+            IecValue::Word(0xBEEF),
+            IecValue::Dword(u32::MAX),
+            IecValue::Lword(u64::MAX),
+        ];
+
+        for value in values {
+            let literal = value.to_string();
+            assert_eq!(parse_as(&literal, value.r#type()), Ok(value), "{literal}");
+        }
+
+        assert!(matches!(
+            parse_as(&IecValue::Real(f32::NAN).to_string(), IecType::Real),
+            Ok(IecValue::Real(v)) if v.is_nan()
+        ));
+        assert!(matches!(
+            parse_as(&IecValue::Lreal(f64::NAN).to_string(), IecType::Lreal),
+            Ok(IecValue::Lreal(v)) if v.is_nan()
+        ));
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(split_array("[]"), Ok(vec![]));
+        assert_eq!(
+            split_array(" [T#1s, T#2s,T#3s] "),
+            Ok(vec!["T#1s", "T#2s", "T#3s"])
+        );
+        assert_eq!(
+            split_array("['a,b', 'c$'d', \"e\"]"),
+            Ok(vec!["'a,b'", "'c$'d'", "\"e\""])
+        );
+        assert_eq!(
+            split_array("T#1s"),
+            Err(LiteralError::InvalidArray("T#1s".into()))
+        );
+        assert_eq!(
+            split_array("['a]"),
+            Err(LiteralError::InvalidArray("['a]".into()))
+        );
+    }
+}
@@ -1,8 +1,14 @@
+pub mod basic;
+pub mod composite;
 pub mod container;
 pub mod emb_res;
 pub mod factory;
 pub mod fb;
+pub mod interface;
+pub mod literal;
 pub mod root;
+pub mod timer;
+pub mod value;
 
 use crate::protocol::server::{Action, Data, Error};
 use crate::protocol::RequestTarget;
@@ -52,8 +58,8 @@ pub struct Requests {
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Destination(Vec<String>);
 
-impl Destination {
-    pub fn from_str(destination: &str) -> Destination {
+impl From<&str> for Destination {
+    fn from(destination: &str) -> Destination {
         if destination.is_empty() {
             return Destination(vec![]);
         }
@@ -84,7 +90,7 @@ impl Requests {
     ) -> Result<Option<Data>, Error> {
         let (tx, rx) = oneshot::channel();
 
-        let destination = Destination::from_str(&destination);
+        let destination = Destination::from(destination.as_str());
 
         let request = RequestHandle {
             request: Request {
@@ -95,7 +101,7 @@ impl Requests {
             tx,
         };
 
-        if let Err(_) = self.tx.send(request).await {
+        if self.tx.send(request).await.is_err() {
             return Err(Error::NotReady);
         }
 
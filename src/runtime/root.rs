@@ -1,6 +1,9 @@
 use crate::runtime::emb_res::EmbeddedResource;
 use crate::runtime::factory::{CreationError, FunctionBlockFactory};
 use crate::runtime::fb::FunctionBlock;
+use crate::runtime::interface::Interface;
+use crate::typelib::fbt::FbType;
+use crate::typelib::LoadError;
 
 pub struct RootFactory<F>
 where
@@ -28,4 +31,17 @@ where
             _ => Err(CreationError::UnknownType),
         }
     }
+
+    /// The types of the function blocks the resources can create.
+    fn types(&self) -> Vec<String> {
+        self.factory.types()
+    }
+
+    fn interface(&self, r#type: &str) -> Option<Interface> {
+        self.factory.interface(r#type)
+    }
+
+    fn register(&self, r#type: &FbType) -> Result<(), LoadError> {
+        self.factory.register(r#type)
+    }
 }
@@ -0,0 +1,186 @@
+//! Time sources of resources, and the timers of function blocks.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// The time source of a resource.
+///
+/// Times are relative to the start of the clock.
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> Duration;
+
+    /// Wait until the clock has reached the deadline.
+    async fn sleep_until(&self, deadline: Duration);
+}
+
+/// A clock following the time of the tokio runtime.
+#[derive(Clone, Debug)]
+pub struct TokioClock {
+    epoch: Instant,
+}
+
+impl TokioClock {
+    /// Create a new clock, starting now.
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Clock for TokioClock {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    async fn sleep_until(&self, deadline: Duration) {
+        tokio::time::sleep_until(self.epoch + deadline).await;
+    }
+}
+
+/// A clock which only moves when it gets advanced, for testing.
+///
+/// Cloning the clock creates a new handle to the same clock.
+#[derive(Clone, Debug)]
+pub struct VirtualClock(Arc<watch::Sender<Duration>>);
+
+impl VirtualClock {
+    /// Create a new clock, starting at zero.
+    pub fn new() -> Self {
+        Self(Arc::new(watch::channel(Duration::ZERO).0))
+    }
+
+    /// Move the clock forward, waking up everyone waiting for a deadline which was reached.
+    pub fn advance(&self, duration: Duration) {
+        self.0.send_modify(|now| *now += duration);
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        *self.0.borrow()
+    }
+
+    async fn sleep_until(&self, deadline: Duration) {
+        let mut rx = self.0.subscribe();
+        while *rx.borrow_and_update() < deadline {
+            // the sender is owned by the clock, and so outlives this call
+            let _ = rx.changed().await;
+        }
+    }
+}
+
+/// The timers of the function blocks of a container, at most one per block.
+#[derive(Debug, Default)]
+pub struct TimerService {
+    timers: HashMap<String, Duration>,
+}
+
+impl TimerService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timer of a block, replacing a timer which was set before.
+    pub fn set(&mut self, block: &str, deadline: Duration) {
+        self.timers.insert(block.to_string(), deadline);
+    }
+
+    /// Cancel the timer of a block, if it was set.
+    pub fn cancel(&mut self, block: &str) {
+        self.timers.remove(block);
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    /// The earliest deadline of all timers.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.timers.values().min().copied()
+    }
+
+    /// Remove the timers which expired by `now`, returning them along with their deadlines.
+    ///
+    /// The timers are ordered by their deadlines, and then by the names of their blocks.
+    pub fn take_expired(&mut self, now: Duration) -> Vec<(Duration, String)> {
+        let mut expired: Vec<_> = self
+            .timers
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(name, deadline)| (*deadline, name.clone()))
+            .collect();
+        expired.sort_unstable();
+        for (_, name) in &expired {
+            self.timers.remove(name);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn expired_timers() {
+        let mut timers = TimerService::new();
+        timers.set("b", ms(20));
+        timers.set("a", ms(20));
+        timers.set("c", ms(10));
+        timers.set("d", ms(30));
+        timers.cancel("d");
+        assert_eq!(timers.next_deadline(), Some(ms(10)));
+
+        assert_eq!(
+            timers.take_expired(ms(25)),
+            vec![
+                (ms(10), "c".into()),
+                (ms(20), "a".into()),
+                (ms(20), "b".into())
+            ]
+        );
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[tokio::test]
+    async fn virtual_clock() {
+        let clock = VirtualClock::new();
+        let sleeper = {
+            let clock = clock.clone();
+            tokio::spawn(async move { clock.sleep_until(ms(100)).await })
+        };
+
+        clock.advance(ms(60));
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(ms(60));
+        sleeper.await.unwrap();
+        assert_eq!(clock.now(), ms(120));
+    }
+}
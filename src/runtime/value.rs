@@ -0,0 +1,532 @@
+use crate::runtime::literal;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const NANOS_PER_MILLISECOND: i128 = 1_000_000;
+const NANOS_PER_DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// The elementary data types of IEC 61131-3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IecType {
+    Bool,
+    Sint,
+    Int,
+    Dint,
+    Lint,
+    Usint,
+    Uint,
+    Udint,
+    Ulint,
+    Real,
+    Lreal,
+    String,
+    WString,
+    Time,
+    Date,
+    TimeOfDay,
+    DateAndTime,
+    Byte,
+    Word,
+    Dword,
+    Lword,
+}
+
+impl IecType {
+    pub const ALL: [IecType; 21] = [
+        Self::Bool,
+        Self::Sint,
+        Self::Int,
+        Self::Dint,
+        Self::Lint,
+        Self::Usint,
+        Self::Uint,
+        Self::Udint,
+        Self::Ulint,
+        Self::Real,
+        Self::Lreal,
+        Self::String,
+        Self::WString,
+        Self::Time,
+        Self::Date,
+        Self::TimeOfDay,
+        Self::DateAndTime,
+        Self::Byte,
+        Self::Word,
+        Self::Dword,
+        Self::Lword,
+    ];
+
+    /// The name of the type, as used in type declarations.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bool => "BOOL",
+            Self::Sint => "SINT",
+            Self::Int => "INT",
+            Self::Dint => "DINT",
+            Self::Lint => "LINT",
+            Self::Usint => "USINT",
+            Self::Uint => "UINT",
+            Self::Udint => "UDINT",
+            Self::Ulint => "ULINT",
+            Self::Real => "REAL",
+            Self::Lreal => "LREAL",
+            Self::String => "STRING",
+            Self::WString => "WSTRING",
+            Self::Time => "TIME",
+            Self::Date => "DATE",
+            Self::TimeOfDay => "TIME_OF_DAY",
+            Self::DateAndTime => "DATE_AND_TIME",
+            Self::Byte => "BYTE",
+            Self::Word => "WORD",
+            Self::Dword => "DWORD",
+            Self::Lword => "LWORD",
+        }
+    }
+
+    /// The initial value of a variable of this type.
+    pub fn default_value(&self) -> IecValue {
+        match self {
+            Self::Bool => IecValue::Bool(false),
+            Self::Sint => IecValue::Sint(0),
+            Self::Int => IecValue::Int(0),
+            Self::Dint => IecValue::Dint(0),
+            Self::Lint => IecValue::Lint(0),
+            Self::Usint => IecValue::Usint(0),
+            Self::Uint => IecValue::Uint(0),
+            Self::Udint => IecValue::Udint(0),
+            Self::Ulint => IecValue::Ulint(0),
+            Self::Real => IecValue::Real(0.0),
+            Self::Lreal => IecValue::Lreal(0.0),
+            Self::String => IecValue::String(String::new()),
+            Self::WString => IecValue::WString(String::new()),
+            Self::Time => IecValue::Time(0),
+            Self::Date => IecValue::Date(0),
+            Self::TimeOfDay => IecValue::TimeOfDay(0),
+            Self::DateAndTime => IecValue::DateAndTime(0),
+            Self::Byte => IecValue::Byte(0),
+            Self::Word => IecValue::Word(0),
+            Self::Dword => IecValue::Dword(0),
+            Self::Lword => IecValue::Lword(0),
+        }
+    }
+}
+
+impl Display for IecType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown type: {0}")]
+pub struct UnknownType(pub String);
+
+/// The generic data types of IEC 61131-3, each standing for a set of elementary types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnyType {
+    Any,
+    AnyElementary,
+    /// Numbers and durations.
+    AnyMagnitude,
+    AnyNum,
+    AnyReal,
+    AnyInt,
+    AnySigned,
+    AnyUnsigned,
+    AnyDuration,
+    /// Bit strings, including `BOOL`.
+    AnyBit,
+    AnyString,
+    AnyDate,
+}
+
+impl AnyType {
+    pub const ALL: [AnyType; 12] = [
+        Self::Any,
+        Self::AnyElementary,
+        Self::AnyMagnitude,
+        Self::AnyNum,
+        Self::AnyReal,
+        Self::AnyInt,
+        Self::AnySigned,
+        Self::AnyUnsigned,
+        Self::AnyDuration,
+        Self::AnyBit,
+        Self::AnyString,
+        Self::AnyDate,
+    ];
+
+    /// The name of the type, as used in type declarations.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "ANY",
+            Self::AnyElementary => "ANY_ELEMENTARY",
+            Self::AnyMagnitude => "ANY_MAGNITUDE",
+            Self::AnyNum => "ANY_NUM",
+            Self::AnyReal => "ANY_REAL",
+            Self::AnyInt => "ANY_INT",
+            Self::AnySigned => "ANY_SIGNED",
+            Self::AnyUnsigned => "ANY_UNSIGNED",
+            Self::AnyDuration => "ANY_DURATION",
+            Self::AnyBit => "ANY_BIT",
+            Self::AnyString => "ANY_STRING",
+            Self::AnyDate => "ANY_DATE",
+        }
+    }
+
+    /// If the elementary type belongs to this generic type.
+    pub fn accepts(&self, r#type: IecType) -> bool {
+        use IecType::*;
+        match self {
+            Self::Any | Self::AnyElementary => true,
+            Self::AnyMagnitude => Self::AnyNum.accepts(r#type) || r#type == Time,
+            Self::AnyNum => Self::AnyReal.accepts(r#type) || Self::AnyInt.accepts(r#type),
+            Self::AnyReal => matches!(r#type, Real | Lreal),
+            Self::AnyInt => Self::AnySigned.accepts(r#type) || Self::AnyUnsigned.accepts(r#type),
+            Self::AnySigned => matches!(r#type, Sint | Int | Dint | Lint),
+            Self::AnyUnsigned => matches!(r#type, Usint | Uint | Udint | Ulint),
+            Self::AnyDuration => r#type == Time,
+            Self::AnyBit => matches!(r#type, Bool | Byte | Word | Dword | Lword),
+            Self::AnyString => matches!(r#type, String | WString),
+            Self::AnyDate => matches!(r#type, Date | TimeOfDay | DateAndTime),
+        }
+    }
+
+    /// The type of a port of this type, until it gets resolved to one of its elementary types.
+    pub fn default_type(&self) -> IecType {
+        match self {
+            Self::Any | Self::AnyElementary | Self::AnyMagnitude | Self::AnyNum | Self::AnyReal => {
+                IecType::Lreal
+            }
+            Self::AnyInt | Self::AnySigned => IecType::Int,
+            Self::AnyUnsigned => IecType::Uint,
+            Self::AnyDuration => IecType::Time,
+            Self::AnyBit => IecType::Bool,
+            Self::AnyString => IecType::String,
+            Self::AnyDate => IecType::DateAndTime,
+        }
+    }
+}
+
+impl Display for AnyType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AnyType {
+    type Err = UnknownType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|r#type| r#type.name() == s)
+            .ok_or_else(|| UnknownType(s.to_string()))
+    }
+}
+
+impl FromStr for IecType {
+    type Err = UnknownType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "BOOL" => Self::Bool,
+            "SINT" => Self::Sint,
+            "INT" => Self::Int,
+            "DINT" => Self::Dint,
+            "LINT" => Self::Lint,
+            "USINT" => Self::Usint,
+            "UINT" => Self::Uint,
+            "UDINT" => Self::Udint,
+            "ULINT" => Self::Ulint,
+            "REAL" => Self::Real,
+            "LREAL" => Self::Lreal,
+            "STRING" => Self::String,
+            "WSTRING" => Self::WString,
+            "TIME" => Self::Time,
+            "DATE" => Self::Date,
+            "TIME_OF_DAY" | "TOD" => Self::TimeOfDay,
+            "DATE_AND_TIME" | "DT" => Self::DateAndTime,
+            "BYTE" => Self::Byte,
+            "WORD" => Self::Word,
+            "DWORD" => Self::Dword,
+            "LWORD" => Self::Lword,
+            _ => return Err(UnknownType(s.to_string())),
+        })
+    }
+}
+
+/// A value of one of the elementary data types.
+///
+/// Time related values are stored in nanoseconds: `TIME` as a duration, `DATE` and
+/// `DATE_AND_TIME` since the Unix epoch, and `TIME_OF_DAY` since midnight.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IecValue {
+    Bool(bool),
+    Sint(i8),
+    Int(i16),
+    Dint(i32),
+    Lint(i64),
+    Usint(u8),
+    Uint(u16),
+    Udint(u32),
+    Ulint(u64),
+    Real(f32),
+    Lreal(f64),
+    String(String),
+    WString(String),
+    Time(i64),
+    Date(i64),
+    TimeOfDay(u64),
+    DateAndTime(i64),
+    Byte(u8),
+    Word(u16),
+    Dword(u32),
+    Lword(u64),
+}
+
+impl IecValue {
+    /// The type of the value.
+    pub fn r#type(&self) -> IecType {
+        match self {
+            Self::Bool(_) => IecType::Bool,
+            Self::Sint(_) => IecType::Sint,
+            Self::Int(_) => IecType::Int,
+            Self::Dint(_) => IecType::Dint,
+            Self::Lint(_) => IecType::Lint,
+            Self::Usint(_) => IecType::Usint,
+            Self::Uint(_) => IecType::Uint,
+            Self::Udint(_) => IecType::Udint,
+            Self::Ulint(_) => IecType::Ulint,
+            Self::Real(_) => IecType::Real,
+            Self::Lreal(_) => IecType::Lreal,
+            Self::String(_) => IecType::String,
+            Self::WString(_) => IecType::WString,
+            Self::Time(_) => IecType::Time,
+            Self::Date(_) => IecType::Date,
+            Self::TimeOfDay(_) => IecType::TimeOfDay,
+            Self::DateAndTime(_) => IecType::DateAndTime,
+            Self::Byte(_) => IecType::Byte,
+            Self::Word(_) => IecType::Word,
+            Self::Dword(_) => IecType::Dword,
+            Self::Lword(_) => IecType::Lword,
+        }
+    }
+}
+
+/// A value can't be converted to another type.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{0}")]
+pub struct ConversionError(pub String);
+
+/// Signedness and size of an integer type.
+pub(crate) fn int_info(r#type: IecType) -> Option<(bool, u32)> {
+    Some(match r#type {
+        IecType::Sint => (true, 8),
+        IecType::Int => (true, 16),
+        IecType::Dint => (true, 32),
+        IecType::Lint => (true, 64),
+        IecType::Usint => (false, 8),
+        IecType::Uint => (false, 16),
+        IecType::Udint => (false, 32),
+        IecType::Ulint => (false, 64),
+        _ => return None,
+    })
+}
+
+/// Size of a bit string type, `BOOL` being the smallest one.
+pub(crate) fn bit_size(r#type: IecType) -> Option<u32> {
+    Some(match r#type {
+        IecType::Bool => 1,
+        IecType::Byte => 8,
+        IecType::Word => 16,
+        IecType::Dword => 32,
+        IecType::Lword => 64,
+        _ => return None,
+    })
+}
+
+/// If the type is `REAL` or `LREAL`.
+pub fn is_real(r#type: IecType) -> bool {
+    matches!(r#type, IecType::Real | IecType::Lreal)
+}
+
+/// If the type is one of the integer types.
+pub fn is_integer(r#type: IecType) -> bool {
+    int_info(r#type).is_some()
+}
+
+/// If the type is one of the integer or real types.
+pub fn is_numeric(r#type: IecType) -> bool {
+    is_integer(r#type) || is_real(r#type)
+}
+
+/// If the type is one of the bit string types, including `BOOL`.
+pub fn is_bit_string(r#type: IecType) -> bool {
+    bit_size(r#type).is_some()
+}
+
+/// The value of an integer, bit string or `BOOL`.
+pub(crate) fn int_value(value: &IecValue) -> Option<i128> {
+    Some(match value {
+        IecValue::Bool(v) => *v as i128,
+        IecValue::Sint(v) => *v as i128,
+        IecValue::Int(v) => *v as i128,
+        IecValue::Dint(v) => *v as i128,
+        IecValue::Lint(v) => *v as i128,
+        IecValue::Usint(v) => *v as i128,
+        IecValue::Uint(v) => *v as i128,
+        IecValue::Udint(v) => *v as i128,
+        IecValue::Ulint(v) => *v as i128,
+        IecValue::Byte(v) => *v as i128,
+        IecValue::Word(v) => *v as i128,
+        IecValue::Dword(v) => *v as i128,
+        IecValue::Lword(v) => *v as i128,
+        _ => return None,
+    })
+}
+
+pub(crate) fn real_value(value: &IecValue) -> Option<f64> {
+    match value {
+        IecValue::Real(v) => Some(*v as f64),
+        IecValue::Lreal(v) => Some(*v),
+        _ => int_value(value).map(|v| v as f64),
+    }
+}
+
+/// Create a value of a numeric or bit string type, wrapping around if it is out of range.
+pub(crate) fn from_int(value: i128, r#type: IecType) -> IecValue {
+    match r#type {
+        IecType::Bool => IecValue::Bool(value != 0),
+        IecType::Sint => IecValue::Sint(value as i8),
+        IecType::Int => IecValue::Int(value as i16),
+        IecType::Dint => IecValue::Dint(value as i32),
+        IecType::Lint => IecValue::Lint(value as i64),
+        IecType::Usint => IecValue::Usint(value as u8),
+        IecType::Uint => IecValue::Uint(value as u16),
+        IecType::Udint => IecValue::Udint(value as u32),
+        IecType::Ulint => IecValue::Ulint(value as u64),
+        IecType::Byte => IecValue::Byte(value as u8),
+        IecType::Word => IecValue::Word(value as u16),
+        IecType::Dword => IecValue::Dword(value as u32),
+        IecType::Lword => IecValue::Lword(value as u64),
+        IecType::Real => IecValue::Real(value as f32),
+        IecType::Lreal => IecValue::Lreal(value as f64),
+        IecType::Time => IecValue::Time(value as i64),
+        _ => unreachable!("{type} is not a numeric type"),
+    }
+}
+
+/// Create a value of a numeric type, rounding to the nearest integer for integer types.
+pub(crate) fn from_real(value: f64, r#type: IecType) -> IecValue {
+    match r#type {
+        IecType::Real => IecValue::Real(value as f32),
+        IecType::Lreal => IecValue::Lreal(value),
+        IecType::Bool => IecValue::Bool(value != 0.0),
+        _ => from_int(value.round() as i128, r#type),
+    }
+}
+
+/// Convert a value to another type, as done by the `*_TO_*` functions.
+///
+/// Durations are converted from and to numbers in milliseconds. Conversions to strings use the
+/// literal representation of the value, conversions from strings parse a literal.
+pub fn convert(value: &IecValue, r#type: IecType) -> Result<IecValue, ConversionError> {
+    let from = value.r#type();
+    if from == r#type {
+        return Ok(value.clone());
+    }
+
+    let unsupported = || ConversionError(format!("Cannot convert {from} to {type}"));
+
+    Ok(match (value, r#type) {
+        (IecValue::String(s) | IecValue::WString(s), IecType::String) => {
+            IecValue::String(s.clone())
+        }
+        (IecValue::String(s) | IecValue::WString(s), IecType::WString) => {
+            IecValue::WString(s.clone())
+        }
+        (IecValue::String(s) | IecValue::WString(s), r#type) => literal::parse_as(s, r#type)
+            .map_err(|err| ConversionError(format!("Cannot convert '{s}' to {type}: {err}")))?,
+        (value, IecType::String) => IecValue::String(value.to_string()),
+        (value, IecType::WString) => IecValue::WString(value.to_string()),
+        (IecValue::Time(nanos), r#type) if is_numeric(r#type) || is_bit_string(r#type) => {
+            if is_real(r#type) {
+                from_real(*nanos as f64 / NANOS_PER_MILLISECOND as f64, r#type)
+            } else {
+                from_int(*nanos as i128 / NANOS_PER_MILLISECOND, r#type)
+            }
+        }
+        (value, IecType::Time) => match value {
+            IecValue::Real(_) | IecValue::Lreal(_) => IecValue::Time(
+                (real_value(value).ok_or_else(unsupported)? * NANOS_PER_MILLISECOND as f64).round()
+                    as i64,
+            ),
+            IecValue::Bool(_) => return Err(unsupported()),
+            value => IecValue::Time(
+                (int_value(value).ok_or_else(unsupported)? * NANOS_PER_MILLISECOND) as i64,
+            ),
+        },
+        (IecValue::DateAndTime(nanos), IecType::Date) => {
+            IecValue::Date(nanos - nanos.rem_euclid(NANOS_PER_DAY))
+        }
+        (IecValue::DateAndTime(nanos), IecType::TimeOfDay) => {
+            IecValue::TimeOfDay(nanos.rem_euclid(NANOS_PER_DAY) as u64)
+        }
+        (IecValue::Real(_) | IecValue::Lreal(_), r#type)
+            if is_numeric(r#type) || is_bit_string(r#type) =>
+        {
+            from_real(real_value(value).ok_or_else(unsupported)?, r#type)
+        }
+        (value, r#type) if is_numeric(r#type) || is_bit_string(r#type) => {
+            from_int(int_value(value).ok_or_else(unsupported)?, r#type)
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+/// If [`convert`] supports converting values of a type to another type.
+pub fn is_convertible(from: IecType, to: IecType) -> bool {
+    let number = |r#type| is_numeric(r#type) || is_bit_string(r#type);
+    from == to
+        || match (from, to) {
+            (IecType::String | IecType::WString, _) | (_, IecType::String | IecType::WString) => {
+                true
+            }
+            (IecType::Time, to) => number(to),
+            (from, IecType::Time) => number(from) && from != IecType::Bool,
+            (IecType::DateAndTime, IecType::Date | IecType::TimeOfDay) => true,
+            (from, to) => number(from) && number(to),
+        }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn type_names() {
+        for r#type in IecType::ALL {
+            assert_eq!(r#type.name().parse::<IecType>(), Ok(r#type));
+            assert_eq!(r#type.default_value().r#type(), r#type);
+        }
+        assert_eq!("TOD".parse::<IecType>(), Ok(IecType::TimeOfDay));
+        assert_eq!("DT".parse::<IecType>(), Ok(IecType::DateAndTime));
+        assert!("FOO".parse::<IecType>().is_err());
+    }
+
+    #[test]
+    fn generic_types() {
+        for r#type in AnyType::ALL {
+            assert_eq!(r#type.name().parse::<AnyType>(), Ok(r#type));
+            assert!(r#type.accepts(r#type.default_type()));
+        }
+        assert!(AnyType::AnyMagnitude.accepts(IecType::Time));
+        assert!(AnyType::AnyNum.accepts(IecType::Usint));
+        assert!(!AnyType::AnyNum.accepts(IecType::Time));
+        assert!(!AnyType::AnySigned.accepts(IecType::Uint));
+        assert!(AnyType::AnyBit.accepts(IecType::Bool));
+        assert!(!AnyType::AnyBit.accepts(IecType::Int));
+        assert!("INT".parse::<AnyType>().is_err());
+    }
+}
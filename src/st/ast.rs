@@ -0,0 +1,164 @@
+//! The syntax tree of Structured Text, with variables and functions already resolved.
+
+use super::functions::Function;
+use crate::runtime::interface::VariableDeclaration;
+use crate::runtime::value::IecValue;
+
+/// A position in the source, for reporting errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A variable, either one of the function block or a temporary variable of the algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variable {
+    /// The index of the variable in the layout of the function block.
+    Block(usize),
+    /// The index of a temporary variable, declared in a `VAR_TEMP` block.
+    Temp(usize),
+}
+
+/// The body of an algorithm.
+#[derive(Clone, Debug, Default)]
+pub struct Body {
+    /// The temporary variables, initialized on each execution.
+    pub temps: Vec<VariableDeclaration>,
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Statement {
+    Assign {
+        target: Variable,
+        value: Expr,
+    },
+    If {
+        branches: Vec<(Expr, Vec<Statement>)>,
+        otherwise: Vec<Statement>,
+    },
+    Case {
+        selector: Expr,
+        branches: Vec<(Vec<CaseLabel>, Vec<Statement>)>,
+        otherwise: Vec<Statement>,
+    },
+    For {
+        variable: Variable,
+        from: Expr,
+        to: Expr,
+        by: Option<Expr>,
+        body: Vec<Statement>,
+    },
+    While {
+        condition: Expr,
+        body: Vec<Statement>,
+    },
+    Repeat {
+        body: Vec<Statement>,
+        until: Expr,
+    },
+    Exit,
+    Return,
+}
+
+#[derive(Clone, Debug)]
+pub enum CaseLabel {
+    Value(Expr),
+    Range(Expr, Expr),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Expt,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::Ne | Self::Lt | Self::Gt | Self::Le | Self::Ge
+        )
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Or => "OR",
+            Self::Xor => "XOR",
+            Self::And => "AND",
+            Self::Eq => "=",
+            Self::Ne => "<>",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Le => "<=",
+            Self::Ge => ">=",
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "MOD",
+            Self::Expt => "**",
+        }
+    }
+}
+
+/// An expression, located at the start of the expression or at its operator.
+#[derive(Clone, Debug)]
+pub struct Expr {
+    pub position: Position,
+    pub kind: ExprKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExprKind {
+    Literal(Literal),
+    Variable(Variable),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function call, with the arguments ordered by the inputs of the function.
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Debug)]
+pub enum Literal {
+    /// A literal which has a type of its own, like `TRUE`, `'text'` or `INT#5`.
+    Typed(IecValue),
+    /// A number without type prefix, its type depends on where it is used.
+    Untyped {
+        source: String,
+        /// The value when there is nothing to infer the type from.
+        default: IecValue,
+    },
+}
+
+impl Expr {
+    /// If the expression is an untyped literal, which takes its type from the context.
+    pub fn is_untyped(&self) -> bool {
+        match &self.kind {
+            ExprKind::Literal(Literal::Untyped { .. }) => true,
+            ExprKind::Unary(UnaryOp::Neg, expr) => expr.is_untyped(),
+            ExprKind::Binary(op, a, b) if !op.is_comparison() => a.is_untyped() && b.is_untyped(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,792 @@
+//! Compiling the syntax tree of Structured Text to a [`Program`], checking types on the way.
+//!
+//! Untyped literals take the type of the other operand of an operator, or of the other generic
+//! inputs of a function, or else the type expected by the context. Implicit conversions become
+//! explicit instructions, and operations on constants are evaluated right away.
+
+use super::ast::{
+    BinaryOp, Body, CaseLabel, Expr, ExprKind, Literal, Position, Statement, UnaryOp, Variable,
+};
+use super::functions::{self, common_type};
+use super::vm::{self, Instruction, Operand, Place, Program};
+use super::{CompileError, ErrorKind};
+use crate::runtime::interface::VariableDeclaration;
+use crate::runtime::literal;
+use crate::runtime::value::{
+    self, is_bit_string, is_integer, is_numeric, is_real, IecType, IecValue,
+};
+
+/// Compile the body of an algorithm, operating on the variables of a function block.
+pub fn compile_body(
+    body: &Body,
+    variables: &[VariableDeclaration],
+) -> Result<Program, CompileError> {
+    let mut compiler = Compiler::new(variables, &body.temps);
+    compiler.block(&body.statements)?;
+    Ok(compiler.finish(None))
+}
+
+/// Compile a condition, which must be a `BOOL` expression.
+pub fn compile_condition(
+    expr: &Expr,
+    variables: &[VariableDeclaration],
+) -> Result<Program, CompileError> {
+    let mut compiler = Compiler::new(variables, &[]);
+    let result = compiler.condition(expr)?;
+    Ok(compiler.finish(Some(result)))
+}
+
+fn type_error<M: Into<String>>(position: Position, message: M) -> CompileError {
+    CompileError {
+        line: position.line,
+        column: position.column,
+        kind: ErrorKind::Type(message.into()),
+    }
+}
+
+/// The type an untyped literal should take, for the type expected by the context.
+fn literal_hint(hint: Option<IecType>) -> Option<IecType> {
+    hint.filter(|r#type| is_numeric(*r#type) || is_bit_string(*r#type))
+        .filter(|r#type| *r#type != IecType::Bool)
+}
+
+/// The value of an untyped literal, of the first of the types it fits in.
+fn untyped_literal<I>(source: &str, default: &IecValue, hints: I) -> IecValue
+where
+    I: IntoIterator<Item = Option<IecType>>,
+{
+    hints
+        .into_iter()
+        .filter_map(literal_hint)
+        .find_map(|r#type| literal::parse_as(source, r#type).ok())
+        .unwrap_or_else(|| default.clone())
+}
+
+/// The type of the result of a binary operator, if it accepts operands of the types.
+fn binary_type(op: BinaryOp, a: IecType, b: IecType) -> Option<IecType> {
+    use IecType::{Date, DateAndTime, Time, TimeOfDay};
+
+    let numeric = || common_type(a, b).filter(|r#type| is_numeric(*r#type));
+    match op {
+        BinaryOp::Or | BinaryOp::Xor | BinaryOp::And => {
+            common_type(a, b).filter(|r#type| is_bit_string(*r#type))
+        }
+        op if op.is_comparison() => {
+            (a == b || common_type(a, b).is_some()).then_some(IecType::Bool)
+        }
+        BinaryOp::Add => match (a, b) {
+            (Time | TimeOfDay | DateAndTime, Time) => Some(a),
+            _ => numeric(),
+        },
+        BinaryOp::Sub => match (a, b) {
+            (Time | TimeOfDay | DateAndTime, Time) => Some(a),
+            (TimeOfDay, TimeOfDay) | (DateAndTime, DateAndTime) | (Date, Date) => Some(Time),
+            _ => numeric(),
+        },
+        BinaryOp::Mul => match (a, b) {
+            (Time, factor) | (factor, Time) if is_numeric(factor) => Some(Time),
+            _ => numeric(),
+        },
+        BinaryOp::Div => match (a, b) {
+            (Time, divisor) if is_numeric(divisor) => Some(Time),
+            _ => numeric(),
+        },
+        BinaryOp::Mod => common_type(a, b).filter(|r#type| is_integer(*r#type)),
+        _ => (is_numeric(a) && is_numeric(b)).then(|| functions::expt_type(a, b)),
+    }
+}
+
+fn unary_type(op: UnaryOp, r#type: IecType) -> Option<IecType> {
+    let valid = match op {
+        UnaryOp::Neg => {
+            matches!(
+                r#type,
+                IecType::Sint | IecType::Int | IecType::Dint | IecType::Lint | IecType::Time
+            ) || is_real(r#type)
+        }
+        UnaryOp::Not => is_bit_string(r#type),
+    };
+    valid.then_some(r#type)
+}
+
+/// A compiled expression.
+#[derive(Clone, Copy, Debug)]
+struct Value {
+    operand: Operand,
+    r#type: IecType,
+}
+
+struct Compiler<'a> {
+    variables: &'a [VariableDeclaration],
+    temps: &'a [VariableDeclaration],
+    code: Vec<Instruction>,
+    constants: Vec<IecValue>,
+    /// The next free register, the ones before hold temporary variables and intermediate results
+    /// of the current statement.
+    next: usize,
+    registers: usize,
+    /// The jumps of `EXIT` statements, for each enclosing loop.
+    exits: Vec<Vec<usize>>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(variables: &'a [VariableDeclaration], temps: &'a [VariableDeclaration]) -> Self {
+        Self {
+            variables,
+            temps,
+            code: Vec::new(),
+            constants: Vec::new(),
+            next: temps.len(),
+            registers: temps.len(),
+            exits: Vec::new(),
+        }
+    }
+
+    fn finish(self, result: Option<Operand>) -> Program {
+        let mut registers = self
+            .temps
+            .iter()
+            .map(VariableDeclaration::initial_value)
+            .collect::<Vec<_>>();
+        registers.resize(self.registers, IecValue::Bool(false));
+
+        Program {
+            code: self.code,
+            constants: self.constants,
+            registers,
+            result,
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// Let a jump continue at the current end of the code.
+    fn patch(&mut self, jump: usize) {
+        let end = self.code.len();
+        match &mut self.code[jump] {
+            Instruction::Jump(target)
+            | Instruction::JumpIf { target, .. }
+            | Instruction::ForTest { target, .. } => *target = end,
+            instruction => unreachable!("not a jump: {instruction:?}"),
+        }
+    }
+
+    fn register(&mut self) -> Place {
+        let register = self.next;
+        self.next += 1;
+        self.registers = self.registers.max(self.next);
+        Place::Reg(register)
+    }
+
+    fn constant(&mut self, value: IecValue) -> Value {
+        let r#type = value.r#type();
+        let index = match self.constants.iter().position(|c| *c == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        Value {
+            operand: Operand::Const(index),
+            r#type,
+        }
+    }
+
+    fn declaration(&self, variable: Variable) -> &'a VariableDeclaration {
+        match variable {
+            Variable::Block(index) => &self.variables[index],
+            Variable::Temp(index) => &self.temps[index],
+        }
+    }
+
+    fn place(&self, variable: Variable) -> Place {
+        match variable {
+            Variable::Block(index) => Place::Var(index),
+            Variable::Temp(index) => Place::Reg(index),
+        }
+    }
+
+    /// Evaluate an instruction with constant operands.
+    fn fold(&self, instruction: &Instruction) -> Option<IecValue> {
+        let constant = |operand| match operand {
+            Operand::Const(index) => Some(&self.constants[index]),
+            _ => None,
+        };
+        match instruction {
+            Instruction::Convert { src, r#type, .. } => {
+                value::convert(constant(*src)?, *r#type).ok()
+            }
+            Instruction::Unary { op, src, .. } => vm::unary(*op, constant(*src)?).ok(),
+            Instruction::Binary { op, a, b, .. } => {
+                vm::binary(*op, constant(*a)?, constant(*b)?).ok()
+            }
+            Instruction::Call {
+                function,
+                args,
+                r#type,
+                ..
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| constant(*arg).cloned())
+                    .collect::<Option<Vec<_>>>()?;
+                vm::call(function, &args, *r#type).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Emit an instruction writing its result to a new register, or evaluate it right away if
+    /// its operands are constant.
+    fn operation<F>(&mut self, r#type: IecType, instruction: F) -> Value
+    where
+        F: FnOnce(Place) -> Instruction,
+    {
+        let instruction = instruction(Place::Reg(self.next));
+        if let Some(value) = self.fold(&instruction) {
+            debug_assert_eq!(value.r#type(), r#type);
+            return self.constant(value);
+        }
+
+        let dst = self.register();
+        self.emit(instruction);
+        Value {
+            operand: dst.into(),
+            r#type,
+        }
+    }
+
+    /// Implicitly convert a value, if it has a smaller type.
+    fn convert(
+        &mut self,
+        value: Value,
+        r#type: IecType,
+        position: Position,
+        what: &str,
+    ) -> Result<Value, CompileError> {
+        if value.r#type == r#type {
+            return Ok(value);
+        }
+        if common_type(value.r#type, r#type) != Some(r#type) {
+            return Err(type_error(
+                position,
+                format!("Cannot assign a {} to {what} of type {type}", value.r#type),
+            ));
+        }
+        Ok(self.operation(r#type, |dst| Instruction::Convert {
+            dst,
+            src: value.operand,
+            r#type,
+        }))
+    }
+
+    /// A value which doesn't change while the body of a loop runs.
+    fn fixed(&mut self, value: Value) -> Value {
+        match value.operand {
+            Operand::Const(_) => value,
+            Operand::Reg(register) if register >= self.temps.len() => value,
+            src => {
+                let dst = self.register();
+                self.emit(Instruction::Move { dst, src });
+                Value {
+                    operand: dst.into(),
+                    ..value
+                }
+            }
+        }
+    }
+
+    fn assign(
+        &mut self,
+        target: Variable,
+        value: Value,
+        position: Position,
+    ) -> Result<(), CompileError> {
+        let declaration = self.declaration(target);
+        let value = self.convert(value, declaration.r#type, position, &declaration.name)?;
+        let place = self.place(target);
+
+        // write the result of the last instruction right to the target
+        if let Operand::Reg(register) = value.operand {
+            if register >= self.temps.len() {
+                if let Some(dst) = self.code.last_mut().and_then(Instruction::dst_mut) {
+                    if *dst == Place::Reg(register) {
+                        *dst = place;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        self.emit(Instruction::Move {
+            dst: place,
+            src: value.operand,
+        });
+        Ok(())
+    }
+
+    fn condition(&mut self, expr: &Expr) -> Result<Operand, CompileError> {
+        let value = self.expr(expr, Some(IecType::Bool))?;
+        match value.r#type {
+            IecType::Bool => Ok(value.operand),
+            r#type => Err(type_error(
+                expr.position,
+                format!("Expected a BOOL condition, not {type}"),
+            )),
+        }
+    }
+
+    /// Compile an expression, the hint is the type expected by the context.
+    fn expr(&mut self, expr: &Expr, hint: Option<IecType>) -> Result<Value, CompileError> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Typed(value)) => Ok(self.constant(value.clone())),
+            ExprKind::Literal(Literal::Untyped { source, default }) => {
+                Ok(self.constant(untyped_literal(source, default, [hint])))
+            }
+            ExprKind::Variable(variable) => Ok(Value {
+                operand: self.place(*variable).into(),
+                r#type: self.declaration(*variable).r#type,
+            }),
+            ExprKind::Unary(op, operand) => {
+                let value = self.expr(operand, hint)?;
+                let r#type = unary_type(*op, value.r#type).ok_or_else(|| {
+                    let symbol = match op {
+                        UnaryOp::Neg => "-",
+                        UnaryOp::Not => "NOT",
+                    };
+                    type_error(
+                        expr.position,
+                        format!("Invalid operand for {symbol}: {}", value.r#type),
+                    )
+                })?;
+                Ok(self.operation(r#type, |dst| Instruction::Unary {
+                    op: *op,
+                    dst,
+                    src: value.operand,
+                }))
+            }
+            ExprKind::Binary(op, a, b) => {
+                let hint = if op.is_comparison() { None } else { hint };
+                let (a, b) = match (a.is_untyped(), b.is_untyped()) {
+                    (true, false) => {
+                        let b = self.expr(b, hint)?;
+                        (self.untyped(a, Some(b.r#type), hint)?, b)
+                    }
+                    (false, true) => {
+                        let a = self.expr(a, hint)?;
+                        let b = self.untyped(b, Some(a.r#type), hint)?;
+                        (a, b)
+                    }
+                    _ => (self.expr(a, hint)?, self.expr(b, hint)?),
+                };
+                self.binary(*op, a, b, expr.position)
+            }
+            ExprKind::Call(function, args) => {
+                // untyped literals for generic inputs take the type of the other generic inputs
+                let mut values = vec![None; args.len()];
+                let mut generic = None;
+                for (index, arg) in args.iter().enumerate() {
+                    let is_generic = function.is_generic(index);
+                    if is_generic && arg.is_untyped() {
+                        continue;
+                    }
+                    let value = self.expr(arg, function.input_type(index))?;
+                    if is_generic {
+                        generic = Some(match generic {
+                            None => value.r#type,
+                            Some(r#type) => common_type(r#type, value.r#type).unwrap_or(r#type),
+                        });
+                    }
+                    values[index] = Some(value);
+                }
+
+                let hint = literal_hint(generic).or(hint);
+                let values = values
+                    .into_iter()
+                    .zip(args)
+                    .map(|(value, arg)| match value {
+                        Some(value) => Ok(value),
+                        None => self.expr(arg, hint),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let types = values.iter().map(|value| value.r#type).collect::<Vec<_>>();
+                let r#type = function
+                    .result_type(&types)
+                    .map_err(|message| type_error(expr.position, message))?;
+                let args = values
+                    .iter()
+                    .map(|value| value.operand)
+                    .collect::<Box<[_]>>();
+                Ok(self.operation(r#type, |dst| Instruction::Call {
+                    function: *function,
+                    dst,
+                    args,
+                    r#type,
+                }))
+            }
+        }
+    }
+
+    /// Compile an untyped expression, preferring the type of the other operand over the type
+    /// expected by the context.
+    ///
+    /// The type of the other operand is skipped for a literal which doesn't fit, like `1.5` for
+    /// an `INT` operand.
+    fn untyped(
+        &mut self,
+        expr: &Expr,
+        other: Option<IecType>,
+        hint: Option<IecType>,
+    ) -> Result<Value, CompileError> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Untyped { source, default }) => {
+                Ok(self.constant(untyped_literal(source, default, [other, hint])))
+            }
+            _ => self.expr(expr, literal_hint(other).or(hint)),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        a: Value,
+        b: Value,
+        position: Position,
+    ) -> Result<Value, CompileError> {
+        let r#type = binary_type(op, a.r#type, b.r#type).ok_or_else(|| {
+            type_error(
+                position,
+                format!(
+                    "Invalid operands for {}: {} and {}",
+                    op.symbol(),
+                    a.r#type,
+                    b.r#type
+                ),
+            )
+        })?;
+
+        // operands of the same type take the fast paths of the machine
+        let (a, b) = match common_type(a.r#type, b.r#type) {
+            Some(common) if a.r#type != b.r#type && op != BinaryOp::Expt => {
+                (self.widen(a, common), self.widen(b, common))
+            }
+            _ => (a, b),
+        };
+        Ok(self.operation(r#type, |dst| Instruction::Binary {
+            op,
+            dst,
+            a: a.operand,
+            b: b.operand,
+        }))
+    }
+
+    fn widen(&mut self, value: Value, r#type: IecType) -> Value {
+        if value.r#type == r#type {
+            return value;
+        }
+        self.operation(r#type, |dst| Instruction::Convert {
+            dst,
+            src: value.operand,
+            r#type,
+        })
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for statement in statements {
+            // intermediate results don't outlive their statement
+            let next = self.next;
+            self.statement(statement)?;
+            self.next = next;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match statement {
+            Statement::Assign { target, value } => {
+                let hint = self.declaration(*target).r#type;
+                let result = self.expr(value, Some(hint))?;
+                self.assign(*target, result, value.position)
+            }
+            Statement::If {
+                branches,
+                otherwise,
+            } => {
+                let mut ends = Vec::new();
+                for (condition, body) in branches {
+                    let condition = self.condition(condition)?;
+                    let skip = self.emit(Instruction::JumpIf {
+                        condition,
+                        value: false,
+                        target: 0,
+                    });
+                    self.block(body)?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    self.patch(skip);
+                }
+                self.block(otherwise)?;
+                for end in ends {
+                    self.patch(end);
+                }
+                Ok(())
+            }
+            Statement::Case {
+                selector,
+                branches,
+                otherwise,
+            } => {
+                let selector = self.expr(selector, None)?;
+                let mut ends = Vec::new();
+                for (labels, body) in branches {
+                    let mut matches = Vec::new();
+                    for label in labels {
+                        match label {
+                            CaseLabel::Value(value) => {
+                                let equal = self.label(BinaryOp::Eq, selector, value)?;
+                                matches.push(self.emit(Instruction::JumpIf {
+                                    condition: equal,
+                                    value: true,
+                                    target: 0,
+                                }));
+                            }
+                            CaseLabel::Range(low, high) => {
+                                let above = self.label(BinaryOp::Ge, selector, low)?;
+                                let skip = self.emit(Instruction::JumpIf {
+                                    condition: above,
+                                    value: false,
+                                    target: 0,
+                                });
+                                let below = self.label(BinaryOp::Le, selector, high)?;
+                                matches.push(self.emit(Instruction::JumpIf {
+                                    condition: below,
+                                    value: true,
+                                    target: 0,
+                                }));
+                                self.patch(skip);
+                            }
+                        }
+                    }
+                    let next = self.emit(Instruction::Jump(0));
+                    for jump in matches {
+                        self.patch(jump);
+                    }
+                    self.block(body)?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    self.patch(next);
+                }
+                self.block(otherwise)?;
+                for end in ends {
+                    self.patch(end);
+                }
+                Ok(())
+            }
+            Statement::For {
+                variable,
+                from,
+                to,
+                by,
+                body,
+            } => self.for_loop(*variable, from, to, by.as_ref(), body),
+            Statement::While { condition, body } => {
+                let start = self.code.len();
+                let condition = self.condition(condition)?;
+                let exit = self.emit(Instruction::JumpIf {
+                    condition,
+                    value: false,
+                    target: 0,
+                });
+                self.loop_body(body)?;
+                self.emit(Instruction::Jump(start));
+                self.patch(exit);
+                self.exit_loop();
+                Ok(())
+            }
+            Statement::Repeat { body, until } => {
+                let start = self.code.len();
+                self.loop_body(body)?;
+                let condition = self.condition(until)?;
+                self.emit(Instruction::JumpIf {
+                    condition,
+                    value: false,
+                    target: start,
+                });
+                self.exit_loop();
+                Ok(())
+            }
+            Statement::Exit => {
+                let jump = self.emit(Instruction::Jump(0));
+                self.exits
+                    .last_mut()
+                    .expect("the parser only accepts EXIT in loops")
+                    .push(jump);
+                Ok(())
+            }
+            Statement::Return => {
+                self.emit(Instruction::Return);
+                Ok(())
+            }
+        }
+    }
+
+    /// Compare the selector of a `CASE` statement with a label.
+    fn label(
+        &mut self,
+        op: BinaryOp,
+        selector: Value,
+        label: &Expr,
+    ) -> Result<Operand, CompileError> {
+        let label_value = self.untyped(label, Some(selector.r#type), None)?;
+        if binary_type(op, selector.r#type, label_value.r#type).is_none() {
+            return Err(type_error(
+                label.position,
+                format!(
+                    "Invalid label for a {} selector: {}",
+                    selector.r#type, label_value.r#type
+                ),
+            ));
+        }
+        Ok(self
+            .binary(op, selector, label_value, label.position)?
+            .operand)
+    }
+
+    fn loop_body(&mut self, body: &[Statement]) -> Result<(), CompileError> {
+        self.exits.push(Vec::new());
+        self.block(body)
+    }
+
+    /// Let the `EXIT` statements of the innermost loop continue at the current end of the code.
+    fn exit_loop(&mut self) {
+        for exit in self.exits.pop().unwrap_or_default() {
+            self.patch(exit);
+        }
+    }
+
+    fn for_loop(
+        &mut self,
+        variable: Variable,
+        from: &Expr,
+        to: &Expr,
+        by: Option<&Expr>,
+        body: &[Statement],
+    ) -> Result<(), CompileError> {
+        let declaration = self.declaration(variable);
+        let r#type = declaration.r#type;
+        if !is_integer(r#type) {
+            return Err(type_error(
+                from.position,
+                format!(
+                    "The variable of a FOR loop must be an integer, {} is a {type}",
+                    declaration.name
+                ),
+            ));
+        }
+
+        let start = self.expr(from, Some(r#type))?;
+        self.assign(variable, start, from.position)?;
+
+        let end = self.expr(to, Some(r#type))?;
+        let end = self.convert(end, r#type, to.position, "the end value")?;
+        let end = self.fixed(end);
+
+        let step = match by {
+            Some(by) => {
+                let step = self.expr(by, Some(r#type))?;
+                let step = self.convert(step, r#type, by.position, "the increment")?;
+                if let Operand::Const(index) = step.operand {
+                    if self.constants[index] == r#type.default_value() {
+                        return Err(type_error(
+                            by.position,
+                            "The increment of a FOR loop must not be zero",
+                        ));
+                    }
+                }
+                self.fixed(step)
+            }
+            None => {
+                let one = value::convert(&IecValue::Dint(1), r#type)
+                    .expect("1 fits into every integer type");
+                self.constant(one)
+            }
+        };
+
+        let place = self.place(variable);
+        let test = self.emit(Instruction::ForTest {
+            var: place,
+            to: end.operand,
+            step: step.operand,
+            target: 0,
+        });
+        self.loop_body(body)?;
+        self.emit(Instruction::ForNext {
+            var: place,
+            step: step.operand,
+            target: test,
+        });
+        self.patch(test);
+        self.exit_loop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::st::parser;
+
+    fn compile(source: &str) -> Program {
+        let variables = vec![
+            VariableDeclaration::new("I", IecType::Int),
+            VariableDeclaration::new("D", IecType::Dint),
+        ];
+        let body = parser::parse_body(source, &variables).unwrap();
+        compile_body(&body, &variables).unwrap()
+    }
+
+    #[test]
+    fn constants_are_folded() {
+        let program = compile("I := 2 * 3 + ABS(-4);");
+        assert!(matches!(
+            program.code[..],
+            [Instruction::Move {
+                dst: Place::Var(0),
+                src: Operand::Const(_),
+            }]
+        ));
+        assert_eq!(program.constants.last(), Some(&IecValue::Int(10)));
+    }
+
+    #[test]
+    fn results_are_written_to_the_target() {
+        let program = compile("D := D + I;");
+        assert!(matches!(
+            program.code[..],
+            [
+                Instruction::Convert {
+                    src: Operand::Var(0),
+                    r#type: IecType::Dint,
+                    ..
+                },
+                Instruction::Binary {
+                    op: BinaryOp::Add,
+                    dst: Place::Var(1),
+                    a: Operand::Var(1),
+                    ..
+                }
+            ]
+        ));
+    }
+
+    #[test]
+    fn registers() {
+        let program = compile("VAR_TEMP x : INT := 5; END_VAR D := (D + 1) * (D - 1); x := I;");
+        assert_eq!(program.registers[0], IecValue::Int(5));
+        // the temporary variable, and the results of the operations of the first assignment
+        assert_eq!(program.registers.len(), 4);
+    }
+}
@@ -0,0 +1,1514 @@
+//! The operators of Structured Text, and the standard functions of IEC 61131-3.
+//!
+//! Integer arithmetic wraps around on overflow, like it does on the targets 4diac IDE
+//! generates code for. Operands of different types are converted to a common type, following
+//! the implicit (widening) conversions of IEC 61131-3.
+
+use crate::runtime::basic::ExecutionError;
+use crate::runtime::value::{
+    bit_size, convert, from_int, from_real, int_info, int_value, is_bit_string, is_convertible,
+    is_integer, is_numeric, is_real, real_value, IecType, IecValue,
+};
+use std::cmp::Ordering;
+
+const NANOS_PER_DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+fn failed<M: Into<String>>(message: M) -> ExecutionError {
+    ExecutionError::Failed(message.into())
+}
+
+fn signed_type(bits: u32) -> Option<IecType> {
+    match bits {
+        8 => Some(IecType::Sint),
+        16 => Some(IecType::Int),
+        32 => Some(IecType::Dint),
+        64 => Some(IecType::Lint),
+        _ => None,
+    }
+}
+
+/// The type both types can implicitly be converted to, if any.
+pub fn common_type(a: IecType, b: IecType) -> Option<IecType> {
+    if a == b {
+        return Some(a);
+    }
+
+    if let (Some((signed_a, bits_a)), Some((signed_b, bits_b))) = (int_info(a), int_info(b)) {
+        return if signed_a == signed_b {
+            Some(if bits_a >= bits_b { a } else { b })
+        } else {
+            let (signed, unsigned) = if signed_a {
+                (bits_a, bits_b)
+            } else {
+                (bits_b, bits_a)
+            };
+            signed_type(signed.max(unsigned * 2))
+        };
+    }
+
+    if is_real(a) || is_real(b) {
+        let other = if is_real(a) { b } else { a };
+        if is_real(other) {
+            return Some(IecType::Lreal);
+        }
+        let (_, bits) = int_info(other)?;
+        return Some(
+            if bits <= 16 && (a == IecType::Real || b == IecType::Real) {
+                IecType::Real
+            } else {
+                IecType::Lreal
+            },
+        );
+    }
+
+    let (bits_a, bits_b) = (bit_size(a)?, bit_size(b)?);
+    Some(if bits_a >= bits_b { a } else { b })
+}
+
+/// The common type of all values, along with the values converted to it.
+pub fn unify(values: &[IecValue]) -> Result<(IecType, Vec<IecValue>), ExecutionError> {
+    let Some(first) = values.first() else {
+        return Err(failed("No values"));
+    };
+
+    let mut r#type = first.r#type();
+    for value in &values[1..] {
+        r#type = common_type(r#type, value.r#type())
+            .ok_or_else(|| failed(format!("Incompatible types: {type} and {}", value.r#type())))?;
+    }
+
+    let values = values
+        .iter()
+        .map(|value| convert(value, r#type))
+        .collect::<Result<_, _>>()?;
+
+    Ok((r#type, values))
+}
+
+/// Convert a value, if it can implicitly be converted to the type.
+pub fn convert_implicit(value: IecValue, r#type: IecType) -> Result<IecValue, ExecutionError> {
+    if value.r#type() == r#type {
+        Ok(value)
+    } else if common_type(value.r#type(), r#type) == Some(r#type) {
+        Ok(convert(&value, r#type)?)
+    } else {
+        Err(failed(format!(
+            "Cannot implicitly convert {} to {type}",
+            value.r#type()
+        )))
+    }
+}
+
+/// The value of an integer argument, like a length or a position.
+fn int_argument(value: &IecValue, name: &str) -> Result<i128, ExecutionError> {
+    match value {
+        IecValue::Bool(_) => None,
+        value => int_value(value),
+    }
+    .ok_or_else(|| failed(format!("{name} must be an integer, not {}", value.r#type())))
+}
+
+/// Apply an arithmetic operation to numeric operands.
+fn arithmetic(
+    operator: &str,
+    a: &IecValue,
+    b: &IecValue,
+    int: fn(i128, i128) -> Option<i128>,
+    real: fn(f64, f64) -> f64,
+) -> Result<IecValue, ExecutionError> {
+    let r#type = common_type(a.r#type(), b.r#type())
+        .filter(|r#type| is_numeric(*r#type))
+        .ok_or_else(|| {
+            failed(format!(
+                "Invalid operands for {operator}: {} and {}",
+                a.r#type(),
+                b.r#type()
+            ))
+        })?;
+
+    if is_real(r#type) {
+        let (a, b) = (real_value(a).unwrap(), real_value(b).unwrap());
+        Ok(from_real(real(a, b), r#type))
+    } else {
+        let (a, b) = (int_value(a).unwrap(), int_value(b).unwrap());
+        int(a, b)
+            .map(|value| from_int(value, r#type))
+            .ok_or_else(|| failed("Division by zero"))
+    }
+}
+
+fn invalid_operands(operator: &str, a: &IecValue, b: &IecValue) -> ExecutionError {
+    failed(format!(
+        "Invalid operands for {operator}: {} and {}",
+        a.r#type(),
+        b.r#type()
+    ))
+}
+
+fn time_of_day(nanos: i64) -> IecValue {
+    IecValue::TimeOfDay(nanos.rem_euclid(NANOS_PER_DAY) as u64)
+}
+
+pub fn add(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (a, b) {
+        (IecValue::Time(a), IecValue::Time(b)) => Ok(IecValue::Time(a.wrapping_add(*b))),
+        (IecValue::TimeOfDay(a), IecValue::Time(b)) => {
+            Ok(time_of_day((*a as i64).wrapping_add(*b)))
+        }
+        (IecValue::DateAndTime(a), IecValue::Time(b)) => {
+            Ok(IecValue::DateAndTime(a.wrapping_add(*b)))
+        }
+        _ => arithmetic("+", a, b, |a, b| Some(a.wrapping_add(b)), |a, b| a + b),
+    }
+}
+
+pub fn sub(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (a, b) {
+        (IecValue::Time(a), IecValue::Time(b)) => Ok(IecValue::Time(a.wrapping_sub(*b))),
+        (IecValue::TimeOfDay(a), IecValue::Time(b)) => {
+            Ok(time_of_day((*a as i64).wrapping_sub(*b)))
+        }
+        (IecValue::TimeOfDay(a), IecValue::TimeOfDay(b)) => {
+            Ok(IecValue::Time(*a as i64 - *b as i64))
+        }
+        (IecValue::DateAndTime(a), IecValue::Time(b)) => {
+            Ok(IecValue::DateAndTime(a.wrapping_sub(*b)))
+        }
+        (IecValue::DateAndTime(a), IecValue::DateAndTime(b))
+        | (IecValue::Date(a), IecValue::Date(b)) => Ok(IecValue::Time(a.wrapping_sub(*b))),
+        _ => arithmetic("-", a, b, |a, b| Some(a.wrapping_sub(b)), |a, b| a - b),
+    }
+}
+
+pub fn mul(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (a, b) {
+        (IecValue::Time(time), factor) | (factor, IecValue::Time(time)) => {
+            scale_time(*time, factor, |time, factor| time * factor, "*")
+        }
+        _ => arithmetic("*", a, b, |a, b| Some(a.wrapping_mul(b)), |a, b| a * b),
+    }
+}
+
+pub fn div(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (a, b) {
+        (IecValue::Time(time), divisor) => {
+            if real_value(divisor) == Some(0.0) {
+                return Err(failed("Division by zero"));
+            }
+            scale_time(*time, divisor, |time, divisor| time / divisor, "/")
+        }
+        _ => arithmetic("/", a, b, i128::checked_div, |a, b| a / b),
+    }
+}
+
+/// Multiply or divide a duration by a number.
+fn scale_time(
+    time: i64,
+    factor: &IecValue,
+    op: fn(f64, f64) -> f64,
+    operator: &str,
+) -> Result<IecValue, ExecutionError> {
+    if !is_numeric(factor.r#type()) {
+        return Err(invalid_operands(operator, &IecValue::Time(time), factor));
+    }
+    match (factor, int_value(factor)) {
+        // stay exact for integer factors
+        (_, Some(factor)) if operator == "*" => {
+            Ok(IecValue::Time((time as i128).wrapping_mul(factor) as i64))
+        }
+        (_, Some(factor)) => Ok(IecValue::Time((time as i128 / factor) as i64)),
+        (factor, None) => Ok(IecValue::Time(
+            op(time as f64, real_value(factor).unwrap()).round() as i64,
+        )),
+    }
+}
+
+/// The remainder of an integer division, which is zero when dividing by zero.
+pub fn modulo(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    if !is_integer(a.r#type()) || !is_integer(b.r#type()) {
+        return Err(invalid_operands("MOD", a, b));
+    }
+    arithmetic(
+        "MOD",
+        a,
+        b,
+        |a, b| Some(if b == 0 { 0 } else { a % b }),
+        |a, b| a % b,
+    )
+}
+
+pub fn expt(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (int_value(a), int_value(b)) {
+        (Some(_), Some(exponent)) if is_integer(a.r#type()) && is_integer(b.r#type()) => {
+            if exponent < 0 {
+                return Err(failed("Negative exponent for an integer base"));
+            }
+            let exponent = u32::try_from(exponent).unwrap_or(u32::MAX);
+            arithmetic("**", a, b, |a, _| Some(a), |a, _| a).map(|base| {
+                let r#type = base.r#type();
+                from_int(int_value(&base).unwrap().wrapping_pow(exponent), r#type)
+            })
+        }
+        _ if is_numeric(a.r#type()) && is_numeric(b.r#type()) => {
+            let r#type = if is_real(a.r#type()) {
+                a.r#type()
+            } else {
+                IecType::Lreal
+            };
+            let (a, b) = (real_value(a).unwrap(), real_value(b).unwrap());
+            Ok(from_real(a.powf(b), r#type))
+        }
+        _ => Err(invalid_operands("**", a, b)),
+    }
+}
+
+/// The type of the result of [`expt`], for numeric operands.
+pub fn expt_type(base: IecType, exponent: IecType) -> IecType {
+    if is_integer(base) && is_integer(exponent) {
+        common_type(base, exponent).unwrap_or(base)
+    } else if is_real(base) {
+        base
+    } else {
+        IecType::Lreal
+    }
+}
+
+pub fn neg(value: &IecValue) -> Result<IecValue, ExecutionError> {
+    Ok(match value {
+        IecValue::Sint(v) => IecValue::Sint(v.wrapping_neg()),
+        IecValue::Int(v) => IecValue::Int(v.wrapping_neg()),
+        IecValue::Dint(v) => IecValue::Dint(v.wrapping_neg()),
+        IecValue::Lint(v) => IecValue::Lint(v.wrapping_neg()),
+        IecValue::Real(v) => IecValue::Real(-v),
+        IecValue::Lreal(v) => IecValue::Lreal(-v),
+        IecValue::Time(v) => IecValue::Time(v.wrapping_neg()),
+        value => return Err(failed(format!("Cannot negate a {}", value.r#type()))),
+    })
+}
+
+/// Compare two values, returns `None` if they are unordered (for NaN).
+pub fn compare(a: &IecValue, b: &IecValue) -> Result<Option<Ordering>, ExecutionError> {
+    Ok(match (a, b) {
+        (IecValue::String(a), IecValue::String(b))
+        | (IecValue::WString(a), IecValue::WString(b)) => Some(a.cmp(b)),
+        (IecValue::Time(a), IecValue::Time(b))
+        | (IecValue::Date(a), IecValue::Date(b))
+        | (IecValue::DateAndTime(a), IecValue::DateAndTime(b)) => Some(a.cmp(b)),
+        (IecValue::TimeOfDay(a), IecValue::TimeOfDay(b)) => Some(a.cmp(b)),
+        _ => match common_type(a.r#type(), b.r#type()) {
+            Some(r#type) if is_real(r#type) => {
+                real_value(a).unwrap().partial_cmp(&real_value(b).unwrap())
+            }
+            Some(_) => int_value(a).unwrap().partial_cmp(&int_value(b).unwrap()),
+            None => return Err(invalid_operands("comparison", a, b)),
+        },
+    })
+}
+
+/// Apply a bitwise operation to `BOOL` or bit string operands.
+fn bitwise(
+    operator: &str,
+    a: &IecValue,
+    b: &IecValue,
+    op: fn(i128, i128) -> i128,
+) -> Result<IecValue, ExecutionError> {
+    match common_type(a.r#type(), b.r#type()) {
+        Some(r#type) if is_bit_string(r#type) => Ok(from_int(
+            op(int_value(a).unwrap(), int_value(b).unwrap()),
+            r#type,
+        )),
+        _ => Err(invalid_operands(operator, a, b)),
+    }
+}
+
+pub fn and(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    bitwise("AND", a, b, |a, b| a & b)
+}
+
+pub fn or(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    bitwise("OR", a, b, |a, b| a | b)
+}
+
+pub fn xor(a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    bitwise("XOR", a, b, |a, b| a ^ b)
+}
+
+pub fn not(value: &IecValue) -> Result<IecValue, ExecutionError> {
+    match value {
+        IecValue::Bool(v) => Ok(IecValue::Bool(!v)),
+        value if is_bit_string(value.r#type()) => {
+            Ok(from_int(!int_value(value).unwrap(), value.r#type()))
+        }
+        value => Err(failed(format!(
+            "Invalid operand for NOT: {}",
+            value.r#type()
+        ))),
+    }
+}
+
+/// The generic data types of IEC 61131-3, as far as the standard functions use them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Any,
+    /// Numbers and durations.
+    Magnitude,
+    Num,
+    Int,
+    /// Bit strings, including `BOOL`.
+    Bit,
+    Bool,
+    String,
+}
+
+impl Class {
+    pub fn accepts(&self, r#type: IecType) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Magnitude => is_numeric(r#type) || r#type == IecType::Time,
+            Self::Num => is_numeric(r#type),
+            Self::Int => is_integer(r#type),
+            Self::Bit => is_bit_string(r#type),
+            Self::Bool => r#type == IecType::Bool,
+            Self::String => matches!(r#type, IecType::String | IecType::WString),
+        }
+    }
+}
+
+impl std::fmt::Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Any => "ANY",
+            Self::Magnitude => "ANY_MAGNITUDE",
+            Self::Num => "ANY_NUM",
+            Self::Int => "ANY_INT",
+            Self::Bit => "ANY_BIT",
+            Self::Bool => "BOOL",
+            Self::String => "ANY_STRING",
+        })
+    }
+}
+
+/// An input of a standard function.
+#[derive(Debug)]
+pub struct Input {
+    pub name: &'static str,
+    /// If the input is of the generic type of the function, which all generic inputs share.
+    pub generic: bool,
+    pub class: Class,
+}
+
+const fn generic(name: &'static str, class: Class) -> Input {
+    Input {
+        name,
+        generic: true,
+        class,
+    }
+}
+
+const fn input(name: &'static str, class: Class) -> Input {
+    Input {
+        name,
+        generic: false,
+        class,
+    }
+}
+
+/// The type of the result of a standard function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    /// The common type of the generic inputs.
+    Generic,
+    /// The generic type for real numbers, `LREAL` for integers.
+    Real,
+    /// The type of the first input.
+    First,
+    Bool,
+    Int,
+    /// The type of the base of `EXPT`, or `LREAL`.
+    Expt,
+}
+
+impl Output {
+    /// The type of the result, given the common type of the generic inputs and the types of all
+    /// inputs.
+    fn r#type(&self, generic: Option<IecType>, inputs: &[IecType]) -> IecType {
+        match self {
+            Self::Generic => generic.unwrap_or(IecType::Lreal),
+            Self::Real => match generic {
+                Some(r#type) if is_real(r#type) => r#type,
+                _ => IecType::Lreal,
+            },
+            Self::First => inputs[0],
+            Self::Bool => IecType::Bool,
+            Self::Int => IecType::Int,
+            Self::Expt => expt_type(inputs[0], inputs[1]),
+        }
+    }
+}
+
+const GENERIC: Output = Output::Generic;
+const REAL: Output = Output::Real;
+const FIRST: Output = Output::First;
+const BOOL: Output = Output::Bool;
+const INT: Output = Output::Int;
+const EXPT: Output = Output::Expt;
+
+/// A standard function, with a fixed number of inputs.
+pub struct StandardFunction {
+    pub name: &'static str,
+    pub inputs: &'static [Input],
+    /// If the last input can be repeated, with increasing numbers in its name.
+    pub variadic: bool,
+    output: Output,
+    call: fn(&[IecValue]) -> Result<IecValue, ExecutionError>,
+}
+
+macro_rules! function {
+    ($name:literal, [$($input:expr),*], $output:expr, $call:expr) => {
+        StandardFunction {
+            name: $name,
+            inputs: &[$($input),*],
+            variadic: false,
+            output: $output,
+            call: $call,
+        }
+    };
+    ($name:literal, [$($input:expr),*].., $output:expr, $call:expr) => {
+        StandardFunction {
+            name: $name,
+            inputs: &[$($input),*],
+            variadic: true,
+            output: $output,
+            call: $call,
+        }
+    };
+}
+
+static FUNCTIONS: &[StandardFunction] = &[
+    // numerical functions
+    function!("ABS", [generic("IN", Class::Magnitude)], GENERIC, |args| {
+        abs(&args[0])
+    }),
+    function!("SQRT", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::sqrt
+    )),
+    function!("LN", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::ln
+    )),
+    function!("LOG", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::log10
+    )),
+    function!("EXP", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::exp
+    )),
+    function!("SIN", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::sin
+    )),
+    function!("COS", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::cos
+    )),
+    function!("TAN", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::tan
+    )),
+    function!("ASIN", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::asin
+    )),
+    function!("ACOS", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::acos
+    )),
+    function!("ATAN", [generic("IN", Class::Num)], REAL, |args| real(
+        &args[0],
+        f64::atan
+    )),
+    // arithmetic functions
+    function!(
+        "ADD",
+        [
+            generic("IN1", Class::Magnitude),
+            generic("IN2", Class::Magnitude)
+        ]..,
+        GENERIC,
+        |args| fold(args, add)
+    ),
+    function!(
+        "MUL",
+        [generic("IN1", Class::Num), generic("IN2", Class::Num)]..,
+        GENERIC,
+        |args| fold(args, mul)
+    ),
+    function!(
+        "SUB",
+        [
+            generic("IN1", Class::Magnitude),
+            generic("IN2", Class::Magnitude)
+        ],
+        GENERIC,
+        |args| sub(&args[0], &args[1])
+    ),
+    function!(
+        "DIV",
+        [generic("IN1", Class::Num), generic("IN2", Class::Num)],
+        GENERIC,
+        |args| div(&args[0], &args[1])
+    ),
+    function!(
+        "MOD",
+        [generic("IN1", Class::Int), generic("IN2", Class::Int)],
+        GENERIC,
+        |args| modulo(&args[0], &args[1])
+    ),
+    function!(
+        "EXPT",
+        [input("IN1", Class::Num), input("IN2", Class::Num)],
+        EXPT,
+        |args| expt(&args[0], &args[1])
+    ),
+    function!("MOVE", [generic("IN", Class::Any)], GENERIC, |args| Ok(
+        args[0].clone()
+    )),
+    // bit shift functions
+    function!(
+        "SHL",
+        [generic("IN", Class::Bit), input("N", Class::Int)],
+        GENERIC,
+        |args| { shift(&args[0], &args[1], false, |v, n, _| v << n) }
+    ),
+    function!(
+        "SHR",
+        [generic("IN", Class::Bit), input("N", Class::Int)],
+        GENERIC,
+        |args| { shift(&args[0], &args[1], false, |v, n, _| v >> n) }
+    ),
+    function!(
+        "ROL",
+        [generic("IN", Class::Bit), input("N", Class::Int)],
+        GENERIC,
+        |args| {
+            shift(&args[0], &args[1], true, |v, n, bits| {
+                (v << n) | (v >> (bits - n))
+            })
+        }
+    ),
+    function!(
+        "ROR",
+        [generic("IN", Class::Bit), input("N", Class::Int)],
+        GENERIC,
+        |args| {
+            shift(&args[0], &args[1], true, |v, n, bits| {
+                (v >> n) | (v << (bits - n))
+            })
+        }
+    ),
+    // bitwise boolean functions
+    function!(
+        "AND",
+        [generic("IN1", Class::Bit), generic("IN2", Class::Bit)]..,
+        GENERIC,
+        |args| fold(args, and)
+    ),
+    function!(
+        "OR",
+        [generic("IN1", Class::Bit), generic("IN2", Class::Bit)]..,
+        GENERIC,
+        |args| fold(args, or)
+    ),
+    function!(
+        "XOR",
+        [generic("IN1", Class::Bit), generic("IN2", Class::Bit)]..,
+        GENERIC,
+        |args| fold(args, xor)
+    ),
+    function!("NOT", [generic("IN", Class::Bit)], GENERIC, |args| not(
+        &args[0]
+    )),
+    // selection functions
+    function!(
+        "SEL",
+        [
+            input("G", Class::Bool),
+            generic("IN0", Class::Any),
+            generic("IN1", Class::Any)
+        ],
+        GENERIC,
+        |args| {
+            let g = match &args[0] {
+                IecValue::Bool(g) => *g,
+                value => return Err(failed(format!("G must be a BOOL, not {}", value.r#type()))),
+            };
+            let (_, mut values) = unify(&args[1..])?;
+            Ok(values.swap_remove(g as usize))
+        }
+    ),
+    function!(
+        "MAX",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        GENERIC,
+        |args| { select(args, Ordering::Greater) }
+    ),
+    function!(
+        "MIN",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        GENERIC,
+        |args| { select(args, Ordering::Less) }
+    ),
+    function!(
+        "LIMIT",
+        [
+            generic("MN", Class::Any),
+            generic("IN", Class::Any),
+            generic("MX", Class::Any)
+        ],
+        GENERIC,
+        |args| {
+            let (_, values) = unify(args)?;
+            let value = select(&values[..2], Ordering::Greater)?;
+            select(&[value, values[2].clone()], Ordering::Less)
+        }
+    ),
+    function!(
+        "MUX",
+        [
+            input("K", Class::Int),
+            generic("IN0", Class::Any),
+            generic("IN1", Class::Any)
+        ]..,
+        GENERIC,
+        |args| {
+            let k = int_argument(&args[0], "K")?;
+            let (_, values) = unify(&args[1..])?;
+            usize::try_from(k)
+                .ok()
+                .and_then(|k| values.get(k).cloned())
+                .ok_or_else(|| failed(format!("K is out of range: {k}")))
+        }
+    ),
+    // comparison functions
+    function!(
+        "GT",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        BOOL,
+        |args| { chain(args, |o| o == Ordering::Greater) }
+    ),
+    function!(
+        "GE",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        BOOL,
+        |args| { chain(args, |o| o != Ordering::Less) }
+    ),
+    function!(
+        "EQ",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        BOOL,
+        |args| { chain(args, |o| o == Ordering::Equal) }
+    ),
+    function!(
+        "LE",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        BOOL,
+        |args| { chain(args, |o| o != Ordering::Greater) }
+    ),
+    function!(
+        "LT",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)]..,
+        BOOL,
+        |args| { chain(args, |o| o == Ordering::Less) }
+    ),
+    function!(
+        "NE",
+        [generic("IN1", Class::Any), generic("IN2", Class::Any)],
+        BOOL,
+        |args| {
+            Ok(IecValue::Bool(
+                compare(&args[0], &args[1])? != Some(Ordering::Equal),
+            ))
+        }
+    ),
+    // character string functions
+    function!("LEN", [input("IN", Class::String)], INT, |args| {
+        let (s, _) = string(&args[0], "IN")?;
+        Ok(from_int(s.chars().count() as i128, IecType::Int))
+    }),
+    function!(
+        "LEFT",
+        [input("IN", Class::String), input("L", Class::Int)],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN")?;
+            let l = length(&args[1], "L")?;
+            Ok(make_string(s.chars().take(l).collect(), r#type))
+        }
+    ),
+    function!(
+        "RIGHT",
+        [input("IN", Class::String), input("L", Class::Int)],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN")?;
+            let l = length(&args[1], "L")?;
+            let skip = s.chars().count().saturating_sub(l);
+            Ok(make_string(s.chars().skip(skip).collect(), r#type))
+        }
+    ),
+    function!(
+        "MID",
+        [
+            input("IN", Class::String),
+            input("L", Class::Int),
+            input("P", Class::Int)
+        ],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN")?;
+            let l = length(&args[1], "L")?;
+            let p = position(&args[2])?;
+            Ok(make_string(s.chars().skip(p).take(l).collect(), r#type))
+        }
+    ),
+    function!(
+        "CONCAT",
+        [input("IN1", Class::String), input("IN2", Class::String)]..,
+        FIRST,
+        |args| {
+            let (_, r#type) = string(&args[0], "IN1")?;
+            let mut result = String::new();
+            for arg in args {
+                result.push_str(string(arg, "IN")?.0);
+            }
+            Ok(make_string(result, r#type))
+        }
+    ),
+    function!(
+        "INSERT",
+        [
+            input("IN1", Class::String),
+            input("IN2", Class::String),
+            input("P", Class::Int)
+        ],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN1")?;
+            let (insert, _) = string(&args[1], "IN2")?;
+            // inserted after the P-th character
+            let p = length(&args[2], "P")?;
+            let mut result = s.chars().take(p).collect::<String>();
+            result.push_str(insert);
+            result.extend(s.chars().skip(p));
+            Ok(make_string(result, r#type))
+        }
+    ),
+    function!(
+        "DELETE",
+        [
+            input("IN", Class::String),
+            input("L", Class::Int),
+            input("P", Class::Int)
+        ],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN")?;
+            let l = length(&args[1], "L")?;
+            let p = position(&args[2])?;
+            let mut result = s.chars().take(p).collect::<String>();
+            result.extend(s.chars().skip(p + l));
+            Ok(make_string(result, r#type))
+        }
+    ),
+    function!(
+        "REPLACE",
+        [
+            input("IN1", Class::String),
+            input("IN2", Class::String),
+            input("L", Class::Int),
+            input("P", Class::Int)
+        ],
+        FIRST,
+        |args| {
+            let (s, r#type) = string(&args[0], "IN1")?;
+            let (replacement, _) = string(&args[1], "IN2")?;
+            let l = length(&args[2], "L")?;
+            let p = position(&args[3])?;
+            let mut result = s.chars().take(p).collect::<String>();
+            result.push_str(replacement);
+            result.extend(s.chars().skip(p + l));
+            Ok(make_string(result, r#type))
+        }
+    ),
+    function!(
+        "FIND",
+        [input("IN1", Class::String), input("IN2", Class::String)],
+        INT,
+        |args| {
+            let (s, _) = string(&args[0], "IN1")?;
+            let (find, _) = string(&args[1], "IN2")?;
+            // the position of the first character, or zero
+            let position = s
+                .find(find)
+                .map(|offset| s[..offset].chars().count() as i128 + 1)
+                .unwrap_or(0);
+            Ok(from_int(position, IecType::Int))
+        }
+    ),
+];
+
+fn fold(
+    args: &[IecValue],
+    op: fn(&IecValue, &IecValue) -> Result<IecValue, ExecutionError>,
+) -> Result<IecValue, ExecutionError> {
+    let mut result = args[0].clone();
+    for arg in &args[1..] {
+        result = op(&result, arg)?;
+    }
+    Ok(result)
+}
+
+/// Select the value which is greatest (or least) of all.
+fn select(args: &[IecValue], ordering: Ordering) -> Result<IecValue, ExecutionError> {
+    let (_, values) = unify(args)?;
+    let mut result = values[0].clone();
+    for value in &values[1..] {
+        if compare(value, &result)? == Some(ordering) {
+            result = value.clone();
+        }
+    }
+    Ok(result)
+}
+
+/// Check that each pair of neighbouring values fulfills the comparison.
+fn chain(args: &[IecValue], check: fn(Ordering) -> bool) -> Result<IecValue, ExecutionError> {
+    for pair in args.windows(2) {
+        if !compare(&pair[0], &pair[1])?.is_some_and(check) {
+            return Ok(IecValue::Bool(false));
+        }
+    }
+    Ok(IecValue::Bool(true))
+}
+
+fn abs(value: &IecValue) -> Result<IecValue, ExecutionError> {
+    Ok(match value {
+        IecValue::Sint(v) => IecValue::Sint(v.wrapping_abs()),
+        IecValue::Int(v) => IecValue::Int(v.wrapping_abs()),
+        IecValue::Dint(v) => IecValue::Dint(v.wrapping_abs()),
+        IecValue::Lint(v) => IecValue::Lint(v.wrapping_abs()),
+        IecValue::Real(v) => IecValue::Real(v.abs()),
+        IecValue::Lreal(v) => IecValue::Lreal(v.abs()),
+        IecValue::Time(v) => IecValue::Time(v.wrapping_abs()),
+        value if is_integer(value.r#type()) => value.clone(),
+        value => {
+            return Err(failed(format!(
+                "Invalid operand for ABS: {}",
+                value.r#type()
+            )))
+        }
+    })
+}
+
+/// Apply a function of real numbers, integers are converted to `LREAL`.
+fn real(value: &IecValue, f: fn(f64) -> f64) -> Result<IecValue, ExecutionError> {
+    let r#type = match value.r#type() {
+        r#type if is_real(r#type) => r#type,
+        r#type if is_integer(r#type) => IecType::Lreal,
+        r#type => return Err(failed(format!("Expected a number, not {type}"))),
+    };
+    Ok(from_real(f(real_value(value).unwrap()), r#type))
+}
+
+fn shift(
+    value: &IecValue,
+    n: &IecValue,
+    rotate: bool,
+    op: fn(u128, u32, u32) -> u128,
+) -> Result<IecValue, ExecutionError> {
+    let r#type = value.r#type();
+    let bits = match (bit_size(r#type), int_info(r#type)) {
+        (Some(bits), _) if bits > 1 => bits,
+        (_, Some((_, bits))) => bits,
+        _ => return Err(failed(format!("Cannot shift a {type}"))),
+    };
+    let n = int_argument(n, "N")?;
+    if n < 0 {
+        return Err(failed(format!("N must not be negative: {n}")));
+    }
+
+    let mask = u128::MAX >> (128 - bits);
+    let v = int_value(value).unwrap() as u128 & mask;
+    let result = if rotate {
+        op(v, (n % bits as i128) as u32, bits)
+    } else if n >= bits as i128 {
+        0
+    } else {
+        op(v, n as u32, bits)
+    };
+
+    Ok(from_int((result & mask) as i128, r#type))
+}
+
+fn string<'a>(value: &'a IecValue, name: &str) -> Result<(&'a str, IecType), ExecutionError> {
+    match value {
+        IecValue::String(s) | IecValue::WString(s) => Ok((s, value.r#type())),
+        value => Err(failed(format!(
+            "{name} must be a string, not {}",
+            value.r#type()
+        ))),
+    }
+}
+
+fn make_string(value: String, r#type: IecType) -> IecValue {
+    match r#type {
+        IecType::WString => IecValue::WString(value),
+        _ => IecValue::String(value),
+    }
+}
+
+/// A number of characters.
+fn length(value: &IecValue, name: &str) -> Result<usize, ExecutionError> {
+    let value = int_argument(value, name)?;
+    usize::try_from(value).map_err(|_| failed(format!("{name} must not be negative: {value}")))
+}
+
+/// The offset of the character at a position, which starts at 1.
+fn position(value: &IecValue) -> Result<usize, ExecutionError> {
+    match int_argument(value, "P")? {
+        p if p >= 1 => Ok((p - 1) as usize),
+        p => Err(failed(format!("P must be at least 1: {p}"))),
+    }
+}
+
+/// A function which can be called from Structured Text.
+#[derive(Clone, Copy, Debug)]
+pub enum Function {
+    Standard(&'static StandardFunction),
+    /// Type conversion, like `INT_TO_REAL` or `TO_REAL`.
+    Convert {
+        from: Option<IecType>,
+        to: IecType,
+    },
+    /// Conversion of a real number to an integer, by truncating it.
+    Truncate {
+        from: Option<IecType>,
+        to: IecType,
+    },
+}
+
+impl std::fmt::Debug for StandardFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StandardFunction")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+fn parse_type(name: &str) -> Option<IecType> {
+    name.parse().ok()
+}
+
+impl Function {
+    /// Find a function by its name, which is case-insensitive.
+    pub fn lookup(name: &str) -> Option<Self> {
+        let name = name.to_ascii_uppercase();
+
+        if let Some(function) = FUNCTIONS.iter().find(|function| function.name == name) {
+            return Some(Self::Standard(function));
+        }
+
+        if name == "TRUNC" {
+            return Some(Self::Truncate {
+                from: None,
+                to: IecType::Dint,
+            });
+        }
+        if let Some((from, to)) = name.split_once("_TRUNC_") {
+            return Some(Self::Truncate {
+                from: Some(parse_type(from).filter(|r#type| is_real(*r#type))?),
+                to: parse_type(to).filter(|r#type| is_integer(*r#type))?,
+            });
+        }
+        if let Some(to) = name.strip_prefix("TO_") {
+            return Some(Self::Convert {
+                from: None,
+                to: parse_type(to)?,
+            });
+        }
+        if let Some((from, to)) = name.split_once("_TO_") {
+            return Some(Self::Convert {
+                from: Some(parse_type(from)?),
+                to: parse_type(to)?,
+            });
+        }
+
+        None
+    }
+
+    /// The names of the standard functions, not including type conversions.
+    pub fn standard_names() -> impl Iterator<Item = &'static str> {
+        FUNCTIONS.iter().map(|function| function.name)
+    }
+
+    fn inputs(&self) -> &'static [Input] {
+        match self {
+            Self::Standard(function) => function.inputs,
+            Self::Convert { .. } => const { &[input("IN", Class::Any)] },
+            Self::Truncate { .. } => const { &[input("IN", Class::Num)] },
+        }
+    }
+
+    fn is_variadic(&self) -> bool {
+        matches!(self, Self::Standard(function) if function.variadic)
+    }
+
+    /// The number of inputs the function accepts, at least and at most.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let inputs = self.inputs().len();
+        (inputs, (!self.is_variadic()).then_some(inputs))
+    }
+
+    /// The name of an input, inputs of a variadic function continue the numbering of its last
+    /// declared input.
+    pub fn input_name(&self, index: usize) -> Option<String> {
+        let inputs = self.inputs();
+        if let Some(input) = inputs.get(index) {
+            return Some(input.name.to_string());
+        }
+        if !self.is_variadic() {
+            return None;
+        }
+        let last = inputs.last()?.name;
+        let prefix = last.trim_end_matches(|c: char| c.is_ascii_digit());
+        let number = last[prefix.len()..].parse::<usize>().ok()?;
+        Some(format!("{prefix}{}", number + index + 1 - inputs.len()))
+    }
+
+    /// The index of an input, by its name.
+    pub fn input_index(&self, name: &str) -> Option<usize> {
+        let inputs = self.inputs();
+        if let Some(index) = inputs
+            .iter()
+            .position(|input| input.name.eq_ignore_ascii_case(name))
+        {
+            return Some(index);
+        }
+        if !self.is_variadic() {
+            return None;
+        }
+        let last = inputs.last()?.name;
+        let prefix = last.trim_end_matches(|c: char| c.is_ascii_digit());
+        let last_number = last[prefix.len()..].parse::<usize>().ok()?;
+        let number = name
+            .get(..prefix.len())
+            .filter(|p| p.eq_ignore_ascii_case(prefix))
+            .and_then(|_| name[prefix.len()..].parse::<usize>().ok())?;
+        // declared inputs have been matched by name already
+        Some(inputs.len() + number.checked_sub(last_number + 1)?)
+    }
+
+    /// The declaration of an input, the last one for the repeated inputs of a variadic function.
+    fn input(&self, index: usize) -> Option<&'static Input> {
+        let inputs = self.inputs();
+        match inputs.get(index) {
+            Some(input) => Some(input),
+            None if self.is_variadic() => inputs.last(),
+            None => None,
+        }
+    }
+
+    /// The class of types an input accepts.
+    pub fn input_class(&self, index: usize) -> Option<Class> {
+        self.input(index).map(|input| input.class)
+    }
+
+    /// How the type of the result derives from the inputs, `None` for the conversions, which
+    /// result in a specific type.
+    pub fn output(&self) -> Option<Output> {
+        match self {
+            Self::Standard(function) => Some(function.output),
+            Self::Convert { .. } | Self::Truncate { .. } => None,
+        }
+    }
+
+    /// If the input is of the generic type, shared by all generic inputs.
+    pub fn is_generic(&self, index: usize) -> bool {
+        self.input(index).is_some_and(|input| input.generic)
+    }
+
+    /// The type an untyped literal for the input should have, if the function expects a
+    /// specific type.
+    pub fn input_type(&self, index: usize) -> Option<IecType> {
+        match self {
+            Self::Convert { from, .. } | Self::Truncate { from, .. } if index == 0 => *from,
+            _ => None,
+        }
+    }
+
+    /// The type of the result when calling the function with arguments of the types, or why the
+    /// types are not accepted.
+    pub fn result_type(&self, args: &[IecType]) -> Result<IecType, String> {
+        let mut generic = None;
+        for (index, r#type) in args.iter().copied().enumerate() {
+            let input = self
+                .input(index)
+                .ok_or_else(|| format!("Wrong number of arguments: {}", args.len()))?;
+            if !input.class.accepts(r#type) {
+                return Err(format!(
+                    "{} must be {}, not {type}",
+                    self.input_name(index).unwrap_or_default(),
+                    input.class
+                ));
+            }
+            if input.generic {
+                generic = Some(match generic {
+                    None => r#type,
+                    Some(other) => common_type(other, r#type)
+                        .ok_or_else(|| format!("Incompatible types: {other} and {type}"))?,
+                });
+            }
+        }
+
+        match self {
+            Self::Standard(function) => Ok(function.output.r#type(generic, args)),
+            Self::Convert { from, to } => {
+                check_source_type(args[0], *from)?;
+                match is_convertible(args[0], *to) {
+                    true => Ok(*to),
+                    false => Err(format!("Cannot convert {} to {to}", args[0])),
+                }
+            }
+            Self::Truncate { from, to } => {
+                check_source_type(args[0], *from)?;
+                match is_real(args[0]) {
+                    true => Ok(*to),
+                    false => Err(format!("Cannot truncate a {}", args[0])),
+                }
+            }
+        }
+    }
+
+    /// Call the function, the number of arguments must match its inputs.
+    pub fn call(&self, args: &[IecValue]) -> Result<IecValue, ExecutionError> {
+        let (min, max) = self.arity();
+        if args.len() < min || max.is_some_and(|max| args.len() > max) {
+            return Err(failed(format!("Wrong number of arguments: {}", args.len())));
+        }
+
+        match self {
+            Self::Standard(function) => (function.call)(args),
+            Self::Convert { from, to } => {
+                check_source(&args[0], *from)?;
+                Ok(convert(&args[0], *to)?)
+            }
+            Self::Truncate { from, to } => {
+                check_source(&args[0], *from)?;
+                match real_value(&args[0]) {
+                    Some(value) if is_real(args[0].r#type()) => {
+                        Ok(from_int(value.trunc() as i128, *to))
+                    }
+                    _ => Err(failed(format!("Cannot truncate a {}", args[0].r#type()))),
+                }
+            }
+        }
+    }
+}
+
+fn check_source_type(r#type: IecType, from: Option<IecType>) -> Result<(), String> {
+    match from {
+        Some(from) if common_type(r#type, from) != Some(from) => {
+            Err(format!("Expected a {from}, not {type}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_source(value: &IecValue, from: Option<IecType>) -> Result<(), ExecutionError> {
+    check_source_type(value.r#type(), from).map_err(failed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn call(name: &str, args: &[IecValue]) -> Result<IecValue, ExecutionError> {
+        Function::lookup(name).unwrap().call(args)
+    }
+
+    #[test]
+    fn common_types() {
+        assert_eq!(
+            common_type(IecType::Int, IecType::Dint),
+            Some(IecType::Dint)
+        );
+        assert_eq!(
+            common_type(IecType::Usint, IecType::Int),
+            Some(IecType::Int)
+        );
+        assert_eq!(
+            common_type(IecType::Uint, IecType::Int),
+            Some(IecType::Dint)
+        );
+        assert_eq!(common_type(IecType::Ulint, IecType::Lint), None);
+        assert_eq!(
+            common_type(IecType::Int, IecType::Real),
+            Some(IecType::Real)
+        );
+        assert_eq!(
+            common_type(IecType::Dint, IecType::Real),
+            Some(IecType::Lreal)
+        );
+        assert_eq!(
+            common_type(IecType::Byte, IecType::Word),
+            Some(IecType::Word)
+        );
+        assert_eq!(common_type(IecType::Byte, IecType::Int), None);
+        assert_eq!(common_type(IecType::String, IecType::WString), None);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            add(&IecValue::Int(1), &IecValue::Dint(2)),
+            Ok(IecValue::Dint(3))
+        );
+        assert_eq!(
+            add(&IecValue::Sint(127), &IecValue::Sint(1)),
+            Ok(IecValue::Sint(-128))
+        );
+        assert_eq!(
+            div(&IecValue::Int(7), &IecValue::Int(2)),
+            Ok(IecValue::Int(3))
+        );
+        assert!(div(&IecValue::Int(7), &IecValue::Int(0)).is_err());
+        assert_eq!(
+            modulo(&IecValue::Int(-7), &IecValue::Int(2)),
+            Ok(IecValue::Int(-1))
+        );
+        assert_eq!(
+            modulo(&IecValue::Int(7), &IecValue::Int(0)),
+            Ok(IecValue::Int(0))
+        );
+        assert_eq!(
+            mul(&IecValue::Real(1.5), &IecValue::Int(2)),
+            Ok(IecValue::Real(3.0))
+        );
+        assert_eq!(
+            expt(&IecValue::Int(2), &IecValue::Int(10)),
+            Ok(IecValue::Int(1024))
+        );
+        assert_eq!(
+            expt(&IecValue::Lreal(4.0), &IecValue::Lreal(0.5)),
+            Ok(IecValue::Lreal(2.0))
+        );
+        assert!(add(&IecValue::Bool(true), &IecValue::Int(1)).is_err());
+        assert!(neg(&IecValue::Uint(1)).is_err());
+    }
+
+    #[test]
+    fn time_arithmetic() {
+        let s = |s: i64| IecValue::Time(s * 1_000_000_000);
+        assert_eq!(add(&s(1), &s(2)), Ok(s(3)));
+        assert_eq!(mul(&s(2), &IecValue::Dint(3)), Ok(s(6)));
+        assert_eq!(mul(&IecValue::Lreal(0.5), &s(4)), Ok(s(2)));
+        assert_eq!(div(&s(6), &IecValue::Int(3)), Ok(s(2)));
+        assert_eq!(
+            add(&IecValue::TimeOfDay(23 * 3600 * 1_000_000_000), &s(7200)),
+            Ok(IecValue::TimeOfDay(3600 * 1_000_000_000))
+        );
+        assert_eq!(
+            sub(
+                &IecValue::DateAndTime(10_000_000_000),
+                &IecValue::DateAndTime(0)
+            ),
+            Ok(s(10))
+        );
+    }
+
+    #[test]
+    fn comparison_and_logic() {
+        assert_eq!(
+            compare(&IecValue::Int(1), &IecValue::Lreal(1.5)),
+            Ok(Some(Ordering::Less))
+        );
+        assert_eq!(
+            compare(&IecValue::String("a".into()), &IecValue::String("b".into())),
+            Ok(Some(Ordering::Less))
+        );
+        assert!(compare(&IecValue::String("a".into()), &IecValue::Int(1)).is_err());
+        assert_eq!(
+            and(&IecValue::Byte(0b1100), &IecValue::Word(0b1010)),
+            Ok(IecValue::Word(0b1000))
+        );
+        assert_eq!(not(&IecValue::Byte(0x0F)), Ok(IecValue::Byte(0xF0)));
+        assert_eq!(
+            xor(&IecValue::Bool(true), &IecValue::Bool(true)),
+            Ok(IecValue::Bool(false))
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            call("INT_TO_REAL", &[IecValue::Int(3)]),
+            Ok(IecValue::Real(3.0))
+        );
+        assert_eq!(
+            call("REAL_TO_INT", &[IecValue::Real(2.5)]),
+            Ok(IecValue::Int(3))
+        );
+        assert_eq!(
+            call("LREAL_TO_DINT", &[IecValue::Lreal(-2.5)]),
+            Ok(IecValue::Dint(-3))
+        );
+        assert_eq!(
+            call("TRUNC", &[IecValue::Lreal(-2.7)]),
+            Ok(IecValue::Dint(-2))
+        );
+        assert_eq!(
+            call("DINT_TO_USINT", &[IecValue::Dint(300)]),
+            Ok(IecValue::Usint(44))
+        );
+        assert_eq!(
+            call("TIME_TO_DINT", &[IecValue::Time(1_500_000_000)]),
+            Ok(IecValue::Dint(1500))
+        );
+        assert_eq!(
+            call("TO_TIME", &[IecValue::Dint(20)]),
+            Ok(IecValue::Time(20_000_000))
+        );
+        assert_eq!(
+            call("INT_TO_STRING", &[IecValue::Int(-5)]),
+            Ok(IecValue::String("-5".into()))
+        );
+        assert_eq!(
+            call("STRING_TO_INT", &[IecValue::String("42".into())]),
+            Ok(IecValue::Int(42))
+        );
+        assert_eq!(
+            call("BOOL_TO_INT", &[IecValue::Bool(true)]),
+            Ok(IecValue::Int(1))
+        );
+        assert_eq!(
+            call("DT_TO_TOD", &[IecValue::DateAndTime(NANOS_PER_DAY + 5)]),
+            Ok(IecValue::TimeOfDay(5))
+        );
+        assert!(call("STRING_TO_INT", &[IecValue::String("x".into())]).is_err());
+        assert!(call("INT_TO_REAL", &[IecValue::Dint(1)]).is_err());
+        assert!(call("DATE_TO_INT", &[IecValue::Date(0)]).is_err());
+        assert!(Function::lookup("FOO_TO_INT").is_none());
+    }
+
+    #[test]
+    fn standard_functions() {
+        assert_eq!(
+            call(
+                "MAX",
+                &[IecValue::Int(1), IecValue::Dint(5), IecValue::Int(3)]
+            ),
+            Ok(IecValue::Dint(5))
+        );
+        assert_eq!(
+            call(
+                "LIMIT",
+                &[IecValue::Int(0), IecValue::Int(12), IecValue::Int(10)]
+            ),
+            Ok(IecValue::Int(10))
+        );
+        assert_eq!(
+            call(
+                "SEL",
+                &[IecValue::Bool(true), IecValue::Int(1), IecValue::Int(2)]
+            ),
+            Ok(IecValue::Int(2))
+        );
+        assert_eq!(
+            call(
+                "MUX",
+                &[
+                    IecValue::Dint(2),
+                    IecValue::Int(1),
+                    IecValue::Int(2),
+                    IecValue::Int(3)
+                ]
+            ),
+            Ok(IecValue::Int(3))
+        );
+        assert!(call(
+            "MUX",
+            &[IecValue::Dint(3), IecValue::Int(1), IecValue::Int(2)]
+        )
+        .is_err());
+        assert_eq!(
+            call(
+                "GT",
+                &[IecValue::Int(3), IecValue::Int(2), IecValue::Int(1)]
+            ),
+            Ok(IecValue::Bool(true))
+        );
+        assert_eq!(
+            call("NE", &[IecValue::Int(3), IecValue::Int(3)]),
+            Ok(IecValue::Bool(false))
+        );
+        assert_eq!(
+            call("SHL", &[IecValue::Byte(0x81), IecValue::Dint(1)]),
+            Ok(IecValue::Byte(0x02))
+        );
+        assert_eq!(
+            call("ROL", &[IecValue::Byte(0x81), IecValue::Dint(1)]),
+            Ok(IecValue::Byte(0x03))
+        );
+        assert_eq!(
+            call("ROR", &[IecValue::Byte(0x81), IecValue::Dint(9)]),
+            Ok(IecValue::Byte(0xC0))
+        );
+        assert_eq!(
+            call("SHR", &[IecValue::Word(0x8000), IecValue::Dint(16)]),
+            Ok(IecValue::Word(0))
+        );
+        assert_eq!(call("ABS", &[IecValue::Int(-3)]), Ok(IecValue::Int(3)));
+        assert_eq!(call("SQRT", &[IecValue::Dint(9)]), Ok(IecValue::Lreal(3.0)));
+        assert!(call("ADD", &[IecValue::Int(1)]).is_err());
+    }
+
+    #[test]
+    fn string_functions() {
+        let s = |s: &str| IecValue::String(s.into());
+        let n = IecValue::Dint;
+
+        assert_eq!(call("LEN", &[s("ABCD")]), Ok(IecValue::Int(4)));
+        assert_eq!(call("LEFT", &[s("ABCD"), n(2)]), Ok(s("AB")));
+        assert_eq!(call("RIGHT", &[s("ABCD"), n(3)]), Ok(s("BCD")));
+        assert_eq!(call("MID", &[s("ABCD"), n(2), n(2)]), Ok(s("BC")));
+        assert_eq!(call("CONCAT", &[s("AB"), s("CD"), s("E")]), Ok(s("ABCDE")));
+        assert_eq!(call("INSERT", &[s("ABC"), s("XY"), n(2)]), Ok(s("ABXYC")));
+        assert_eq!(call("DELETE", &[s("ABXYC"), n(2), n(3)]), Ok(s("ABC")));
+        assert_eq!(
+            call("REPLACE", &[s("ABCDE"), s("X"), n(2), n(3)]),
+            Ok(s("ABXE"))
+        );
+        assert_eq!(call("FIND", &[s("ABCBC"), s("BC")]), Ok(IecValue::Int(2)));
+        assert_eq!(call("FIND", &[s("ABC"), s("X")]), Ok(IecValue::Int(0)));
+        assert!(call("MID", &[s("ABCD"), n(2), n(0)]).is_err());
+    }
+
+    #[test]
+    fn inputs() {
+        let mux = Function::lookup("mux").unwrap();
+        assert_eq!(mux.input_name(3).as_deref(), Some("IN2"));
+        assert_eq!(mux.input_index("IN2"), Some(3));
+        assert_eq!(mux.input_index("K"), Some(0));
+        assert!(!mux.is_generic(0));
+        assert!(mux.is_generic(5));
+
+        let add = Function::lookup("ADD").unwrap();
+        assert_eq!(add.input_index("IN3"), Some(2));
+        assert_eq!(add.input_index("IN0"), None);
+
+        let limit = Function::lookup("LIMIT").unwrap();
+        assert_eq!(limit.input_index("MX"), Some(2));
+        assert_eq!(limit.arity(), (3, Some(3)));
+    }
+}
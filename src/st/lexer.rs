@@ -0,0 +1,326 @@
+//! Splitting Structured Text into tokens.
+
+use super::{CompileError, ErrorKind};
+
+/// A token, keywords are identifiers which the parser matches case-insensitively.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Identifier(String),
+    /// A literal, as written in the source: a number, a string or a typed literal.
+    Literal(String),
+    /// An operator or a punctuation character, like `:=`, `<=`, `(` or `;`.
+    Symbol(&'static str),
+    End,
+}
+
+/// A token, along with its position in the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spanned {
+    pub token: Token,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Symbols, longer ones first.
+const SYMBOLS: &[&str] = &[
+    ":=", "<=", ">=", "<>", "**", "..", "+", "-", "*", "/", "&", "=", "<", ">", "(", ")", ",", ";",
+    ":", "[", "]", ".",
+];
+
+/// Type prefixes of date and time of day literals, whose body may contain dashes and colons.
+const DATE_PREFIXES: &[&str] = &["D", "DATE", "DT", "DATE_AND_TIME", "TOD", "TIME_OF_DAY"];
+
+struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Split the source into tokens, the last token is always [`Token::End`].
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, CompileError> {
+    let mut lexer = Lexer {
+        source,
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    let mut tokens = Vec::new();
+    loop {
+        lexer.skip_whitespace_and_comments()?;
+        let (line, column) = (lexer.line, lexer.column);
+        let token = lexer.next_token()?;
+        let end = token == Token::End;
+        tokens.push(Spanned {
+            token,
+            line,
+            column,
+        });
+        if end {
+            return Ok(tokens);
+        }
+    }
+}
+
+impl Lexer<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_while<F: Fn(char) -> bool>(&mut self, f: F) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> CompileError {
+        CompileError {
+            line: self.line,
+            column: self.column,
+            kind: ErrorKind::Syntax(message.into()),
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), CompileError> {
+        loop {
+            self.bump_while(char::is_whitespace);
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.bump_while(|c| c != '\n');
+            } else if rest.starts_with("(*") {
+                self.skip_block_comment("*)")?;
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment("*/")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_block_comment(&mut self, end: &str) -> Result<(), CompileError> {
+        let error = self.error("Unterminated comment");
+        self.bump();
+        self.bump();
+        while !self.rest().starts_with(end) {
+            self.bump().ok_or(error.clone())?;
+        }
+        self.bump();
+        self.bump();
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Token, CompileError> {
+        let start = self.offset;
+        let Some(c) = self.peek() else {
+            return Ok(Token::End);
+        };
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            self.bump_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            if self.peek() == Some('#') {
+                let prefix = self.source[start..self.offset].to_ascii_uppercase();
+                self.bump();
+                self.typed_literal_body(&prefix)?;
+                return Ok(Token::Literal(self.source[start..self.offset].to_string()));
+            }
+            return Ok(Token::Identifier(
+                self.source[start..self.offset].to_string(),
+            ));
+        }
+
+        if c.is_ascii_digit() {
+            self.number();
+            return Ok(Token::Literal(self.source[start..self.offset].to_string()));
+        }
+
+        if c == '\'' || c == '"' {
+            self.string(c)?;
+            return Ok(Token::Literal(self.source[start..self.offset].to_string()));
+        }
+
+        for symbol in SYMBOLS {
+            if self.rest().starts_with(symbol) {
+                for _ in 0..symbol.len() {
+                    self.bump();
+                }
+                return Ok(Token::Symbol(symbol));
+            }
+        }
+
+        Err(self.error(format!("Unexpected character: '{c}'")))
+    }
+
+    /// A number, possibly with a base (`16#FF`), a fraction and an exponent.
+    fn number(&mut self) {
+        self.bump_while(|c| c.is_ascii_digit() || c == '_');
+        if self.peek() == Some('#') {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_hexdigit() || c == '_');
+            return;
+        }
+        // a range (`1..5`) is no fraction
+        if self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit() || c == '_');
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<(), CompileError> {
+        let error = self.error("Unterminated string");
+        self.bump();
+        loop {
+            match self.bump().ok_or(error.clone())? {
+                // escape sequences, including an escaped quote
+                '$' => {
+                    self.bump().ok_or(error.clone())?;
+                }
+                c if c == quote => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// The body of a typed literal, after the `#`.
+    fn typed_literal_body(&mut self, prefix: &str) -> Result<(), CompileError> {
+        if let Some(quote @ ('\'' | '"')) = self.peek() {
+            return self.string(quote);
+        }
+        if matches!(self.peek(), Some('+' | '-')) {
+            self.bump();
+        }
+
+        let date = DATE_PREFIXES.contains(&prefix);
+        let mut previous = '#';
+        while let Some(c) = self.peek() {
+            let accept = c.is_ascii_alphanumeric()
+                || matches!(c, '_' | '.' | '#')
+                || (matches!(c, '-' | ':') && date)
+                || (matches!(c, '+' | '-') && matches!(previous, 'e' | 'E'));
+            // a range of typed literals (`INT#1..INT#5`)
+            if !accept || (c == '.' && self.peek_second() == Some('.')) {
+                break;
+            }
+            previous = c;
+            self.bump();
+        }
+
+        if previous == '#' {
+            return Err(self.error(format!("Missing value of {prefix}# literal")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    fn id(s: &str) -> Token {
+        Token::Identifier(s.to_string())
+    }
+
+    fn lit(s: &str) -> Token {
+        Token::Literal(s.to_string())
+    }
+
+    #[test]
+    fn tokens_and_comments() {
+        assert_eq!(
+            tokens("CV := CV + 1; (* count *)\n// done\nQ:=CV>=PV /* check */;"),
+            vec![
+                id("CV"),
+                Token::Symbol(":="),
+                id("CV"),
+                Token::Symbol("+"),
+                lit("1"),
+                Token::Symbol(";"),
+                id("Q"),
+                Token::Symbol(":="),
+                id("CV"),
+                Token::Symbol(">="),
+                id("PV"),
+                Token::Symbol(";"),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            tokens("1.5E-3 16#FF 1..5 T#-1h_30m INT#-5 D#2024-01-31 'it$'s' \"w\" TRUE"),
+            vec![
+                lit("1.5E-3"),
+                lit("16#FF"),
+                lit("1"),
+                Token::Symbol(".."),
+                lit("5"),
+                lit("T#-1h_30m"),
+                lit("INT#-5"),
+                lit("D#2024-01-31"),
+                lit("'it$'s'"),
+                lit("\"w\""),
+                id("TRUE"),
+                Token::End,
+            ]
+        );
+        assert_eq!(
+            tokens("X-T#1s"),
+            vec![id("X"), Token::Symbol("-"), lit("T#1s"), Token::End]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            tokenize("X := 'abc"),
+            Err(CompileError {
+                line: 1,
+                column: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            tokenize("X := 1;\n(* open"),
+            Err(CompileError { line: 2, .. })
+        ));
+        assert!(tokenize("X := ?").is_err());
+        assert!(tokenize("X := T#").is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Structured Text (ST), as used by the algorithms and ECC transition conditions of basic
+//! function blocks.
+//!
+//! Algorithms are compiled against the variables of the function block, so that references to
+//! unknown variables or functions, as well as type errors, are reported when loading a type, not
+//! when running it. The compiled code runs on a small register machine:
+//!
+//! ```
+//! use toref::runtime::basic::{Action, BasicType, Guard};
+//! use toref::runtime::value::IecType;
+//! use toref::st::{StAlgorithm, StCondition};
+//! use std::sync::Arc;
+//!
+//! let builder = BasicType::builder("E_CTU")
+//!     .event_input("CU", &[])
+//!     .event_output("CUO", &["CV"])
+//!     .output("CV", IecType::Uint);
+//!
+//! let count = StAlgorithm::new("CV := CV + 1;", builder.variables()).unwrap();
+//! let below = StCondition::new("CV < 65535", builder.variables()).unwrap();
+//!
+//! let r#type = builder
+//!     .algorithm_impl("CU", Arc::new(count))
+//!     .state("START", [])
+//!     .state("CU", [Action::algorithm("CU").output("CUO")])
+//!     .transition("START", "CU", Guard::event("CU").with_condition(Arc::new(below)))
+//!     .transition("CU", "START", Guard::always())
+//!     .build()
+//!     .unwrap();
+//! ```
+
+pub mod functions;
+
+mod ast;
+mod compiler;
+mod lexer;
+mod parser;
+mod vm;
+
+use crate::runtime::basic::{Algorithm, Condition, ExecutionError, Variables};
+use crate::runtime::interface::VariableDeclaration;
+use crate::runtime::literal::LiteralError;
+use crate::runtime::value::IecValue;
+
+/// An error in the source of an algorithm or condition.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{line}:{column}: {kind}")]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ErrorKind {
+    #[error("{0}")]
+    Syntax(String),
+    #[error("Unknown variable: {0}")]
+    UnknownVariable(String),
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
+    #[error("Unknown type: {0}")]
+    UnknownType(String),
+    #[error("Invalid literal: {0}")]
+    InvalidLiteral(LiteralError),
+    #[error("{0}")]
+    Type(String),
+}
+
+/// Check that the variables match the ones the code was compiled for.
+fn check_layout(variables: &Variables, expected: usize) -> Result<(), ExecutionError> {
+    match variables.layout().variables().len() {
+        len if len == expected => Ok(()),
+        _ => Err(ExecutionError::Failed(
+            "Variables don't match the layout the code was compiled for".to_string(),
+        )),
+    }
+}
+
+/// An algorithm written in Structured Text.
+#[derive(Debug)]
+pub struct StAlgorithm {
+    program: vm::Program,
+    variables: usize,
+}
+
+impl StAlgorithm {
+    /// Compile an algorithm, operating on the variables of a function block type, in the order
+    /// of its layout.
+    pub fn new<'a, I>(source: &str, variables: I) -> Result<Self, CompileError>
+    where
+        I: IntoIterator<Item = &'a VariableDeclaration>,
+    {
+        let variables = variables.into_iter().cloned().collect::<Vec<_>>();
+        let body = parser::parse_body(source, &variables)?;
+        Ok(Self {
+            program: compiler::compile_body(&body, &variables)?,
+            variables: variables.len(),
+        })
+    }
+}
+
+impl Algorithm for StAlgorithm {
+    fn execute(&self, variables: &mut Variables) -> Result<(), ExecutionError> {
+        check_layout(variables, self.variables)?;
+        vm::execute(&self.program, variables)?;
+        Ok(())
+    }
+}
+
+/// A transition condition written in Structured Text, an expression resulting in a `BOOL`.
+#[derive(Debug)]
+pub struct StCondition {
+    program: vm::Program,
+    variables: usize,
+}
+
+impl StCondition {
+    /// Compile a condition, operating on the variables of a function block type, in the order
+    /// of its layout.
+    pub fn new<'a, I>(source: &str, variables: I) -> Result<Self, CompileError>
+    where
+        I: IntoIterator<Item = &'a VariableDeclaration>,
+    {
+        let variables = variables.into_iter().cloned().collect::<Vec<_>>();
+        let expr = parser::parse_expression(source, &variables)?;
+        Ok(Self {
+            program: compiler::compile_condition(&expr, &variables)?,
+            variables: variables.len(),
+        })
+    }
+}
+
+impl Condition for StCondition {
+    fn evaluate(&self, variables: &Variables) -> Result<bool, ExecutionError> {
+        check_layout(variables, self.variables)?;
+        match vm::execute(&self.program, &mut vm::ReadOnly(variables))? {
+            Some(IecValue::Bool(value)) => Ok(value),
+            _ => unreachable!("the compiler checks the type of conditions"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::basic::Layout;
+    use crate::runtime::value::IecType;
+    use std::sync::Arc;
+
+    fn variables(declarations: &[(&str, IecType)]) -> Variables {
+        let layout = Layout::new(
+            declarations
+                .iter()
+                .map(|(name, r#type)| VariableDeclaration::new(*name, *r#type))
+                .collect(),
+        )
+        .unwrap();
+        Variables::new(Arc::new(layout))
+    }
+
+    fn run(variables: &mut Variables, source: &str) -> Result<(), ExecutionError> {
+        StAlgorithm::new(source, variables.layout().variables())
+            .unwrap()
+            .execute(variables)
+    }
+
+    fn compile(variables: &Variables, source: &str) -> Result<StAlgorithm, CompileError> {
+        StAlgorithm::new(source, variables.layout().variables())
+    }
+
+    #[test]
+    fn assignments_and_types() {
+        let mut vars = variables(&[
+            ("I", IecType::Int),
+            ("D", IecType::Dint),
+            ("R", IecType::Real),
+            ("B", IecType::Byte),
+            ("T", IecType::Time),
+            ("S", IecType::String),
+            ("U", IecType::Usint),
+        ]);
+        run(
+            &mut vars,
+            r#"
+            I := 7 / 2;
+            D := I * 1000;
+            R := 1.5 * I;
+            B := 16#F0 OR 2#1111;
+            T := T#1s * 2 + T#500ms;
+            S := CONCAT('a', INT_TO_STRING(I));
+            U := 255;
+            U := U + 1;
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(vars.get("I"), Ok(&IecValue::Int(3)));
+        assert_eq!(vars.get("D"), Ok(&IecValue::Dint(3000)));
+        assert_eq!(vars.get("R"), Ok(&IecValue::Real(4.5)));
+        assert_eq!(vars.get("B"), Ok(&IecValue::Byte(0xFF)));
+        assert_eq!(vars.get("T"), Ok(&IecValue::Time(2_500_000_000)));
+        assert_eq!(vars.get("S"), Ok(&IecValue::String("a3".into())));
+        assert_eq!(vars.get("U"), Ok(&IecValue::Usint(0)));
+
+        assert!(run(&mut vars, "D := D / (I - I);").is_err());
+    }
+
+    #[test]
+    fn control_flow() {
+        let mut vars = variables(&[
+            ("N", IecType::Int),
+            ("SUM", IecType::Dint),
+            ("MODE", IecType::String),
+        ]);
+        run(
+            &mut vars,
+            r#"
+            VAR_TEMP i : INT; END_VAR
+            N := 6;
+            FOR i := 1 TO N DO
+                IF i MOD 2 = 0 THEN SUM := SUM + i; END_IF;
+            END_FOR;
+            FOR i := 10 TO 1 BY -3 DO SUM := SUM + 1000; END_FOR;
+            WHILE TRUE DO
+                SUM := SUM + 1;
+                IF SUM > 4010 THEN EXIT; END_IF;
+            END_WHILE;
+            REPEAT N := N - 1; UNTIL N <= 0 END_REPEAT;
+            CASE SUM OF
+                0..4000: MODE := 'low';
+                4011..4012, 4013: MODE := 'exact';
+            ELSE
+                MODE := 'high';
+            END_CASE;
+            RETURN;
+            SUM := 0;
+            "#,
+        )
+        .unwrap();
+        assert_eq!(vars.get("N"), Ok(&IecValue::Int(0)));
+        assert_eq!(vars.get("SUM"), Ok(&IecValue::Dint(4013)));
+        assert_eq!(vars.get("MODE"), Ok(&IecValue::String("exact".into())));
+    }
+
+    #[test]
+    fn loops_ending_at_the_end_of_the_range() {
+        let mut vars = variables(&[("I", IecType::Sint), ("COUNT", IecType::Dint)]);
+        run(
+            &mut vars,
+            "FOR I := 120 TO 127 DO COUNT := COUNT + 1; END_FOR;",
+        )
+        .unwrap();
+        assert_eq!(vars.get("COUNT"), Ok(&IecValue::Dint(8)));
+        assert!(compile(&vars, "FOR I := 1 TO 2 BY 0 DO END_FOR;").is_err());
+        run(&mut vars, "FOR I := 1 TO 2 BY I - I DO END_FOR;").unwrap_err();
+    }
+
+    #[test]
+    fn functions() {
+        let mut vars = variables(&[
+            ("X", IecType::Lreal),
+            ("Y", IecType::Int),
+            ("Q", IecType::Bool),
+        ]);
+        run(
+            &mut vars,
+            r#"
+            X := SQRT(16.0) + EXPT(2, 3);
+            Y := LIMIT(MN := 0, IN := 15, MX := 10);
+            Y := MAX(Y, 3, -4) + MUX(1, 5, 6, 7);
+            Q := GT(Y, 10) AND NOT (X < 0.0);
+            "#,
+        )
+        .unwrap();
+        assert_eq!(vars.get("X"), Ok(&IecValue::Lreal(12.0)));
+        assert_eq!(vars.get("Y"), Ok(&IecValue::Int(16)));
+        assert_eq!(vars.get("Q"), Ok(&IecValue::Bool(true)));
+    }
+
+    #[test]
+    fn conditions() {
+        let mut vars = variables(&[("CV", IecType::Uint), ("PV", IecType::Uint)]);
+        vars.set("PV", IecValue::Uint(3)).unwrap();
+        let condition = StCondition::new("cv < pv AND PV <> 0", vars.layout().variables()).unwrap();
+        assert_eq!(condition.evaluate(&vars), Ok(true));
+        vars.set("CV", IecValue::Uint(3)).unwrap();
+        assert_eq!(condition.evaluate(&vars), Ok(false));
+
+        let error = StCondition::new("CV + 1", vars.layout().variables()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1:4: Expected a BOOL condition, not UINT"
+        );
+
+        let other = variables(&[("CV", IecType::Uint)]);
+        assert!(condition.evaluate(&other).is_err());
+    }
+
+    #[test]
+    fn compile_errors() {
+        let vars = variables(&[("X", IecType::Int)]);
+        let error = StAlgorithm::new("X := Y;", vars.layout().variables()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownVariable("Y".into()));
+        assert_eq!(error.to_string(), "1:6: Unknown variable: Y");
+    }
+
+    #[test]
+    fn type_errors() {
+        let vars = variables(&[
+            ("I", IecType::Int),
+            ("D", IecType::Dint),
+            ("S", IecType::String),
+            ("R", IecType::Real),
+            ("T", IecType::Time),
+        ]);
+        let error = |source| compile(&vars, source).unwrap_err().to_string();
+
+        // no implicit narrowing
+        assert_eq!(
+            error("I := D;"),
+            "1:6: Cannot assign a DINT to I of type INT"
+        );
+        assert_eq!(
+            error("I := 1.5;"),
+            "1:6: Cannot assign a LREAL to I of type INT"
+        );
+        assert_eq!(
+            error("I := 100000;"),
+            "1:6: Cannot assign a DINT to I of type INT"
+        );
+        assert_eq!(
+            error("D := I + S;"),
+            "1:8: Invalid operands for +: INT and STRING"
+        );
+        assert_eq!(
+            error("T := T * T;"),
+            "1:8: Invalid operands for *: TIME and TIME"
+        );
+        assert_eq!(error("I := -S;"), "1:6: Invalid operand for -: STRING");
+        assert_eq!(
+            error("IF I THEN END_IF;"),
+            "1:4: Expected a BOOL condition, not INT"
+        );
+        assert_eq!(
+            error("R := SQRT(S);"),
+            "1:6: IN must be ANY_NUM, not STRING"
+        );
+        assert_eq!(error("I := LEN(I);"), "1:6: IN must be ANY_STRING, not INT");
+        assert_eq!(
+            error("I := MAX(I, S);"),
+            "1:6: Incompatible types: INT and STRING"
+        );
+        assert_eq!(
+            error("R := INT_TO_REAL(D);"),
+            "1:6: Expected a INT, not DINT"
+        );
+        assert_eq!(error("D := TRUNC(D);"), "1:6: Cannot truncate a DINT");
+        assert_eq!(
+            error("FOR R := 1 TO 2 DO END_FOR;"),
+            "1:10: The variable of a FOR loop must be an integer, R is a REAL"
+        );
+        assert_eq!(
+            error("CASE I OF 'a': D := 1; END_CASE;"),
+            "1:11: Invalid label for a INT selector: STRING"
+        );
+
+        // constant expressions are evaluated when compiling
+        assert!(compile(&vars, "I := INT#1 + 2 * 3; S := CONCAT('a', 'b');").is_ok());
+    }
+}
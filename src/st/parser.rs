@@ -0,0 +1,833 @@
+//! A recursive descent parser for Structured Text, resolving variables and functions.
+
+use super::ast::{
+    BinaryOp, Body, CaseLabel, Expr, ExprKind, Literal, Position, Statement, UnaryOp, Variable,
+};
+use super::functions::Function;
+use super::lexer::{tokenize, Spanned, Token};
+use super::{CompileError, ErrorKind};
+use crate::runtime::interface::VariableDeclaration;
+use crate::runtime::literal;
+use crate::runtime::value::{IecType, IecValue};
+
+const KEYWORDS: &[&str] = &[
+    "IF",
+    "THEN",
+    "ELSIF",
+    "ELSE",
+    "END_IF",
+    "CASE",
+    "OF",
+    "END_CASE",
+    "FOR",
+    "TO",
+    "BY",
+    "DO",
+    "END_FOR",
+    "WHILE",
+    "END_WHILE",
+    "REPEAT",
+    "UNTIL",
+    "END_REPEAT",
+    "EXIT",
+    "RETURN",
+    "AND",
+    "OR",
+    "XOR",
+    "NOT",
+    "MOD",
+    "TRUE",
+    "FALSE",
+    "VAR",
+    "VAR_TEMP",
+    "END_VAR",
+];
+
+struct Parser<'a> {
+    tokens: Vec<Spanned>,
+    position: usize,
+    variables: &'a [VariableDeclaration],
+    temps: Vec<VariableDeclaration>,
+    loops: usize,
+}
+
+/// Parse the body of an algorithm, operating on the variables of a function block.
+pub fn parse_body(source: &str, variables: &[VariableDeclaration]) -> Result<Body, CompileError> {
+    let mut parser = Parser::new(source, variables)?;
+
+    while parser.is_keyword("VAR_TEMP") || parser.is_keyword("VAR") {
+        parser.advance();
+        parser.declarations()?;
+    }
+
+    let statements = parser.statements(&[])?;
+    parser.expect_end()?;
+
+    Ok(Body {
+        temps: parser.temps,
+        statements,
+    })
+}
+
+/// Parse a single expression, operating on the variables of a function block.
+pub fn parse_expression(
+    source: &str,
+    variables: &[VariableDeclaration],
+) -> Result<Expr, CompileError> {
+    let mut parser = Parser::new(source, variables)?;
+    let expr = parser.expression()?;
+    parser.expect_end()?;
+    Ok(expr)
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &str, variables: &'a [VariableDeclaration]) -> Result<Self, CompileError> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            position: 0,
+            variables,
+            temps: Vec::new(),
+            loops: 0,
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].token
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let index = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].token
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_kind(&self, kind: ErrorKind) -> CompileError {
+        let token = &self.tokens[self.position];
+        CompileError {
+            line: token.line,
+            column: token.column,
+            kind,
+        }
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> CompileError {
+        self.error_kind(ErrorKind::Syntax(message.into()))
+    }
+
+    fn unexpected(&self, expected: &str) -> CompileError {
+        let found = match self.peek() {
+            Token::Identifier(s) | Token::Literal(s) => format!("'{s}'"),
+            Token::Symbol(s) => format!("'{s}'"),
+            Token::End => "end of input".to_string(),
+        };
+        self.error(format!("Expected {expected}, found {found}"))
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Identifier(s) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), CompileError> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), CompileError> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{symbol}'")))
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), CompileError> {
+        match self.peek() {
+            Token::End => Ok(()),
+            _ => Err(self.unexpected("end of input")),
+        }
+    }
+
+    /// An identifier, which must not be a keyword.
+    fn identifier(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Token::Identifier(s) if !is_keyword(s) => {
+                let s = s.clone();
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    /// Declarations of temporary variables, up to `END_VAR`.
+    fn declarations(&mut self) -> Result<(), CompileError> {
+        while !self.accept_keyword("END_VAR") {
+            let mut names = vec![self.identifier()?];
+            while self.accept_symbol(",") {
+                names.push(self.identifier()?);
+            }
+            self.expect_symbol(":")?;
+
+            let type_name = self.identifier()?;
+            let r#type = type_name
+                .to_ascii_uppercase()
+                .parse::<IecType>()
+                .map_err(|_| self.error_kind(ErrorKind::UnknownType(type_name)))?;
+
+            let initial = if self.accept_symbol(":=") {
+                let source = match self.advance() {
+                    Token::Symbol("-") => match self.advance() {
+                        Token::Literal(s) => format!("-{s}"),
+                        _ => return Err(self.unexpected("a literal")),
+                    },
+                    Token::Literal(s) | Token::Identifier(s) => s,
+                    _ => return Err(self.unexpected("a literal")),
+                };
+                Some(
+                    literal::parse_as(&source, r#type)
+                        .map_err(|err| self.error_kind(ErrorKind::InvalidLiteral(err)))?,
+                )
+            } else {
+                None
+            };
+            self.expect_symbol(";")?;
+
+            for name in names {
+                if self.resolve(&name).is_some() {
+                    return Err(self.error(format!("Duplicate variable: {name}")));
+                }
+                self.temps.push(VariableDeclaration {
+                    initial: initial.clone(),
+                    ..VariableDeclaration::new(name, r#type)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, name: &str) -> Option<Variable> {
+        let find = |variables: &[VariableDeclaration], exact: bool| {
+            variables.iter().position(|var| {
+                if exact {
+                    var.name == name
+                } else {
+                    var.name.eq_ignore_ascii_case(name)
+                }
+            })
+        };
+
+        // identifiers are case-insensitive, but prefer an exact match
+        [true, false].into_iter().find_map(|exact| {
+            find(&self.temps, exact)
+                .map(Variable::Temp)
+                .or_else(|| find(self.variables, exact).map(Variable::Block))
+        })
+    }
+
+    fn variable(&mut self) -> Result<Variable, CompileError> {
+        let name = self.identifier()?;
+        self.position -= 1;
+        let variable = self
+            .resolve(&name)
+            .ok_or_else(|| self.error_kind(ErrorKind::UnknownVariable(name)))?;
+        self.advance();
+        Ok(variable)
+    }
+
+    /// Statements, up to one of the terminating keywords.
+    fn statements(&mut self, terminators: &[&str]) -> Result<Vec<Statement>, CompileError> {
+        let mut statements = Vec::new();
+        loop {
+            if *self.peek() == Token::End
+                || terminators.iter().any(|keyword| self.is_keyword(keyword))
+            {
+                return Ok(statements);
+            }
+            if let Some(statement) = self.statement()? {
+                statements.push(statement);
+            }
+        }
+    }
+
+    /// Statements of a `CASE` branch, up to the next label.
+    fn case_statements(&mut self) -> Result<Vec<Statement>, CompileError> {
+        let mut statements = Vec::new();
+        while !self.is_case_label() && !self.is_keyword("ELSE") && !self.is_keyword("END_CASE") {
+            if *self.peek() == Token::End {
+                return Err(self.unexpected("END_CASE"));
+            }
+            if let Some(statement) = self.statement()? {
+                statements.push(statement);
+            }
+        }
+        Ok(statements)
+    }
+
+    fn is_case_label(&self) -> bool {
+        match (self.peek(), self.peek_at(1)) {
+            (Token::Literal(_), _) => true,
+            (Token::Symbol("-"), Token::Literal(_)) => true,
+            (Token::Identifier(s), Token::Symbol(":" | "," | "..")) => !is_keyword(s),
+            _ => false,
+        }
+    }
+
+    /// A statement, or `None` for an empty statement.
+    fn statement(&mut self) -> Result<Option<Statement>, CompileError> {
+        if self.accept_symbol(";") {
+            return Ok(None);
+        }
+
+        let statement = if self.accept_keyword("IF") {
+            self.if_statement()?
+        } else if self.accept_keyword("CASE") {
+            self.case_statement()?
+        } else if self.accept_keyword("FOR") {
+            self.for_statement()?
+        } else if self.accept_keyword("WHILE") {
+            let condition = self.expression()?;
+            self.expect_keyword("DO")?;
+            let body = self.loop_body(&["END_WHILE"])?;
+            self.expect_keyword("END_WHILE")?;
+            Statement::While { condition, body }
+        } else if self.accept_keyword("REPEAT") {
+            let body = self.loop_body(&["UNTIL"])?;
+            self.expect_keyword("UNTIL")?;
+            let until = self.expression()?;
+            self.expect_keyword("END_REPEAT")?;
+            Statement::Repeat { body, until }
+        } else if self.is_keyword("EXIT") {
+            if self.loops == 0 {
+                return Err(self.error("EXIT outside of a loop"));
+            }
+            self.advance();
+            self.expect_symbol(";")?;
+            return Ok(Some(Statement::Exit));
+        } else if self.accept_keyword("RETURN") {
+            self.expect_symbol(";")?;
+            return Ok(Some(Statement::Return));
+        } else {
+            let target = self.variable()?;
+            self.expect_symbol(":=")?;
+            let value = self.expression()?;
+            self.expect_symbol(";")?;
+            return Ok(Some(Statement::Assign { target, value }));
+        };
+
+        // the semicolon after END_IF, END_FOR, ... is commonly omitted
+        self.accept_symbol(";");
+        Ok(Some(statement))
+    }
+
+    fn loop_body(&mut self, terminators: &[&str]) -> Result<Vec<Statement>, CompileError> {
+        self.loops += 1;
+        let body = self.statements(terminators);
+        self.loops -= 1;
+        body
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, CompileError> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.expression()?;
+            self.expect_keyword("THEN")?;
+            let statements = self.statements(&["ELSIF", "ELSE", "END_IF"])?;
+            branches.push((condition, statements));
+            if !self.accept_keyword("ELSIF") {
+                break;
+            }
+        }
+
+        let otherwise = if self.accept_keyword("ELSE") {
+            self.statements(&["END_IF"])?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("END_IF")?;
+
+        Ok(Statement::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn case_statement(&mut self) -> Result<Statement, CompileError> {
+        let selector = self.expression()?;
+        self.expect_keyword("OF")?;
+
+        let mut branches = Vec::new();
+        while self.is_case_label() {
+            let mut labels = Vec::new();
+            loop {
+                let value = self.expression()?;
+                labels.push(if self.accept_symbol("..") {
+                    CaseLabel::Range(value, self.expression()?)
+                } else {
+                    CaseLabel::Value(value)
+                });
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(":")?;
+            branches.push((labels, self.case_statements()?));
+        }
+
+        let otherwise = if self.accept_keyword("ELSE") {
+            self.statements(&["END_CASE"])?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("END_CASE")?;
+
+        Ok(Statement::Case {
+            selector,
+            branches,
+            otherwise,
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<Statement, CompileError> {
+        let variable = self.variable()?;
+        self.expect_symbol(":=")?;
+        let from = self.expression()?;
+        self.expect_keyword("TO")?;
+        let to = self.expression()?;
+        let by = if self.accept_keyword("BY") {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.expect_keyword("DO")?;
+        let body = self.loop_body(&["END_FOR"])?;
+        self.expect_keyword("END_FOR")?;
+
+        Ok(Statement::For {
+            variable,
+            from,
+            to,
+            by,
+            body,
+        })
+    }
+
+    pub fn expression(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    /// Binary operators, by precedence level, lowest first.
+    fn binary_operator(&self, level: usize) -> Option<BinaryOp> {
+        let keyword = |keyword: &str| self.is_keyword(keyword);
+        let symbol = |symbol: &str| self.is_symbol(symbol);
+        match level {
+            0 if keyword("OR") => Some(BinaryOp::Or),
+            1 if keyword("XOR") => Some(BinaryOp::Xor),
+            2 if keyword("AND") || symbol("&") => Some(BinaryOp::And),
+            3 if symbol("=") => Some(BinaryOp::Eq),
+            3 if symbol("<>") => Some(BinaryOp::Ne),
+            4 if symbol("<") => Some(BinaryOp::Lt),
+            4 if symbol(">") => Some(BinaryOp::Gt),
+            4 if symbol("<=") => Some(BinaryOp::Le),
+            4 if symbol(">=") => Some(BinaryOp::Ge),
+            5 if symbol("+") => Some(BinaryOp::Add),
+            5 if symbol("-") => Some(BinaryOp::Sub),
+            6 if symbol("*") => Some(BinaryOp::Mul),
+            6 if symbol("/") => Some(BinaryOp::Div),
+            6 if keyword("MOD") => Some(BinaryOp::Mod),
+            _ => None,
+        }
+    }
+
+    fn current_position(&self) -> Position {
+        let token = &self.tokens[self.position];
+        Position {
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        if level > 6 {
+            return self.unary();
+        }
+
+        let mut expr = self.binary(level + 1)?;
+        while let Some(op) = self.binary_operator(level) {
+            let position = self.current_position();
+            self.advance();
+            let right = self.binary(level + 1)?;
+            expr = Expr {
+                position,
+                kind: ExprKind::Binary(op, Box::new(expr), Box::new(right)),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let position = self.current_position();
+        if self.accept_keyword("NOT") {
+            let kind = ExprKind::Unary(UnaryOp::Not, Box::new(self.unary()?));
+            return Ok(Expr { position, kind });
+        }
+        if self.is_symbol("-") {
+            self.advance();
+            // fold the sign into an untyped literal, unless it is the base of an exponentiation
+            if let (Token::Literal(source), next) = (self.peek(), self.peek_at(1)) {
+                if starts_with_digit(source) && *next != Token::Symbol("**") {
+                    let source = format!("-{source}");
+                    self.advance();
+                    return self.untyped_literal(source, position);
+                }
+            }
+            let kind = ExprKind::Unary(UnaryOp::Neg, Box::new(self.unary()?));
+            return Ok(Expr { position, kind });
+        }
+        if self.accept_symbol("+") {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, CompileError> {
+        let mut expr = self.primary()?;
+        while self.is_symbol("**") {
+            let position = self.current_position();
+            self.advance();
+            let exponent = if self.is_symbol("-") || self.is_keyword("NOT") {
+                self.unary()?
+            } else {
+                self.primary()?
+            };
+            expr = Expr {
+                position,
+                kind: ExprKind::Binary(BinaryOp::Expt, Box::new(expr), Box::new(exponent)),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// An untyped literal, whose last token has just been consumed.
+    fn untyped_literal(
+        &mut self,
+        source: String,
+        position: Position,
+    ) -> Result<Expr, CompileError> {
+        self.position -= 1;
+        let default = literal::parse(&source)
+            .map_err(|err| self.error_kind(ErrorKind::InvalidLiteral(err)))?;
+        self.advance();
+        Ok(Expr {
+            position,
+            kind: ExprKind::Literal(Literal::Untyped { source, default }),
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let position = self.current_position();
+        let typed = |value| Expr {
+            position,
+            kind: ExprKind::Literal(Literal::Typed(value)),
+        };
+
+        match self.peek().clone() {
+            Token::Literal(source) => {
+                self.advance();
+                if starts_with_digit(&source) {
+                    self.untyped_literal(source, position)
+                } else {
+                    self.position -= 1;
+                    let value = literal::parse(&source)
+                        .map_err(|err| self.error_kind(ErrorKind::InvalidLiteral(err)))?;
+                    self.advance();
+                    Ok(typed(value))
+                }
+            }
+            Token::Symbol("(") => {
+                self.advance();
+                let expr = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Token::Identifier(s) if s.eq_ignore_ascii_case("TRUE") => {
+                self.advance();
+                Ok(typed(IecValue::Bool(true)))
+            }
+            Token::Identifier(s) if s.eq_ignore_ascii_case("FALSE") => {
+                self.advance();
+                Ok(typed(IecValue::Bool(false)))
+            }
+            Token::Identifier(name) if !is_keyword(&name) => {
+                if *self.peek_at(1) == Token::Symbol("(") {
+                    self.call(&name)
+                } else {
+                    Ok(Expr {
+                        position,
+                        kind: ExprKind::Variable(self.variable()?),
+                    })
+                }
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, CompileError> {
+        let position = self.current_position();
+        let function = Function::lookup(name)
+            .ok_or_else(|| self.error_kind(ErrorKind::UnknownFunction(name.to_string())))?;
+        self.advance();
+        self.expect_symbol("(")?;
+
+        let mut positional = Vec::new();
+        let mut named: Vec<Option<Expr>> = Vec::new();
+        if !self.is_symbol(")") {
+            loop {
+                if let (Token::Identifier(input), Token::Symbol(":=")) =
+                    (self.peek().clone(), self.peek_at(1))
+                {
+                    let index = function
+                        .input_index(&input)
+                        .ok_or_else(|| self.error(format!("Unknown input of {}: {input}", name)))?;
+                    self.advance();
+                    self.advance();
+                    if named.len() <= index {
+                        named.resize(index + 1, None);
+                    }
+                    if named[index].replace(self.expression()?).is_some() {
+                        return Err(self.error(format!("Duplicate input: {input}")));
+                    }
+                } else {
+                    positional.push(self.expression()?);
+                }
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        if !positional.is_empty() && !named.is_empty() {
+            return Err(self.error("Cannot mix positional and named arguments"));
+        }
+        let args = if named.is_empty() {
+            positional
+        } else {
+            named
+                .into_iter()
+                .enumerate()
+                .map(|(index, arg)| {
+                    arg.ok_or_else(|| {
+                        self.error(format!(
+                            "Missing input: {}",
+                            function.input_name(index).unwrap_or_default()
+                        ))
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        let (min, max) = function.arity();
+        if args.len() < min || max.is_some_and(|max| args.len() > max) {
+            return Err(self.error(format!(
+                "Wrong number of arguments for {}: {}",
+                name.to_ascii_uppercase(),
+                args.len()
+            )));
+        }
+        self.expect_symbol(")")?;
+
+        Ok(Expr {
+            position,
+            kind: ExprKind::Call(function, args),
+        })
+    }
+}
+
+fn is_keyword(s: &str) -> bool {
+    KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(s))
+}
+
+fn starts_with_digit(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn variables() -> Vec<VariableDeclaration> {
+        vec![
+            VariableDeclaration::new("X", IecType::Int),
+            VariableDeclaration::new("Y", IecType::Int),
+            VariableDeclaration::new("Q", IecType::Bool),
+        ]
+    }
+
+    fn body(source: &str) -> Result<Body, CompileError> {
+        parse_body(source, &variables())
+    }
+
+    #[test]
+    fn statements() {
+        let body = body(
+            r#"
+            VAR_TEMP i, j : DINT := 1; END_VAR
+            IF X > 0 THEN Y := 1; ELSIF X < 0 THEN Y := -1; ELSE Y := 0; END_IF;
+            CASE X OF
+                1, 2: Y := 1;
+                3..5, -1: Y := 2; Q := TRUE;
+                ELSE Y := 3;
+            END_CASE
+            FOR i := 1 TO 10 BY 2 DO
+                IF i = 5 THEN EXIT; END_IF;
+            END_FOR;
+            WHILE j < 10 DO j := j + 1; END_WHILE;
+            REPEAT j := j - 1; UNTIL j = 0 END_REPEAT;
+            ;
+            RETURN;
+            "#,
+        )
+        .unwrap();
+
+        let temps = body
+            .temps
+            .iter()
+            .map(VariableDeclaration::initial_value)
+            .collect::<Vec<_>>();
+        assert_eq!(temps, vec![IecValue::Dint(1), IecValue::Dint(1)]);
+        assert_eq!(body.statements.len(), 6);
+        match &body.statements[1] {
+            Statement::Case {
+                branches,
+                otherwise,
+                ..
+            } => {
+                assert_eq!(branches.len(), 2);
+                assert_eq!(branches[1].0.len(), 2);
+                assert_eq!(branches[1].1.len(), 2);
+                assert_eq!(otherwise.len(), 1);
+            }
+            statement => panic!("Unexpected statement: {statement:?}"),
+        }
+        assert!(matches!(
+            body.statements[2],
+            Statement::For {
+                variable: Variable::Temp(0),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn precedence() {
+        let variables = variables();
+        let expr = parse_expression("x + Y * 2 > 3 AND NOT q OR -2 ** 2 = 4", &variables).unwrap();
+        // ((X + (Y * 2)) > 3 AND NOT Q) OR ((-(2 ** 2)) = 4)
+        assert_eq!(
+            expr.position,
+            Position {
+                line: 1,
+                column: 25
+            }
+        );
+        let ExprKind::Binary(BinaryOp::Or, left, right) = expr.kind else {
+            panic!("Expected OR: {expr:?}");
+        };
+        let ExprKind::Binary(BinaryOp::And, comparison, not) = left.kind else {
+            panic!("Expected AND");
+        };
+        assert!(matches!(not.kind, ExprKind::Unary(UnaryOp::Not, _)));
+        let ExprKind::Binary(BinaryOp::Gt, sum, _) = comparison.kind else {
+            panic!("Expected >");
+        };
+        assert!(matches!(
+            sum.kind,
+            ExprKind::Binary(BinaryOp::Add, _, ref product)
+                if matches!(product.kind, ExprKind::Binary(BinaryOp::Mul, _, _))
+        ));
+        let ExprKind::Binary(BinaryOp::Eq, negation, _) = right.kind else {
+            panic!("Expected =");
+        };
+        assert!(matches!(negation.kind, ExprKind::Unary(UnaryOp::Neg, _)));
+    }
+
+    #[test]
+    fn calls() {
+        let variables = variables();
+        let expr = parse_expression("LIMIT(MX := 10, IN := X, MN := 0)", &variables).unwrap();
+        let ExprKind::Call(_, args) = expr.kind else {
+            panic!("Expected a call");
+        };
+        assert!(matches!(
+            args[1].kind,
+            ExprKind::Variable(Variable::Block(0))
+        ));
+
+        assert!(parse_expression("max(1, 2, 3, X)", &variables).is_ok());
+        assert!(parse_expression("INT_TO_REAL(X)", &variables).is_ok());
+        assert!(parse_expression("LIMIT(0, X)", &variables).is_err());
+        assert!(parse_expression("LIMIT(MN := 0, X, MX := 1)", &variables).is_err());
+        assert!(parse_expression("LIMIT(MN := 0, IN := X)", &variables).is_err());
+        assert!(matches!(
+            parse_expression("FOO(X)", &variables),
+            Err(CompileError {
+                kind: ErrorKind::UnknownFunction(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let error = body("X := 1;\nZ := 2;").unwrap_err();
+        assert_eq!(
+            error,
+            CompileError {
+                line: 2,
+                column: 1,
+                kind: ErrorKind::UnknownVariable("Z".into())
+            }
+        );
+
+        assert!(body("X := 1").is_err());
+        assert!(body("IF Q THEN X := 1;").is_err());
+        assert!(body("EXIT;").is_err());
+        assert!(body("X := INT#'a';").is_err());
+        assert!(body("VAR_TEMP X : INT; END_VAR").is_err());
+        assert!(body("VAR_TEMP T : FOO; END_VAR").is_err());
+        assert!(body("IF := 1;").is_err());
+        assert!(parse_expression("X +", &variables()).is_err());
+        assert!(parse_expression("X Y", &variables()).is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! A register machine executing compiled Structured Text.
+//!
+//! Programs operate on the variables of the function block, on registers holding temporary
+//! variables and intermediate results, and on constants. All types have been checked by the
+//! compiler, so the machine only fails on errors which depend on values, like a division by
+//! zero.
+
+use super::ast::{BinaryOp, UnaryOp};
+use super::functions::{self, Function};
+use crate::runtime::basic::{ExecutionError, Variables};
+use crate::runtime::value::{self, IecType, IecValue};
+use std::cmp::Ordering;
+
+/// Where an instruction reads a value from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// A variable of the function block, by its index in the layout.
+    Var(usize),
+    Reg(usize),
+    Const(usize),
+}
+
+/// Where an instruction writes its result to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Place {
+    Var(usize),
+    Reg(usize),
+}
+
+impl From<Place> for Operand {
+    fn from(place: Place) -> Self {
+        match place {
+            Place::Var(index) => Self::Var(index),
+            Place::Reg(index) => Self::Reg(index),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Instruction {
+    Move {
+        dst: Place,
+        src: Operand,
+    },
+    Convert {
+        dst: Place,
+        src: Operand,
+        r#type: IecType,
+    },
+    Unary {
+        op: UnaryOp,
+        dst: Place,
+        src: Operand,
+    },
+    Binary {
+        op: BinaryOp,
+        dst: Place,
+        a: Operand,
+        b: Operand,
+    },
+    /// Call a function, converting its result to the type the compiler determined.
+    Call {
+        function: Function,
+        dst: Place,
+        args: Box<[Operand]>,
+        r#type: IecType,
+    },
+    Jump(usize),
+    JumpIf {
+        condition: Operand,
+        value: bool,
+        target: usize,
+    },
+    /// Jump to the target if the variable of a `FOR` loop is past its end value.
+    ForTest {
+        var: Place,
+        to: Operand,
+        step: Operand,
+        target: usize,
+    },
+    /// Advance the variable of a `FOR` loop and jump to the target, unless the variable would
+    /// wrap around at the end of the range of its type.
+    ForNext {
+        var: Place,
+        step: Operand,
+        target: usize,
+    },
+    Return,
+}
+
+impl Instruction {
+    /// The place the instruction writes to, if any.
+    pub fn dst_mut(&mut self) -> Option<&mut Place> {
+        match self {
+            Self::Move { dst, .. }
+            | Self::Convert { dst, .. }
+            | Self::Unary { dst, .. }
+            | Self::Binary { dst, .. }
+            | Self::Call { dst, .. } => Some(dst),
+            _ => None,
+        }
+    }
+}
+
+/// Compiled code, along with its constants and the initial values of its registers.
+#[derive(Clone, Debug, Default)]
+pub struct Program {
+    pub code: Vec<Instruction>,
+    pub constants: Vec<IecValue>,
+    pub registers: Vec<IecValue>,
+    /// The result of an expression, if the program evaluates one.
+    pub result: Option<Operand>,
+}
+
+/// The variables a program operates on.
+pub trait Memory {
+    fn get(&self, index: usize) -> &IecValue;
+    fn set(&mut self, index: usize, value: IecValue);
+}
+
+impl Memory for Variables {
+    fn get(&self, index: usize) -> &IecValue {
+        self.get_at(index)
+    }
+
+    fn set(&mut self, index: usize, value: IecValue) {
+        let result = self.set_at(index, value);
+        debug_assert!(
+            result.is_ok(),
+            "the compiler checks the types of assignments"
+        );
+    }
+}
+
+/// Read-only access to the variables, for evaluating conditions.
+pub struct ReadOnly<'a>(pub &'a Variables);
+
+impl Memory for ReadOnly<'_> {
+    fn get(&self, index: usize) -> &IecValue {
+        self.0.get_at(index)
+    }
+
+    fn set(&mut self, _index: usize, _value: IecValue) {
+        unreachable!("expressions don't assign variables")
+    }
+}
+
+struct Machine<'a, M: Memory> {
+    program: &'a Program,
+    memory: &'a mut M,
+    registers: Vec<IecValue>,
+}
+
+impl<M: Memory> Machine<'_, M> {
+    fn read(&self, operand: Operand) -> &IecValue {
+        match operand {
+            Operand::Var(index) => self.memory.get(index),
+            Operand::Reg(index) => &self.registers[index],
+            Operand::Const(index) => &self.program.constants[index],
+        }
+    }
+
+    fn write(&mut self, place: Place, value: IecValue) {
+        match place {
+            Place::Var(index) => self.memory.set(index, value),
+            Place::Reg(index) => self.registers[index] = value,
+        }
+    }
+
+    fn condition(&self, operand: Operand) -> bool {
+        matches!(self.read(operand), IecValue::Bool(true))
+    }
+
+    fn run(&mut self) -> Result<(), ExecutionError> {
+        let program = self.program;
+        let code = &program.code;
+        let mut pc = 0;
+        while let Some(instruction) = code.get(pc) {
+            pc += 1;
+            match instruction {
+                Instruction::Move { dst, src } => {
+                    let value = self.read(*src).clone();
+                    self.write(*dst, value);
+                }
+                Instruction::Convert { dst, src, r#type } => {
+                    let value = value::convert(self.read(*src), *r#type)?;
+                    self.write(*dst, value);
+                }
+                Instruction::Unary { op, dst, src } => {
+                    let value = unary(*op, self.read(*src))?;
+                    self.write(*dst, value);
+                }
+                Instruction::Binary { op, dst, a, b } => {
+                    let value = binary(*op, self.read(*a), self.read(*b))?;
+                    self.write(*dst, value);
+                }
+                Instruction::Call {
+                    function,
+                    dst,
+                    args,
+                    r#type,
+                } => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.read(*arg).clone())
+                        .collect::<Vec<_>>();
+                    let value = call(function, &args, *r#type)?;
+                    self.write(*dst, value);
+                }
+                Instruction::Jump(target) => pc = *target,
+                Instruction::JumpIf {
+                    condition,
+                    value,
+                    target,
+                } => {
+                    if self.condition(*condition) == *value {
+                        pc = *target;
+                    }
+                }
+                Instruction::ForTest {
+                    var,
+                    to,
+                    step,
+                    target,
+                } => {
+                    let done = direction(self.read(*step))?;
+                    if compare(self.read((*var).into()), self.read(*to))? == Some(done) {
+                        pc = *target;
+                    }
+                }
+                Instruction::ForNext { var, step, target } => {
+                    let current = self.read((*var).into());
+                    let step = self.read(*step);
+                    let next = binary(BinaryOp::Add, current, step)?;
+                    // stop instead of wrapping around at the end of the range of the type
+                    if compare(&next, current)? == Some(direction(step)?) {
+                        self.write(*var, next);
+                        pc = *target;
+                    }
+                }
+                Instruction::Return => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Execute a program, returning the value of its result, if it has one.
+pub fn execute<M: Memory>(
+    program: &Program,
+    memory: &mut M,
+) -> Result<Option<IecValue>, ExecutionError> {
+    let mut machine = Machine {
+        program,
+        memory,
+        registers: program.registers.clone(),
+    };
+    machine.run()?;
+    Ok(program.result.map(|result| machine.read(result).clone()))
+}
+
+/// The direction of the step of a `FOR` loop.
+fn direction(step: &IecValue) -> Result<Ordering, ExecutionError> {
+    match compare(step, &step.r#type().default_value())? {
+        Some(Ordering::Equal) | None => Err(ExecutionError::Failed(
+            "The increment of a FOR loop must not be zero".to_string(),
+        )),
+        Some(ordering) => Ok(ordering),
+    }
+}
+
+pub fn unary(op: UnaryOp, value: &IecValue) -> Result<IecValue, ExecutionError> {
+    match (op, value) {
+        (UnaryOp::Not, IecValue::Bool(value)) => Ok(IecValue::Bool(!value)),
+        (UnaryOp::Not, value) => functions::not(value),
+        (UnaryOp::Neg, value) => functions::neg(value),
+    }
+}
+
+pub fn call(
+    function: &Function,
+    args: &[IecValue],
+    r#type: IecType,
+) -> Result<IecValue, ExecutionError> {
+    match function.call(args)? {
+        value if value.r#type() == r#type => Ok(value),
+        value => Ok(value::convert(&value, r#type)?),
+    }
+}
+
+fn division_by_zero() -> ExecutionError {
+    ExecutionError::Failed("Division by zero".to_string())
+}
+
+/// Apply an operation to operands of the same numeric type, `None` for other operands.
+macro_rules! numeric {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $int:expr) => {
+        match ($a, $b) {
+            (IecValue::Sint($x), IecValue::Sint($y)) => Some(IecValue::Sint($int)),
+            (IecValue::Int($x), IecValue::Int($y)) => Some(IecValue::Int($int)),
+            (IecValue::Dint($x), IecValue::Dint($y)) => Some(IecValue::Dint($int)),
+            (IecValue::Lint($x), IecValue::Lint($y)) => Some(IecValue::Lint($int)),
+            (IecValue::Usint($x), IecValue::Usint($y)) => Some(IecValue::Usint($int)),
+            (IecValue::Uint($x), IecValue::Uint($y)) => Some(IecValue::Uint($int)),
+            (IecValue::Udint($x), IecValue::Udint($y)) => Some(IecValue::Udint($int)),
+            (IecValue::Ulint($x), IecValue::Ulint($y)) => Some(IecValue::Ulint($int)),
+            _ => None,
+        }
+    };
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $int:expr, $real:expr) => {
+        match ($a, $b) {
+            (IecValue::Real($x), IecValue::Real($y)) => Some(IecValue::Real($real)),
+            (IecValue::Lreal($x), IecValue::Lreal($y)) => Some(IecValue::Lreal($real)),
+            (a, b) => numeric!(a, b, |$x, $y| $int),
+        }
+    };
+}
+
+/// Compare values, with a shortcut for operands of the same numeric type.
+fn compare(a: &IecValue, b: &IecValue) -> Result<Option<Ordering>, ExecutionError> {
+    let ordering = match (a, b) {
+        (IecValue::Sint(a), IecValue::Sint(b)) => a.partial_cmp(b),
+        (IecValue::Int(a), IecValue::Int(b)) => a.partial_cmp(b),
+        (IecValue::Dint(a), IecValue::Dint(b)) => a.partial_cmp(b),
+        (IecValue::Lint(a), IecValue::Lint(b)) => a.partial_cmp(b),
+        (IecValue::Usint(a), IecValue::Usint(b)) => a.partial_cmp(b),
+        (IecValue::Uint(a), IecValue::Uint(b)) => a.partial_cmp(b),
+        (IecValue::Udint(a), IecValue::Udint(b)) => a.partial_cmp(b),
+        (IecValue::Ulint(a), IecValue::Ulint(b)) => a.partial_cmp(b),
+        (IecValue::Real(a), IecValue::Real(b)) => a.partial_cmp(b),
+        (IecValue::Lreal(a), IecValue::Lreal(b)) => a.partial_cmp(b),
+        (a, b) => return functions::compare(a, b),
+    };
+    Ok(ordering)
+}
+
+pub fn binary(op: BinaryOp, a: &IecValue, b: &IecValue) -> Result<IecValue, ExecutionError> {
+    let fast = match op {
+        BinaryOp::Add => numeric!(a, b, |x, y| x.wrapping_add(*y), x + y),
+        BinaryOp::Sub => numeric!(a, b, |x, y| x.wrapping_sub(*y), x - y),
+        BinaryOp::Mul => numeric!(a, b, |x, y| x.wrapping_mul(*y), x * y),
+        BinaryOp::Div => numeric!(
+            a,
+            b,
+            |x, y| match *y {
+                0 => return Err(division_by_zero()),
+                y => x.wrapping_div(y),
+            },
+            x / y
+        ),
+        BinaryOp::Mod => numeric!(a, b, |x, y| match *y {
+            0 => 0,
+            y => x.wrapping_rem(y),
+        }),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => match (a, b) {
+            (IecValue::Bool(a), IecValue::Bool(b)) => Some(IecValue::Bool(match op {
+                BinaryOp::And => *a && *b,
+                BinaryOp::Or => *a || *b,
+                _ => a != b,
+            })),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(value) = fast {
+        return Ok(value);
+    }
+
+    let check = |check: fn(Ordering) -> bool| {
+        compare(a, b).map(|ordering| IecValue::Bool(ordering.is_some_and(check)))
+    };
+    match op {
+        BinaryOp::Or => functions::or(a, b),
+        BinaryOp::Xor => functions::xor(a, b),
+        BinaryOp::And => functions::and(a, b),
+        BinaryOp::Eq => check(|o| o == Ordering::Equal),
+        BinaryOp::Ne => {
+            compare(a, b).map(|ordering| IecValue::Bool(ordering != Some(Ordering::Equal)))
+        }
+        BinaryOp::Lt => check(|o| o == Ordering::Less),
+        BinaryOp::Gt => check(|o| o == Ordering::Greater),
+        BinaryOp::Le => check(|o| o != Ordering::Greater),
+        BinaryOp::Ge => check(|o| o != Ordering::Less),
+        BinaryOp::Add => functions::add(a, b),
+        BinaryOp::Sub => functions::sub(a, b),
+        BinaryOp::Mul => functions::mul(a, b),
+        BinaryOp::Div => functions::div(a, b),
+        BinaryOp::Mod => functions::modulo(a, b),
+        BinaryOp::Expt => functions::expt(a, b),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Cells(Vec<IecValue>);
+
+    impl Memory for Cells {
+        fn get(&self, index: usize) -> &IecValue {
+            &self.0[index]
+        }
+
+        fn set(&mut self, index: usize, value: IecValue) {
+            self.0[index] = value;
+        }
+    }
+
+    #[test]
+    fn fast_paths_match_the_operators() {
+        let values = [
+            (IecValue::Int(i16::MAX), IecValue::Int(2)),
+            (IecValue::Usint(3), IecValue::Usint(250)),
+            (IecValue::Dint(i32::MIN), IecValue::Dint(-1)),
+            (IecValue::Lreal(1.5), IecValue::Lreal(-0.5)),
+        ];
+        let operators = [
+            (BinaryOp::Add, functions::add as fn(&_, &_) -> _),
+            (BinaryOp::Sub, functions::sub),
+            (BinaryOp::Mul, functions::mul),
+            (BinaryOp::Div, functions::div),
+            (BinaryOp::Mod, functions::modulo),
+        ];
+        for (a, b) in &values {
+            for (op, function) in operators {
+                assert_eq!(binary(op, a, b), function(a, b), "{a} {op:?} {b}");
+            }
+            assert_eq!(compare(a, b), functions::compare(a, b));
+        }
+
+        let nan = IecValue::Real(f32::NAN);
+        assert_eq!(compare(&nan, &IecValue::Real(1.0)), Ok(None));
+        assert!(binary(BinaryOp::Div, &IecValue::Int(1), &IecValue::Int(0)).is_err());
+        assert_eq!(
+            binary(BinaryOp::Xor, &IecValue::Bool(true), &IecValue::Bool(false)),
+            Ok(IecValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn loops() {
+        // SUM := 0; FOR I := 1 TO 10 BY STEP DO SUM := SUM + I; END_FOR;
+        let program = Program {
+            code: vec![
+                Instruction::Move {
+                    dst: Place::Var(0),
+                    src: Operand::Const(0),
+                },
+                Instruction::ForTest {
+                    var: Place::Var(0),
+                    to: Operand::Const(1),
+                    step: Operand::Var(2),
+                    target: 4,
+                },
+                Instruction::Binary {
+                    op: BinaryOp::Add,
+                    dst: Place::Var(1),
+                    a: Operand::Var(1),
+                    b: Operand::Var(0),
+                },
+                Instruction::ForNext {
+                    var: Place::Var(0),
+                    step: Operand::Var(2),
+                    target: 1,
+                },
+            ],
+            constants: vec![IecValue::Int(1), IecValue::Int(10)],
+            ..Default::default()
+        };
+
+        let mut cells = Cells(vec![IecValue::Int(0), IecValue::Int(0), IecValue::Int(3)]);
+        execute(&program, &mut cells).unwrap();
+        assert_eq!(cells.0[1], IecValue::Int(1 + 4 + 7 + 10));
+
+        let mut cells = Cells(vec![IecValue::Int(0), IecValue::Int(0), IecValue::Int(0)]);
+        assert!(execute(&program, &mut cells).is_err());
+    }
+}
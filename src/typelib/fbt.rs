@@ -0,0 +1,205 @@
+//! The XML model of type definitions (`.fbt`, `.adp` and `.dtp` files), as defined by
+//! IEC 61499-2 and exported by 4diac IDE.
+//!
+//! Only the parts required to instantiate a type are modelled, everything else (comments,
+//! identification, version info, graphical positions, ...) is ignored.
+
+/// The `FBType` element.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FbType {
+    pub name: String,
+    #[serde(default)]
+    pub interface_list: InterfaceList,
+    #[serde(default, rename = "BasicFB", skip_serializing_if = "Option::is_none")]
+    pub basic: Option<BasicFb>,
+    #[serde(default, rename = "FBNetwork", skip_serializing_if = "Option::is_none")]
+    pub network: Option<FbNetwork>,
+}
+
+/// The `AdapterType` element.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AdapterType {
+    pub name: String,
+    #[serde(default)]
+    pub interface_list: InterfaceList,
+}
+
+/// The `DataType` element.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataType {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InterfaceList {
+    #[serde(default)]
+    pub event_inputs: Events,
+    #[serde(default)]
+    pub event_outputs: Events,
+    #[serde(default)]
+    pub input_vars: Variables,
+    #[serde(default)]
+    pub output_vars: Variables,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Events {
+    #[serde(default, rename = "Event")]
+    pub events: Vec<Event>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Event {
+    pub name: String,
+    #[serde(default)]
+    pub with: Vec<With>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct With {
+    pub var: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Variables {
+    #[serde(default, rename = "VarDeclaration")]
+    pub variables: Vec<VarDeclaration>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VarDeclaration {
+    pub name: String,
+    pub r#type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_value: Option<String>,
+}
+
+/// The `BasicFB` element, the internals of a basic function block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BasicFb {
+    #[serde(default)]
+    pub internal_vars: Variables,
+    #[serde(rename = "ECC")]
+    pub ecc: Ecc,
+    #[serde(default, rename = "Algorithm")]
+    pub algorithms: Vec<Algorithm>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Ecc {
+    #[serde(default, rename = "ECState")]
+    pub states: Vec<EcState>,
+    #[serde(default, rename = "ECTransition")]
+    pub transitions: Vec<EcTransition>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EcState {
+    pub name: String,
+    #[serde(default, rename = "ECAction")]
+    pub actions: Vec<EcAction>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EcAction {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EcTransition {
+    pub source: String,
+    pub destination: String,
+    /// An event, an event followed by a guard condition in brackets (`EI[X > 1]`), a guard
+    /// condition only, or `1` for a transition which is always taken.
+    pub condition: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Algorithm {
+    pub name: String,
+    #[serde(default, rename = "ST", skip_serializing_if = "Option::is_none")]
+    pub st: Option<StructuredText>,
+}
+
+impl Algorithm {
+    /// The Structured Text source of the algorithm, if it is written in Structured Text.
+    pub fn st(&self) -> Option<&str> {
+        self.st.as_ref().map(StructuredText::text)
+    }
+}
+
+/// Structured Text, either in the `Text` attribute (older exports) or as the content.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StructuredText {
+    #[serde(default, rename = "Text", skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, rename = "$value", skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+impl StructuredText {
+    pub fn text(&self) -> &str {
+        self.text
+            .as_deref()
+            .or(self.content.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// The `FBNetwork` element, the internals of a composite function block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FbNetwork {
+    #[serde(default, rename = "FB")]
+    pub function_blocks: Vec<Fb>,
+    #[serde(default)]
+    pub event_connections: Connections,
+    #[serde(default)]
+    pub data_connections: Connections,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Fb {
+    pub name: String,
+    pub r#type: String,
+    #[serde(default, rename = "Parameter")]
+    pub parameters: Vec<Parameter>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Parameter {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Connections {
+    #[serde(default, rename = "Connection")]
+    pub connections: Vec<Connection>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Connection {
+    pub source: String,
+    pub destination: String,
+}
@@ -0,0 +1,607 @@
+//! Function block types, loaded from IEC 61499-2 type definitions.
+//!
+//! Basic and composite function block types can be loaded from `.fbt` files, as exported by
+//! 4diac IDE, and registered with a [`StandardFactory`]:
+//!
+//! ```no_run
+//! use toref::runtime::factory::StandardFactory;
+//!
+//! let mut factory = StandardFactory::new();
+//! factory.register_standard_types();
+//! toref::typelib::load_directory(&mut factory, "types").unwrap();
+//! ```
+
+pub mod fbt;
+
+use crate::runtime::basic::{Action, BasicType, Guard, TypeError};
+use crate::runtime::composite::CompositeType;
+use crate::runtime::factory::StandardFactory;
+use crate::runtime::interface::{EventDeclaration, Interface, VariableDeclaration};
+use crate::runtime::literal::{self, LiteralError};
+use crate::runtime::value::{AnyType, IecType, UnknownType};
+use crate::st::{CompileError, StAlgorithm, StCondition};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse type definition: {0}")]
+    Xml(#[from] quick_xml::DeError),
+    #[error("Invalid type: {0}")]
+    InvalidType(#[from] TypeError),
+    #[error(transparent)]
+    UnknownType(#[from] UnknownType),
+    #[error("Invalid initial value of {name}: {error}")]
+    InvalidValue { name: String, error: LiteralError },
+    #[error("Invalid Structured Text in {name}: {error}")]
+    InvalidCode { name: String, error: CompileError },
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+}
+
+/// Parse a function block type definition.
+pub fn parse(xml: &str) -> Result<fbt::FbType, LoadError> {
+    Ok(quick_xml::de::from_str(xml)?)
+}
+
+/// Register a function block type definition with the factory, replacing any type of the
+/// same name.
+pub fn register(factory: &mut StandardFactory, r#type: &fbt::FbType) -> Result<(), LoadError> {
+    if let Some(basic) = &r#type.basic {
+        factory.register_basic_type(basic_type(r#type, basic)?);
+    } else if let Some(network) = &r#type.network {
+        factory.register_composite_type(composite_type(r#type, network)?);
+    } else {
+        return Err(LoadError::Unsupported(format!(
+            "{} is neither a basic nor a composite function block type",
+            r#type.name
+        )));
+    }
+
+    log::info!("Registered type: {}", r#type.name);
+
+    Ok(())
+}
+
+/// Load and register all `.fbt` files in a directory, and its subdirectories.
+///
+/// Type definitions which fail to load are skipped with a warning. Returns the names of the
+/// registered types.
+pub fn load_directory<P: AsRef<Path>>(
+    factory: &mut StandardFactory,
+    path: P,
+) -> Result<Vec<String>, LoadError> {
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_unstable();
+
+    let mut result = vec![];
+    for path in entries {
+        if path.is_dir() {
+            result.extend(load_directory(factory, &path)?);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("fbt"))
+        {
+            match std::fs::read_to_string(&path)
+                .map_err(LoadError::from)
+                .and_then(|xml| parse(&xml))
+                .and_then(|r#type| register(factory, &r#type).map(|_| r#type.name))
+            {
+                Ok(name) => result.push(name),
+                Err(err) => log::warn!("Failed to load {}: {err}", path.display()),
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn declaration(var: &fbt::VarDeclaration) -> Result<VariableDeclaration, LoadError> {
+    if var
+        .array_size
+        .as_deref()
+        .is_some_and(|size| !size.is_empty())
+    {
+        return Err(LoadError::Unsupported(format!(
+            "Array variable {}",
+            var.name
+        )));
+    }
+
+    let declaration = match var.r#type.parse::<AnyType>() {
+        Ok(generic) => VariableDeclaration::generic(var.name.clone(), generic),
+        Err(_) => VariableDeclaration::new(var.name.clone(), var.r#type.parse::<IecType>()?),
+    };
+    let r#type = declaration.r#type;
+
+    match var.initial_value.as_deref().map(str::trim) {
+        None | Some("") => Ok(declaration),
+        Some(value) => literal::parse_as(value, r#type)
+            .map(|value| declaration.with_initial(value))
+            .map_err(|error| LoadError::InvalidValue {
+                name: var.name.clone(),
+                error,
+            }),
+    }
+}
+
+fn events(events: &fbt::Events) -> impl Iterator<Item = (&str, Vec<&str>)> {
+    events.events.iter().map(|event| {
+        (
+            event.name.as_str(),
+            event.with.iter().map(|with| with.var.as_str()).collect(),
+        )
+    })
+}
+
+/// Convert the definition of a basic function block type.
+pub fn basic_type(r#type: &fbt::FbType, basic: &fbt::BasicFb) -> Result<BasicType, LoadError> {
+    let interface = &r#type.interface_list;
+    let mut builder = BasicType::builder(r#type.name.clone());
+
+    for (name, with) in events(&interface.event_inputs) {
+        builder = builder.event_input(name, &with);
+    }
+    for (name, with) in events(&interface.event_outputs) {
+        builder = builder.event_output(name, &with);
+    }
+    for var in &interface.input_vars.variables {
+        builder = builder.input_declaration(declaration(var)?);
+    }
+    for var in &interface.output_vars.variables {
+        builder = builder.output_declaration(declaration(var)?);
+    }
+    for var in &basic.internal_vars.variables {
+        builder = builder.internal_declaration(declaration(var)?);
+    }
+
+    for algorithm in &basic.algorithms {
+        let Some(text) = algorithm.st() else {
+            return Err(LoadError::Unsupported(format!(
+                "Algorithm {} is not written in Structured Text",
+                algorithm.name
+            )));
+        };
+        let compiled = StAlgorithm::new(text, builder.variables()).map_err(|error| {
+            LoadError::InvalidCode {
+                name: algorithm.name.clone(),
+                error,
+            }
+        })?;
+        builder = builder.algorithm_impl(algorithm.name.clone(), Arc::new(compiled));
+    }
+
+    for state in &basic.ecc.states {
+        builder = builder.state(
+            state.name.clone(),
+            state.actions.iter().map(|action| Action {
+                algorithm: action.algorithm.clone().filter(|name| !name.is_empty()),
+                output: action.output.clone().filter(|name| !name.is_empty()),
+            }),
+        );
+    }
+
+    for transition in &basic.ecc.transitions {
+        let guard = guard(&transition.condition, interface, builder.variables())?;
+        builder = builder.transition(
+            transition.source.clone(),
+            transition.destination.clone(),
+            guard,
+        );
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Parse the condition of an ECC transition.
+fn guard<'a, I>(
+    condition: &str,
+    interface: &fbt::InterfaceList,
+    variables: I,
+) -> Result<Guard, LoadError>
+where
+    I: IntoIterator<Item = &'a VariableDeclaration>,
+{
+    let is_event = |name: &str| {
+        interface
+            .event_inputs
+            .events
+            .iter()
+            .any(|event| event.name == name)
+    };
+
+    let condition = condition.trim();
+    let (guard, expression) = match condition
+        .strip_suffix(']')
+        .and_then(|condition| condition.split_once('['))
+    {
+        Some((event, expression)) if is_event(event.trim()) => {
+            (Guard::event(event.trim()), expression.trim())
+        }
+        _ if is_event(condition) => (Guard::event(condition), ""),
+        _ => (Guard::always(), condition),
+    };
+
+    Ok(match expression {
+        "" | "1" | "TRUE" => guard,
+        expression => {
+            let compiled = StCondition::new(expression, variables).map_err(|error| {
+                LoadError::InvalidCode {
+                    name: condition.to_string(),
+                    error,
+                }
+            })?;
+            guard.with_condition(Arc::new(compiled))
+        }
+    })
+}
+
+/// Convert the definition of a composite function block type.
+pub fn composite_type(
+    r#type: &fbt::FbType,
+    network: &fbt::FbNetwork,
+) -> Result<CompositeType, LoadError> {
+    let interface = &r#type.interface_list;
+    let mut builder = CompositeType::builder(r#type.name.clone());
+
+    for (name, with) in events(&interface.event_inputs) {
+        builder = builder.event_input(name, &with);
+    }
+    for (name, with) in events(&interface.event_outputs) {
+        builder = builder.event_output(name, &with);
+    }
+    for var in &interface.input_vars.variables {
+        builder = builder.input_declaration(declaration(var)?);
+    }
+    for var in &interface.output_vars.variables {
+        builder = builder.output_declaration(declaration(var)?);
+    }
+
+    for fb in &network.function_blocks {
+        builder = builder.child(fb.name.clone(), fb.r#type.clone());
+        for parameter in &fb.parameters {
+            builder = builder.parameter(
+                format!("{}.{}", fb.name, parameter.name),
+                parameter.value.clone(),
+            );
+        }
+    }
+
+    for connection in network
+        .event_connections
+        .connections
+        .iter()
+        .chain(&network.data_connections.connections)
+    {
+        builder = builder.connection(connection.source.clone(), connection.destination.clone());
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Describe an interface the way type definitions do, for reporting it to 4diac IDE.
+pub fn interface_list(interface: &Interface) -> fbt::InterfaceList {
+    let events = |events: &[EventDeclaration]| fbt::Events {
+        events: events
+            .iter()
+            .map(|event| fbt::Event {
+                name: event.name.clone(),
+                with: event
+                    .with
+                    .iter()
+                    .map(|var| fbt::With { var: var.clone() })
+                    .collect(),
+            })
+            .collect(),
+    };
+    let variables = |variables: &[VariableDeclaration]| fbt::Variables {
+        variables: variables
+            .iter()
+            .map(|var| fbt::VarDeclaration {
+                name: var.name.clone(),
+                r#type: var
+                    .generic
+                    .map_or(var.r#type.name(), |generic| generic.name())
+                    .to_string(),
+                array_size: var.array_size.map(|size| size.to_string()),
+                initial_value: var.initial.as_ref().map(ToString::to_string),
+            })
+            .collect(),
+    };
+
+    fbt::InterfaceList {
+        event_inputs: events(&interface.event_inputs),
+        event_outputs: events(&interface.event_outputs),
+        input_vars: variables(&interface.data_inputs),
+        output_vars: variables(&interface.data_outputs),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::container::{Container, SimpleContainer, WriteError};
+    use crate::runtime::factory::{CreationError, FunctionBlockFactory};
+    use crate::runtime::fb::EventContext;
+    use crate::runtime::value::IecValue;
+
+    const E_SPLIT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE FBType SYSTEM "http://www.holobloc.com/xml/LibraryElement.dtd">
+<FBType Name="E_SPLIT" Comment="Split an event">
+  <Identification Standard="61499-1" />
+  <VersionInfo Organization="4DIAC" Version="1.0" Author="AZ" Date="2008-08-28" />
+  <InterfaceList>
+    <EventInputs>
+      <Event Name="EI" Comment="Input event" />
+    </EventInputs>
+    <EventOutputs>
+      <Event Name="EO1" Comment="First output event" />
+      <Event Name="EO2" Comment="Second output event" />
+    </EventOutputs>
+  </InterfaceList>
+  <BasicFB>
+    <ECC>
+      <ECState Name="START" Comment="Initial State" x="550.0" y="425.0" />
+      <ECState Name="State" x="1045.0" y="550.0">
+        <ECAction Output="EO1" />
+        <ECAction Output="EO2" />
+      </ECState>
+      <ECTransition Source="START" Destination="State" Condition="EI" x="820.0" y="470.0" />
+      <ECTransition Source="State" Destination="START" Condition="1" x="830.0" y="565.0" />
+    </ECC>
+  </BasicFB>
+</FBType>
+"#;
+
+    const E_CTU: &str = r#"<FBType Name="E_CTU">
+  <InterfaceList>
+    <EventInputs>
+      <Event Name="CU"><With Var="PV" /></Event>
+      <Event Name="R" />
+    </EventInputs>
+    <EventOutputs>
+      <Event Name="CUO"><With Var="Q" /><With Var="CV" /></Event>
+      <Event Name="RO"><With Var="Q" /><With Var="CV" /></Event>
+    </EventOutputs>
+    <InputVars>
+      <VarDeclaration Name="PV" Type="UINT" InitialValue="2" />
+    </InputVars>
+    <OutputVars>
+      <VarDeclaration Name="Q" Type="BOOL" />
+      <VarDeclaration Name="CV" Type="UINT" />
+    </OutputVars>
+  </InterfaceList>
+  <BasicFB>
+    <ECC>
+      <ECState Name="START" />
+      <ECState Name="CU"><ECAction Algorithm="CU" Output="CUO" /></ECState>
+      <ECState Name="R"><ECAction Algorithm="R" Output="RO" /></ECState>
+      <ECTransition Source="START" Destination="CU" Condition="CU[CV &lt; 65535]" />
+      <ECTransition Source="START" Destination="R" Condition="R" />
+      <ECTransition Source="CU" Destination="START" Condition="1" />
+      <ECTransition Source="R" Destination="START" Condition="1" />
+    </ECC>
+    <Algorithm Name="CU">
+      <ST Text="CV := CV + 1;&#10;Q := (CV &gt;= PV);" />
+    </Algorithm>
+    <Algorithm Name="R">
+      <ST><![CDATA[CV := 0;
+Q := FALSE;]]></ST>
+    </Algorithm>
+  </BasicFB>
+</FBType>
+"#;
+
+    const E_SPLIT3: &str = r#"<FBType Name="E_SPLIT3">
+  <InterfaceList>
+    <EventInputs><Event Name="EI" /></EventInputs>
+    <EventOutputs>
+      <Event Name="EO1" /><Event Name="EO2" /><Event Name="EO3" />
+    </EventOutputs>
+  </InterfaceList>
+  <FBNetwork>
+    <FB Name="s1" Type="E_SPLIT" x="1" y="2" />
+    <FB Name="s2" Type="E_SPLIT" />
+    <FB Name="cycle" Type="E_CYCLE"><Parameter Name="DT" Value="T#100ms" /></FB>
+    <EventConnections>
+      <Connection Source="EI" Destination="s1.EI" />
+      <Connection Source="s1.EO1" Destination="EO1" />
+      <Connection Source="s1.EO2" Destination="s2.EI" dx1="10.0" />
+      <Connection Source="s2.EO1" Destination="EO2" />
+      <Connection Source="s2.EO2" Destination="EO3" />
+    </EventConnections>
+  </FBNetwork>
+</FBType>
+"#;
+
+    fn factory() -> StandardFactory {
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        for xml in [E_SPLIT, E_CTU, E_SPLIT3] {
+            register(&mut factory, &parse(xml).unwrap()).unwrap();
+        }
+        factory
+    }
+
+    fn send(factory: &StandardFactory, r#type: &str, event: &str) -> Vec<String> {
+        let mut fb = factory.create(r#type).unwrap();
+        let mut context = EventContext::default();
+        fb.receive_event(event, &mut context);
+        context.take_fired()
+    }
+
+    #[test]
+    fn parse_basic() {
+        let r#type = parse(E_CTU).unwrap();
+        let basic = r#type.basic.as_ref().unwrap();
+
+        assert_eq!(
+            r#type.interface_list.event_inputs.events[0].with[0].var,
+            "PV"
+        );
+        assert_eq!(basic.ecc.transitions[0].condition, "CU[CV < 65535]");
+        assert_eq!(
+            basic.algorithms[0].st(),
+            Some("CV := CV + 1;\nQ := (CV >= PV);")
+        );
+        assert_eq!(basic.algorithms[1].st(), Some("CV := 0;\nQ := FALSE;"));
+
+        let basic = basic_type(&r#type, basic).unwrap();
+        assert_eq!(
+            basic.interface().data_input("PV").unwrap().initial_value(),
+            IecValue::Uint(2)
+        );
+        assert_eq!(interface_list(basic.interface()), r#type.interface_list);
+    }
+
+    #[test]
+    fn basic() {
+        let factory = factory();
+        assert_eq!(send(&factory, "E_SPLIT", "EI"), vec!["EO1", "EO2"]);
+        assert_eq!(send(&factory, "E_CTU", "R"), vec!["RO"]);
+
+        let mut fb = factory.create("E_CTU").unwrap();
+        let mut context = EventContext::default();
+        let cv = fb.get_data_output("CV").unwrap();
+        let q = fb.get_data_output("Q").unwrap();
+        for _ in 0..2 {
+            fb.receive_event("CU", &mut context);
+        }
+        assert_eq!(context.take_fired(), vec!["CUO", "CUO"]);
+        assert_eq!(cv.get(), IecValue::Uint(2));
+        assert_eq!(q.get(), IecValue::Bool(true));
+
+        fb.receive_event("R", &mut context);
+        assert_eq!(cv.get(), IecValue::Uint(0));
+        assert_eq!(q.get(), IecValue::Bool(false));
+    }
+
+    #[test]
+    fn composite() {
+        let factory = factory();
+        assert_eq!(send(&factory, "E_SPLIT3", "EI"), vec!["EO1", "EO2", "EO3"]);
+    }
+
+    #[test]
+    fn generic() {
+        let r#type = parse(
+            r#"<FBType Name="DOUBLE">
+  <InterfaceList>
+    <EventInputs><Event Name="REQ"><With Var="IN" /></Event></EventInputs>
+    <EventOutputs><Event Name="CNF"><With Var="OUT" /></Event></EventOutputs>
+    <InputVars><VarDeclaration Name="IN" Type="ANY_NUM" /></InputVars>
+    <OutputVars><VarDeclaration Name="OUT" Type="ANY_NUM" /></OutputVars>
+  </InterfaceList>
+  <BasicFB>
+    <ECC>
+      <ECState Name="START" />
+      <ECState Name="REQ"><ECAction Algorithm="REQ" Output="CNF" /></ECState>
+      <ECTransition Source="START" Destination="REQ" Condition="REQ" />
+      <ECTransition Source="REQ" Destination="START" Condition="1" />
+    </ECC>
+    <Algorithm Name="REQ"><ST Text="OUT := IN * 2;" /></Algorithm>
+  </BasicFB>
+</FBType>"#,
+        )
+        .unwrap();
+        let mut factory = factory();
+        register(&mut factory, &r#type).unwrap();
+        assert_eq!(
+            factory
+                .interface("DOUBLE")
+                .map(|interface| interface_list(&interface)),
+            Some(r#type.interface_list)
+        );
+
+        let mut container = SimpleContainer::new(factory);
+        container.add_child("d".into(), "DOUBLE").unwrap();
+        container.add_child("ctu".into(), "E_CTU").unwrap();
+        container
+            .connect("ctu.CV".parse().unwrap(), "d.IN".parse().unwrap())
+            .unwrap();
+        container
+            .connect("d.OUT".parse().unwrap(), "ctu.PV".parse().unwrap())
+            .unwrap();
+
+        container.add_watch("d.OUT".parse().unwrap()).unwrap();
+
+        // the value is converted for the algorithm, and back
+        container.write("5", "d.IN".parse().unwrap()).unwrap();
+        container.send_event("d.REQ".parse().unwrap());
+        container.dispatch();
+        assert_eq!(container.read_watches()[0].ports[0].data.value, "10");
+        assert!(matches!(
+            container.write("5.5", "d.IN".parse().unwrap()),
+            Err(WriteError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        let load = |xml: &str| register(&mut factory(), &parse(xml)?);
+
+        assert!(matches!(
+            load(r#"<FBType Name="X"><InterfaceList/></FBType>"#),
+            Err(LoadError::Unsupported(_))
+        ));
+        assert!(matches!(
+            load(&E_CTU.replace("UINT", "FOO")),
+            Err(LoadError::UnknownType(_))
+        ));
+        assert!(matches!(
+            load(&E_CTU.replace(r#"InitialValue="2""#, r#"InitialValue="TRUE""#)),
+            Err(LoadError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            load(&E_CTU.replace(r#"Type="UINT" "#, r#"Type="UINT" ArraySize="4" "#)),
+            Err(LoadError::Unsupported(_))
+        ));
+        assert!(matches!(
+            load(&E_SPLIT.replace(r#"Output="EO2""#, r#"Output="EO4""#)),
+            Err(LoadError::InvalidType(TypeError::UnknownEvent(_)))
+        ));
+        assert!(matches!(load("<FBType"), Err(LoadError::Xml(_))));
+        assert!(matches!(
+            load(&E_CTU.replace("CV := 0;", "CX := 0;")),
+            Err(LoadError::InvalidCode { name, .. }) if name == "R"
+        ));
+        assert!(matches!(
+            load(&E_CTU.replace("CV &lt; 65535", "CV &lt;")),
+            Err(LoadError::InvalidCode { .. })
+        ));
+
+        // the network is only checked when the type is instantiated
+        let mut factory = factory();
+        register(
+            &mut factory,
+            &parse(&E_SPLIT3.replace("E_CYCLE", "E_FOO")).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            factory.create("E_SPLIT3"),
+            Err(CreationError::InvalidType(_))
+        ));
+    }
+
+    #[test]
+    fn directory() {
+        let path = std::env::temp_dir().join(format!("toref-typelib-{}", std::process::id()));
+        std::fs::create_dir_all(path.join("events")).unwrap();
+        std::fs::write(path.join("events/E_SPLIT.fbt"), E_SPLIT).unwrap();
+        std::fs::write(path.join("E_SPLIT3.fbt"), E_SPLIT3).unwrap();
+        std::fs::write(path.join("BROKEN.fbt"), "<FBType").unwrap();
+        std::fs::write(path.join("README.md"), "Not a type").unwrap();
+
+        let mut factory = StandardFactory::new();
+        factory.register_standard_types();
+        let result = load_directory(&mut factory, &path);
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(result.unwrap(), vec!["E_SPLIT3", "E_SPLIT"]);
+        assert_eq!(send(&factory, "E_SPLIT3", "EI"), vec!["EO1", "EO2", "EO3"]);
+    }
+}